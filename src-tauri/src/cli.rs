@@ -1,6 +1,21 @@
 // filepath: f:\TauriProjects\ZipHere\src-tauri\src\cli.rs
-use crate::comde::{CompressionFormat, CompressionOptions, Compressor, DecompressionOptions};
+use crate::comde::z_zstd::ZstdCompressor;
+use crate::comde::z_brotli::{BrotliCompressor, TreeReport};
+use crate::comde::z_gzip::GzipCompressor;
+use crate::comde::z_xz::XzCompressor;
+use crate::comde::bench::{self, BenchGoal};
+use crate::comde::conflict::{self, ConflictAnswer, ConflictPolicy, ConflictResolver};
+use crate::comde::limits::{self, ExtractLimits};
+use crate::comde::posix::{IdMapping, TarMetadata};
+use crate::comde::preflight::{self, SpaceCheck};
+use crate::comde::sanitize::{self, RenameLog};
+use crate::comde::smart;
+use crate::comde::{
+    CompressSettings, CompressionFormat, CompressionOptions, CompressionPreset, Compressor,
+    DecompressionOptions, ExtractSettings, TarFormat, XzCheck, XzFilter, ZipNameEncoding,
+};
 use clap::{Args, Parser, Subcommand};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
@@ -27,6 +42,14 @@ pub enum Commands {
     /// 查看压缩文件内容（文件查看器模式）
     #[command(name = "view")]
     View(ViewArgs),
+
+    /// 从样本文件训练ZSTD字典
+    #[command(name = "train-dict")]
+    TrainDict(TrainDictArgs),
+
+    /// 采样输入并试压缩，比较各格式的压缩率、速度和内存并给出推荐
+    #[command(name = "bench")]
+    Bench(BenchArgs),
 }
 
 #[derive(Args)]
@@ -39,17 +62,166 @@ pub struct CompressArgs {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// 压缩格式，可选: zip, tar, gz, bz2, xz, zst, 7z
-    #[arg(short, long, default_value = "zip")]
+    // 格式、级别和密码的帮助文本由各压缩器声明的能力生成
+    #[arg(short, long, default_value = "zip", help = format_help())]
     pub format: String,
 
-    /// 压缩级别 (1-9，对于zstd是1-22)
-    #[arg(short, long)]
-    pub level: Option<u32>,
+    /// 压缩预设，可选: store, fastest, fast, normal, high, ultra (默认normal，--level会覆盖预设的级别)
+    #[arg(long)]
+    pub preset: Option<String>,
 
-    /// 用于加密的密码 (仅支持zip和7z格式)
-    #[arg(short, long)]
+    #[arg(short, long, allow_negative_numbers = true, help = level_help())]
+    pub level: Option<i32>,
+
+    #[arg(short, long, help = password_help())]
     pub password: Option<String>,
+
+    /// ZSTD字典文件路径 (仅支持zst格式)
+    #[arg(long)]
+    pub dict: Option<PathBuf>,
+
+    /// 启用ZSTD长距离匹配 (仅支持zst格式)
+    #[arg(long)]
+    pub long: bool,
+
+    /// ZSTD窗口大小的以2为底的对数，如27表示128MB (仅支持zst格式)
+    #[arg(long)]
+    pub window_log: Option<u32>,
+
+    /// 写入内容校验和 (仅支持zst和lz4格式)
+    #[arg(long)]
+    pub checksum: bool,
+
+    /// 使用XZ极限预设，更慢但压缩率略高 (仅支持xz格式)
+    #[arg(short, long)]
+    pub extreme: bool,
+
+    /// XZ过滤器，逗号分隔，可选: x86, arm, armthumb, powerpc, ia64, sparc, delta[:距离] (仅支持xz格式，不支持arm64)
+    #[arg(long, value_delimiter = ',')]
+    pub filters: Vec<String>,
+
+    /// XZ字典大小，单位为字节 (仅支持xz格式)
+    #[arg(long)]
+    pub dict_size: Option<u32>,
+
+    /// XZ完整性校验类型，可选: none, crc32, crc64, sha256 (仅支持xz格式)
+    #[arg(long, default_value = "crc64")]
+    pub check: String,
+    /// 写入GZIP头部的注释 (仅支持gz格式)
+    #[arg(long)]
+    pub comment: Option<String>,
+
+    /// BROTLI窗口大小的以2为底的对数，范围10-24，默认由预设决定 (仅支持br格式)
+    #[arg(long)]
+    pub window_size: Option<u32>,
+
+    /// 批量模式：为输入目录树中的每个文件生成同级的.br文件 (仅支持br格式)
+    #[arg(long)]
+    pub siblings: bool,
+
+    /// 批量模式中.br文件已存在时的处理方式，可选: overwrite, skip, rename, keep-newer, ask (默认跳过已是最新的文件)
+    #[arg(long, default_value = "keep-newer")]
+    pub conflict: String,
+
+    /// 跳过压缩前的磁盘空间检查
+    #[arg(long)]
+    pub no_space_check: bool,
+
+    /// 生成可复现的归档: 条目按名称排序，所有者清零，权限统一为0755/0644，修改时间不晚于环境变量SOURCE_DATE_EPOCH (未设置时统一为1980-01-01) (仅支持zip、gz和tar系列格式)
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// TAR头部格式，可选: pax, gnu, ustar (仅支持tar系列格式)
+    #[arg(long, default_value = "pax")]
+    pub tar_format: String,
+
+    #[command(flatten)]
+    pub tar: TarArgs,
+}
+
+// TAR系列格式的POSIX元数据选项，压缩和解压共用
+#[derive(Args)]
+pub struct TarArgs {
+    /// 只使用数字ID: 压缩时不记录用户名和组名，解压时不按名称查找本机用户 (仅支持tar系列格式)
+    #[arg(long)]
+    pub numeric_owner: bool,
+
+    /// 用户映射，如 1000:0、alice:root，可多次指定或用逗号分隔 (仅支持tar系列格式)
+    #[arg(long, value_delimiter = ',', value_parser = parse_id_mapping)]
+    pub owner_map: Vec<IdMapping>,
+
+    /// 组映射，格式同 --owner-map (仅支持tar系列格式)
+    #[arg(long, value_delimiter = ',', value_parser = parse_id_mapping)]
+    pub group_map: Vec<IdMapping>,
+
+    /// 保存/恢复扩展属性 (仅支持tar系列格式)
+    #[arg(long)]
+    pub xattrs: bool,
+
+    /// 保存/恢复POSIX ACL (仅支持tar系列格式，仅Linux)
+    #[arg(long)]
+    pub acls: bool,
+}
+
+impl TarArgs {
+    fn metadata(&self) -> TarMetadata {
+        TarMetadata {
+            numeric_owner: self.numeric_owner,
+            owner_map: self.owner_map.clone(),
+            group_map: self.group_map.clone(),
+            xattrs: self.xattrs,
+            acls: self.acls,
+            ..TarMetadata::default()
+        }
+    }
+}
+
+fn parse_id_mapping(value: &str) -> Result<IdMapping, String> {
+    IdMapping::parse(value).ok_or_else(|| format!("无效的映射，应为 来源:目标 形式: {}", value))
+}
+
+// 可用于压缩的格式列表，只列出内置格式，显示帮助时不启动插件
+fn format_help() -> String {
+    let formats: Vec<&str> = Compressor::builtin_capabilities()
+        .into_iter()
+        .filter(|(_, capabilities)| capabilities.can_compress)
+        .map(|(format, _)| format.extension())
+        .collect();
+    format!("压缩格式，可选: {}，以及插件提供的格式", formats.join(", "))
+}
+
+// 各格式的压缩级别范围及默认值
+fn level_help() -> String {
+    let ranges: Vec<String> = Compressor::builtin_capabilities()
+        .into_iter()
+        .filter(|(_, capabilities)| capabilities.can_compress)
+        .filter_map(|(format, capabilities)| {
+            capabilities.level.map(|level| {
+                format!(
+                    "{} {}~{} (默认{})",
+                    format.extension(),
+                    level.min,
+                    level.max,
+                    level.default
+                )
+            })
+        })
+        .collect();
+    format!("压缩级别，各格式范围: {}", ranges.join(", "))
+}
+
+// 支持密码的格式
+fn password_help() -> String {
+    let formats: Vec<&str> = Compressor::builtin_capabilities()
+        .into_iter()
+        .filter(|(_, capabilities)| capabilities.can_compress && capabilities.supports_password)
+        .map(|(format, _)| format.extension())
+        .collect();
+    if formats.is_empty() {
+        "用于加密的密码 (当前没有格式支持密码保护)".to_string()
+    } else {
+        format!("用于加密的密码 (仅支持{}格式)", formats.join("、"))
+    }
 }
 
 #[derive(Args)]
@@ -65,6 +237,80 @@ pub struct DecompressArgs {
     /// 用于解密的密码 (仅在需要时使用)
     #[arg(short, long)]
     pub password: Option<String>,
+
+    /// ZSTD字典文件路径 (解压使用字典压缩的zst文件时需要)
+    #[arg(long)]
+    pub dict: Option<PathBuf>,
+
+    /// 允许的最大ZSTD窗口大小的对数 (解压使用 --long=31 等大窗口压缩的文件时需要)
+    #[arg(long)]
+    pub window_log_max: Option<u32>,
+    /// ZIP中未标记UTF-8的文件名所用的编码，可选: auto, utf8, gbk, shift-jis, cp437 (默认自动识别)
+    #[arg(long, default_value = "auto")]
+    pub encoding: String,
+
+    /// 使用GZIP头部记录的原始文件名并恢复修改时间 (仅支持gz格式)
+    #[arg(short = 'N', long)]
+    pub name: bool,
+
+    /// 目标文件已存在时的处理方式，可选: overwrite, skip, rename, keep-newer, ask
+    #[arg(long, default_value = "overwrite")]
+    pub conflict: String,
+
+    /// 智能解压: 归档顶层只有一个条目时直接解压，否则放入以归档名命名的文件夹 (默认解压到归档所在目录)
+    #[arg(long)]
+    pub smart: bool,
+
+    /// 先解压到输出目录中的暂存目录，全部成功后再移入，失败时不留下解压了一半的文件
+    #[arg(long)]
+    pub staged: bool,
+
+    /// 断点续解: 在输出目录中记录已完成的条目，中断后重新执行同一命令时从未完成的条目继续 (支持zip和tar系列格式)
+    #[arg(long)]
+    pub resume: bool,
+
+    /// 跳过解压前的磁盘空间检查
+    #[arg(long)]
+    pub no_space_check: bool,
+
+    /// 恢复归档中记录的文件所有者，通常需要root权限 (仅支持tar系列格式)
+    #[arg(long)]
+    pub same_owner: bool,
+
+    /// 保留setuid、setgid和粘滞位，默认只恢复读写执行权限 (仅支持tar系列格式)
+    #[arg(long)]
+    pub same_permissions: bool,
+
+    /// 不恢复修改时间，使用解压时的时间 (仅支持tar系列格式)
+    #[arg(long)]
+    pub no_mtime: bool,
+
+    #[command(flatten)]
+    pub tar: TarArgs,
+
+    /// 按Windows和macOS的文件名规则清理条目名 (默认只按本系统的规则)，适合解压到会被其他系统读取的位置
+    #[arg(long)]
+    pub portable_names: bool,
+
+    /// 单个归档解压出的最大总大小，如 500M、10G (0表示不限制，默认不限制)
+    #[arg(long, value_parser = parse_size)]
+    pub max_size: Option<u64>,
+
+    /// 单个条目解压后的最大大小，如 2G (0表示不限制，默认不限制)
+    #[arg(long, value_parser = parse_size)]
+    pub max_entry_size: Option<u64>,
+
+    /// 单个归档中的最大条目数 (0表示不限制，默认1000000)
+    #[arg(long)]
+    pub max_entries: Option<u64>,
+
+    /// 条目路径的最大目录嵌套层数 (0表示不限制，默认256)
+    #[arg(long)]
+    pub max_depth: Option<u32>,
+
+    /// 解压总大小与压缩文件大小的最大比值，用于识别解压炸弹 (0表示不限制，默认1000)
+    #[arg(long)]
+    pub max_ratio: Option<u64>,
 }
 
 #[derive(Args)]
@@ -77,7 +323,7 @@ pub struct ConfigArgs {
     #[arg(long)]
     pub remove_association: bool,
 
-    /// 要关联的文件格式，可选: all, zip, tar, gz, bz2, xz, zst, 7z
+    /// 要关联的文件格式，可选: all, zip, tar, gz, bz2, xz, zst, 7z, lz4, br, lzma, lz
     #[arg(long, default_value = "all")]
     pub formats: String,
 
@@ -103,6 +349,52 @@ pub struct ViewArgs {
     /// 要查看的压缩文件路径
     #[arg(required = true)]
     pub input: PathBuf,
+
+    /// 只解压指定的条目 (可多次指定，选择目录时包含其全部内容)
+    #[arg(short = 'x', long)]
+    pub extract: Vec<String>,
+
+    /// 选择性解压的目标目录
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// 目标文件已存在时的处理方式，可选: overwrite, skip, rename, keep-newer, ask
+    #[arg(long, default_value = "overwrite")]
+    pub conflict: String,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// 要分析的文件或目录的路径
+    #[arg(required = true)]
+    pub input: Vec<PathBuf>,
+
+    /// 推荐目标，可选: smallest, fastest, balanced
+    #[arg(short, long, default_value = "balanced")]
+    pub goal: String,
+
+    /// 参与比较的格式，逗号分隔，默认比较所有可压缩的格式
+    #[arg(short, long, value_delimiter = ',')]
+    pub formats: Vec<String>,
+
+    /// 采样大小，单位为MB
+    #[arg(short, long, default_value_t = 16)]
+    pub sample_size: u64,
+}
+
+#[derive(Args)]
+pub struct TrainDictArgs {
+    /// 样本文件或目录的路径
+    #[arg(required = true)]
+    pub input: Vec<PathBuf>,
+
+    /// 输出字典文件路径
+    #[arg(short, long, default_value = "dictionary")]
+    pub output: PathBuf,
+
+    /// 字典最大字节数
+    #[arg(short, long, default_value_t = 112640)]
+    pub max_size: usize,
 }
 
 /// 处理CLI命令执行
@@ -133,6 +425,14 @@ pub async fn handle_cli() -> anyhow::Result<bool> {
             view_command(args).await?;
             Ok(true)
         },
+        Commands::TrainDict(args) => {
+            train_dict_command(args)?;
+            Ok(true)
+        },
+        Commands::Bench(args) => {
+            bench_command(args).await?;
+            Ok(true)
+        },
     }
 }
 
@@ -144,6 +444,11 @@ async fn compress_command(args: CompressArgs) -> anyhow::Result<()> {
         }
     }
 
+    // BROTLI批量模式，直接在输入文件旁生成.br文件
+    if args.siblings {
+        return brotli_siblings_command(&args);
+    }
+
     // 确定输出路径
     let output_path = if let Some(path) = args.output {
         path
@@ -161,6 +466,14 @@ async fn compress_command(args: CompressArgs) -> anyhow::Result<()> {
         output
     };
 
+    // 写入任何数据前检查磁盘空间
+    if !args.no_space_check {
+        let inputs: Vec<&str> = args.input.iter().filter_map(|p| p.to_str()).collect();
+        let check = preflight::check_compress(&inputs, &output_path)
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        report_space_check(&check)?;
+    }
+
     // 创建输出目录
     if let Some(parent) = output_path.parent() {
         if !parent.exists() {
@@ -174,34 +487,87 @@ async fn compress_command(args: CompressArgs) -> anyhow::Result<()> {
 
     // 创建压缩器
     let compressor = Compressor::new(format);
-
-    // 创建压缩选项
-    let options = match format {
-        CompressionFormat::Zip => Some(CompressionOptions::Zip {
-            level: args.level.unwrap_or(6),
-            password: args.password,
-        }),
-        CompressionFormat::Tar => Some(CompressionOptions::Tar {
-            level: args.level.unwrap_or(6),
-        }),
-        CompressionFormat::Gzip => Some(CompressionOptions::Gzip {
-            level: args.level.unwrap_or(6),
-        }),
-        CompressionFormat::Bzip2 => Some(CompressionOptions::Bzip2 {
-            level: args.level.unwrap_or(6),
-        }),
-        CompressionFormat::Xz => Some(CompressionOptions::Xz {
-            level: args.level.unwrap_or(6),
-        }),
-        CompressionFormat::Zstd => Some(CompressionOptions::Zstd {
-            level: args.level.unwrap_or(3),
-        }),
-        CompressionFormat::SevenZip => Some(CompressionOptions::SevenZip {
-            level: args.level.unwrap_or(6),
-            password: args.password,
-        }),
+    let settings = CompressSettings {
+        tar_metadata: args.tar.metadata(),
+        reproducible: args.reproducible,
+        tar_format: TarFormat::from_name(&args.tar_format)
+            .ok_or_else(|| anyhow::anyhow!("不支持的tar格式: {}", args.tar_format))?,
     };
 
+    // 检查格式能否满足请求
+    let capabilities = compressor.capabilities();
+    if !capabilities.can_compress {
+        return Err(anyhow::anyhow!("{} 为只读格式，不支持压缩", format.name()));
+    }
+    if args.password.is_some() && !capabilities.supports_password {
+        return Err(anyhow::anyhow!("{} 格式不支持密码保护", format.name()));
+    }
+    if args.input.len() > 1 && !capabilities.multi_input {
+        return Err(anyhow::anyhow!("{} 格式只能压缩单个输入", format.name()));
+    }
+
+    // 从预设得到该格式的原生选项，再应用命令行中显式指定的参数
+    let preset = parse_preset(&args.preset)?;
+    let mut options = compressor
+        .preset_options(preset)
+        .ok_or_else(|| anyhow::anyhow!("{} 为只读格式，不支持压缩", format.name()))?;
+
+    if let Some(level) = args.level {
+        options.set_level(level);
+    }
+    options.set_settings(settings);
+
+    match &mut options {
+        CompressionOptions::Zip { password, .. }
+        | CompressionOptions::SevenZip { password, .. }
+        | CompressionOptions::Plugin { password, .. } => {
+            *password = args.password.clone();
+        }
+        CompressionOptions::Gzip { comment, .. } => {
+            *comment = args.comment.clone();
+        }
+        CompressionOptions::Xz {
+            extreme,
+            filters,
+            dict_size,
+            check,
+            ..
+        } => {
+            *extreme |= args.extreme;
+            *filters = args
+                .filters
+                .iter()
+                .map(|name| XzFilter::parse(name).map_err(anyhow::Error::msg))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            *dict_size = args.dict_size.or(*dict_size);
+            *check = XzCheck::from_name(&args.check)
+                .ok_or_else(|| anyhow::anyhow!("不支持的XZ校验类型: {}", args.check))?;
+        }
+        CompressionOptions::Zstd {
+            dictionary,
+            long_distance_matching,
+            window_log,
+            checksum,
+            ..
+        } => {
+            *dictionary = args.dict.as_ref().map(|p| p.to_string_lossy().to_string());
+            *long_distance_matching |= args.long;
+            *window_log = args.window_log.or(*window_log);
+            *checksum |= args.checksum;
+        }
+        CompressionOptions::Lz4 { checksum, .. } => {
+            *checksum |= args.checksum;
+        }
+        CompressionOptions::Brotli { window_size, .. } => {
+            *window_size = args.window_size.unwrap_or(*window_size);
+        }
+        CompressionOptions::Tar { .. }
+        | CompressionOptions::Bzip2 { .. }
+        | CompressionOptions::Lzma { .. }
+        | CompressionOptions::Lzip { .. } => {}
+    }
+    let options = Some(options);
+
     // 转换路径为字符串
     let input_paths: Vec<&str> = args.input.iter().filter_map(|p| p.to_str()).collect();
 
@@ -225,6 +591,162 @@ async fn compress_command(args: CompressArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+// 处理磁盘空间预检的结果: 确定放不下时拒绝执行，可能放不下时给出警告
+fn report_space_check(check: &SpaceCheck) -> anyhow::Result<()> {
+    if check.must_refuse() {
+        return Err(anyhow::anyhow!(
+            "{}，可使用 --no-space-check 跳过检查",
+            check.message()
+        ));
+    }
+    if !check.is_sufficient() {
+        eprintln!("警告: {}", check.message());
+    }
+    Ok(())
+}
+
+// 解析带单位的大小 (K/M/G/T，按1024进位)
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("无效的大小: {}", value))?;
+    let shift = match unit.trim().to_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("无效的大小单位: {}", value)),
+    };
+    number
+        .checked_mul(1u64 << shift)
+        .ok_or_else(|| format!("大小超出范围: {}", value))
+}
+
+// 按命令行参数得到解压限制，未指定的保持默认值，0表示不限制
+fn parse_limits(args: &DecompressArgs) -> ExtractLimits {
+    fn pick<T: Copy + Default + PartialEq>(value: Option<T>, default: Option<T>) -> Option<T> {
+        match value {
+            Some(value) if value == T::default() => None,
+            Some(value) => Some(value),
+            None => default,
+        }
+    }
+
+    let defaults = ExtractLimits::default();
+    ExtractLimits {
+        max_total_size: pick(args.max_size, defaults.max_total_size),
+        max_entry_size: pick(args.max_entry_size, defaults.max_entry_size),
+        max_entries: pick(args.max_entries, defaults.max_entries),
+        max_depth: pick(args.max_depth, defaults.max_depth),
+        max_ratio: pick(args.max_ratio, defaults.max_ratio),
+    }
+}
+
+// 解压错误，触及解压限制时提示如何调整
+fn decompress_error(e: String) -> anyhow::Error {
+    if limits::is_limit_error(&e) {
+        anyhow::anyhow!(
+            "{}，已删除本次解压出的文件；可使用 --max-size、--max-ratio 等参数调整限制",
+            e
+        )
+    } else {
+        anyhow::anyhow!(e)
+    }
+}
+
+// 解析压缩预设，未指定时使用normal
+fn parse_preset(name: &Option<String>) -> anyhow::Result<CompressionPreset> {
+    match name {
+        Some(name) => CompressionPreset::from_name(name)
+            .ok_or_else(|| anyhow::anyhow!("不支持的压缩预设: {}", name)),
+        None => Ok(CompressionPreset::Normal),
+    }
+}
+
+// 解析冲突处理方式，询问模式下从标准输入读取用户的选择
+fn parse_conflict(name: &str) -> anyhow::Result<ConflictPolicy> {
+    let policy = ConflictPolicy::from_name(name)
+        .ok_or_else(|| anyhow::anyhow!("不支持的冲突处理方式: {}", name))?;
+
+    if policy == ConflictPolicy::Ask {
+        conflict::set_ask_handler(Box::new(|info| loop {
+            println!("文件已存在: {}", info.path);
+            print!("[o]覆盖 [s]跳过 [r]重命名 [n]保留较新 (大写表示之后全部应用): ");
+            std::io::stdout().flush().map_err(|e| e.to_string())?;
+
+            let mut line = String::new();
+            if std::io::stdin().read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("已取消解压".to_string());
+            }
+            let choice = match line.trim().to_lowercase().as_str() {
+                "o" => ConflictPolicy::Overwrite,
+                "s" => ConflictPolicy::Skip,
+                "r" => ConflictPolicy::Rename,
+                "n" => ConflictPolicy::KeepNewer,
+                _ => continue,
+            };
+            return Ok(ConflictAnswer {
+                choice,
+                apply_to_all: line.trim().chars().all(|c| c.is_uppercase()),
+            });
+        }));
+    }
+
+    Ok(policy)
+}
+
+fn brotli_siblings_command(args: &CompressArgs) -> anyhow::Result<()> {
+    if CompressionFormat::from_extension(&args.format) != Some(CompressionFormat::Brotli) {
+        return Err(anyhow::anyhow!("批量模式仅支持br格式"));
+    }
+
+    let compressor = BrotliCompressor::new();
+    let preset = parse_preset(&args.preset)?;
+    let Some(CompressionOptions::Brotli {
+        quality,
+        window_size,
+        ..
+    }) = Compressor::new(CompressionFormat::Brotli).preset_options(preset)
+    else {
+        return Err(anyhow::anyhow!("BROTLI不支持该预设"));
+    };
+    let quality = args.level.map(|level| level.max(0) as u32).unwrap_or(quality);
+    let window_size = args.window_size.unwrap_or(window_size);
+
+    let mut conflict = ConflictResolver::new(parse_conflict(&args.conflict)?);
+    let mut report = TreeReport::default();
+    for path in &args.input {
+        if !path.is_dir() {
+            return Err(anyhow::anyhow!("批量模式的输入必须是目录: {}", path.display()));
+        }
+
+        let tree = compressor
+            .compress_tree(path, quality, window_size, &mut conflict)
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        report.compressed += tree.compressed;
+        report.skipped += tree.skipped;
+        report.failed.extend(tree.failed);
+    }
+
+    println!(
+        "压缩完成: 已生成 {} 个.br文件，跳过 {} 个",
+        report.compressed, report.skipped
+    );
+    if !report.failed.is_empty() {
+        for (path, e) in &report.failed {
+            eprintln!("  {}: {}", path.display(), e);
+        }
+        return Err(anyhow::anyhow!("{} 个文件压缩失败", report.failed.len()));
+    }
+    Ok(())
+}
+
 async fn decompress_command(args: DecompressArgs) -> anyhow::Result<()> {
     // 验证输入路径
     for path in &args.input {
@@ -233,8 +755,13 @@ async fn decompress_command(args: DecompressArgs) -> anyhow::Result<()> {
         }
     }
 
+    // 断点续解依赖固定的输出目录，不能与暂存或智能解压同时使用
+    if args.resume && (args.smart || args.staged) {
+        return Err(anyhow::anyhow!("--resume 不能与 --smart 或 --staged 同时使用"));
+    }
+
     // 确定输出路径
-    let output_path = if let Some(path) = args.output {
+    let output_path = if let Some(path) = args.output.clone() {
         path
     } else if args.input.len() == 1 {
         // 如果只有一个输入，使用其名称作为目录
@@ -249,11 +776,6 @@ async fn decompress_command(args: DecompressArgs) -> anyhow::Result<()> {
         PathBuf::from("extracted")
     };
 
-    // 创建输出目录
-    if !output_path.exists() {
-        std::fs::create_dir_all(&output_path)?;
-    }
-
     // 转换路径为字符串
     let input_paths: Vec<&str> = args.input.iter().filter_map(|p| p.to_str()).collect();
 
@@ -263,82 +785,361 @@ async fn decompress_command(args: DecompressArgs) -> anyhow::Result<()> {
 
     // 自动检测格式
     let first_path = Path::new(input_paths[0]);
-    let format = if let Some(ext) = first_path.extension() {
-        if let Some(ext_str) = ext.to_str() {
-            CompressionFormat::from_extension(ext_str)
-                .ok_or_else(|| anyhow::anyhow!("无法从文件扩展名识别格式: {}", ext_str))?
-        } else {
-            return Err(anyhow::anyhow!("无效的文件扩展名"));
-        }
-    } else {
-        return Err(anyhow::anyhow!("文件没有扩展名，无法确定格式"));
-    };
+    let format = CompressionFormat::detect(first_path)
+        .ok_or_else(|| anyhow::anyhow!("无法识别文件格式: {}", first_path.display()))?;
+
+    // 写入任何数据前检查磁盘空间，智能解压默认解压到归档所在目录
+    if !args.no_space_check {
+        let target_dir = match &args.output {
+            None if args.smart => first_path.parent().unwrap_or(Path::new(".")),
+            _ => output_path.as_path(),
+        };
+        let check = preflight::check_decompress(format, &input_paths, target_dir)
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        report_space_check(&check)?;
+    }
+
+    // 创建输出目录，智能解压时由每个归档分别决定
+    if !args.smart && !output_path.exists() {
+        std::fs::create_dir_all(&output_path)?;
+    }
 
     // 创建解压器
     let decompressor = Compressor::new(format);
+    let conflict = parse_conflict(&args.conflict)?;
+    let renames = RenameLog::default();
+    let settings = ExtractSettings {
+        limits: parse_limits(&args),
+        portable_names: args.portable_names,
+        tar_metadata: TarMetadata {
+            same_owner: args.same_owner,
+            preserve_permissions: args.same_permissions,
+            preserve_mtime: !args.no_mtime,
+            ..args.tar.metadata()
+        },
+        renames: renames.clone(),
+    };
 
     // 创建解压选项
     let options = match format {
         CompressionFormat::Zip => Some(DecompressionOptions::Zip {
             password: args.password,
+            encoding: ZipNameEncoding::from_name(&args.encoding)
+                .ok_or_else(|| anyhow::anyhow!("不支持的文件名编码: {}", args.encoding))?,
+            conflict,
+            settings,
+        }),
+        CompressionFormat::Tar => Some(DecompressionOptions::Tar { conflict, settings }),
+        CompressionFormat::Gzip | CompressionFormat::TarGz => Some(DecompressionOptions::Gzip {
+            restore_name: args.name,
+            conflict,
+            settings,
+        }),
+        CompressionFormat::Bzip2 | CompressionFormat::TarBz2 => Some(DecompressionOptions::Bzip2 { conflict, settings }),
+        CompressionFormat::Xz | CompressionFormat::TarXz => Some(DecompressionOptions::Xz { conflict, settings }),
+        CompressionFormat::Zstd | CompressionFormat::TarZst => Some(DecompressionOptions::Zstd {
+            dictionary: args.dict.map(|p| p.to_string_lossy().to_string()),
+            window_log_max: args.window_log_max,
+            conflict,
+            settings,
         }),
-        CompressionFormat::Tar => Some(DecompressionOptions::Tar {}),
-        CompressionFormat::Gzip => Some(DecompressionOptions::Gzip {}),
-        CompressionFormat::Bzip2 => Some(DecompressionOptions::Bzip2 {}),
-        CompressionFormat::Xz => Some(DecompressionOptions::Xz {}),
-        CompressionFormat::Zstd => Some(DecompressionOptions::Zstd {}),
         CompressionFormat::SevenZip => Some(DecompressionOptions::SevenZip {
             password: args.password,
+            conflict,
+            settings,
+        }),
+        CompressionFormat::Lz4 | CompressionFormat::TarLz4 => Some(DecompressionOptions::Lz4 { conflict, settings }),
+        CompressionFormat::Brotli => Some(DecompressionOptions::Brotli { conflict, settings }),
+        CompressionFormat::Lzma | CompressionFormat::TarLzma => Some(DecompressionOptions::Lzma { conflict, settings }),
+        CompressionFormat::Lzip | CompressionFormat::TarLzip => Some(DecompressionOptions::Lzip { conflict, settings }),
+        CompressionFormat::Cpio
+        | CompressionFormat::Ar
+        | CompressionFormat::Deb
+        | CompressionFormat::Iso => Some(DecompressionOptions::Archive { conflict, settings }),
+        CompressionFormat::Plugin(_) => Some(DecompressionOptions::Plugin {
+            password: args.password,
+            conflict,
+            settings,
         }),
     };
 
+    // 显示XZ文件记录的校验类型
+    if format == CompressionFormat::Xz {
+        for input_path in &input_paths {
+            let check = XzCompressor::new()
+                .read_check(input_path)
+                .map_err(|e: String| anyhow::anyhow!(e))?;
+            println!("{} 校验类型: {}", input_path, check.name());
+        }
+    }
+
+    // 智能解压: 逐个归档检查顶层条目，决定直接解压还是包一层文件夹
+    if args.smart {
+        for input_path in input_paths {
+            let base_dir = match &args.output {
+                Some(path) => path.clone(),
+                None => Path::new(input_path)
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(".")),
+            };
+            let target = smart::smart_extract(
+                &decompressor,
+                format,
+                input_path,
+                &base_dir,
+                options.clone(),
+            )
+            .await
+            .map_err(decompress_error)?;
+            println!("解压完成: {}", target.display());
+            report_renames(&renames, &target)?;
+        }
+        return Ok(());
+    }
+
     // 执行解压
-    decompressor
-        .decompress(
-            input_paths,
-            output_path
-                .to_str()
-                .ok_or_else(|| anyhow::anyhow!("无法处理输出路径"))?,
-            options,
-        )
-        .await
-        .map_err(|e: String| anyhow::anyhow!(e))?;
+    let output_str = output_path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("无法处理输出路径"))?;
+    let result = if args.resume {
+        decompressor
+            .decompress_resumable(input_paths, output_str, options)
+            .await
+    } else if args.staged {
+        decompressor
+            .decompress_staged(input_paths, output_str, options)
+            .await
+    } else {
+        decompressor
+            .decompress(input_paths, output_str, options)
+            .await
+    };
+    // 中途失败时同样写出已发生的重命名
+    report_renames(&renames, &output_path)?;
+    result.map_err(decompress_error)?;
 
     println!("解压完成: {}", output_path.display());
     Ok(())
 }
 
+// 本次解压重命名了条目时在输出目录旁写报告并提示查看
+fn report_renames(renames: &RenameLog, dir: &Path) -> anyhow::Result<()> {
+    if let Some(report) = sanitize::write_report(dir, &renames.take()).map_err(|e: String| anyhow::anyhow!(e))? {
+        println!("部分条目名称在此系统上无效或相互冲突，已重命名，详见: {}", report.display());
+    }
+    Ok(())
+}
+
 async fn view_command(args: ViewArgs) -> anyhow::Result<()> {
     // 验证输入文件是否存在
     if !args.input.exists() {
         return Err(anyhow::anyhow!("输入文件不存在: {}", args.input.display()));
     }
     
-    // 获取文件扩展名
-    let format = if let Some(ext) = args.input.extension() {
-        if let Some(ext_str) = ext.to_str() {
-            CompressionFormat::from_extension(ext_str)
-                .ok_or_else(|| anyhow::anyhow!("无法从文件扩展名识别格式: {}", ext_str))?
-        } else {
-            return Err(anyhow::anyhow!("无效的文件扩展名"));
-        }
-    } else {
-        return Err(anyhow::anyhow!("文件没有扩展名，无法确定格式"));
-    };
-    
-    println!("TODO: 文件查看器模式尚未实现");
-    println!("将打开文件: {}", args.input.display());
+    // 识别文件格式
+    let format = CompressionFormat::detect(&args.input)
+        .ok_or_else(|| anyhow::anyhow!("无法识别文件格式: {}", args.input.display()))?;
+    let input_path = args
+        .input
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("无法处理输入路径"))?;
+    let reader = Compressor::new(format);
+
+    // 选择性解压指定的条目
+    if !args.extract.is_empty() {
+        let output_path = args.output.unwrap_or_else(|| PathBuf::from("."));
+        let mut conflict = ConflictResolver::new(parse_conflict(&args.conflict)?);
+        reader
+            .extract(
+                input_path,
+                output_path
+                    .to_str()
+                    .ok_or_else(|| anyhow::anyhow!("无法处理输出路径"))?,
+                &args.extract,
+                &mut conflict,
+            )
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        println!("已解压到: {}", output_path.display());
+        return Ok(());
+    }
+
+    println!("文件: {}", args.input.display());
     println!("文件格式: {}", format.name());
+
+    // 列出归档中的条目
+    if let Ok(entries) = reader.list(input_path) {
+        for entry in &entries {
+            if entry.is_dir {
+                println!("{:>12}  {}/", "<DIR>", entry.path);
+            } else {
+                println!("{:>12}  {}", entry.size, entry.path);
+            }
+        }
+        println!("共 {} 个条目", entries.len());
+    }
+
+    if format == CompressionFormat::Xz {
+        let check = XzCompressor::new()
+            .read_check(&args.input.to_string_lossy())
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        println!("校验类型: {}", check.name());
+    }
+
+    if format == CompressionFormat::Gzip {
+        let header = GzipCompressor::new()
+            .read_header(&args.input.to_string_lossy())
+            .map_err(|e: String| anyhow::anyhow!(e))?;
+        println!(
+            "原始文件名: {}",
+            header.filename.as_deref().unwrap_or("(未记录)")
+        );
+        let mtime = chrono::DateTime::from_timestamp(header.mtime as i64, 0)
+            .filter(|_| header.mtime != 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "(未记录)".to_string());
+        println!("修改时间: {}", mtime);
+        if let Some(comment) = &header.comment {
+            println!("注释: {}", comment);
+        }
+    }
     
-    // 这里我们将来会实现实际的文件查看器逻辑
-    // 可能包括：
-    // 1. 列出压缩文件中的条目
-    // 2. 提供预览选定条目的功能
-    // 3. 提供从压缩文件中提取单个条目的功能
+    // 这里我们将来会实现预览选定条目的功能
     
     Ok(())
 }
 
+fn train_dict_command(args: TrainDictArgs) -> anyhow::Result<()> {
+    // 验证输入路径
+    for path in &args.input {
+        if !path.exists() {
+            return Err(anyhow::anyhow!("输入路径不存在: {}", path.display()));
+        }
+    }
+
+    // 转换路径为字符串
+    let input_paths: Vec<&str> = args.input.iter().filter_map(|p| p.to_str()).collect();
+
+    if input_paths.is_empty() {
+        return Err(anyhow::anyhow!("无法处理输入路径"));
+    }
+
+    // 训练字典
+    let dict_id = ZstdCompressor::new()
+        .train_dictionary(
+            input_paths,
+            args.output
+                .to_str()
+                .ok_or_else(|| anyhow::anyhow!("无法处理输出路径"))?,
+            args.max_size,
+        )
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+
+    println!("字典训练完成: {} (字典ID: {})", args.output.display(), dict_id);
+    Ok(())
+}
+
+async fn bench_command(args: BenchArgs) -> anyhow::Result<()> {
+    // 验证输入路径
+    for path in &args.input {
+        if !path.exists() {
+            return Err(anyhow::anyhow!("输入路径不存在: {}", path.display()));
+        }
+    }
+
+    let goal = BenchGoal::from_name(&args.goal)
+        .ok_or_else(|| anyhow::anyhow!("不支持的推荐目标: {}", args.goal))?;
+
+    let formats = if args.formats.is_empty() {
+        CompressionFormat::all_formats()
+    } else {
+        args.formats
+            .iter()
+            .map(|name| {
+                CompressionFormat::from_extension(name.trim())
+                    .ok_or_else(|| anyhow::anyhow!("不支持的压缩格式: {}", name))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    // 转换路径为字符串
+    let input_paths: Vec<&str> = args.input.iter().filter_map(|p| p.to_str()).collect();
+
+    if input_paths.is_empty() {
+        return Err(anyhow::anyhow!("无法处理输入路径"));
+    }
+
+    println!("正在采样并试压缩，这可能需要一些时间...");
+    let report = bench::analyze(&input_paths, &formats, goal, args.sample_size * 1024 * 1024)
+        .await
+        .map_err(|e: String| anyhow::anyhow!(e))?;
+
+    println!(
+        "样本: {} 个文件，共 {}",
+        report.sample_files,
+        bench::format_bytes(report.sample_size)
+    );
+    println!();
+    println!(
+        "{:<10} {:<8} {:>6} {:>12} {:>8} {:>12} {:>12} {:>10}",
+        "格式", "预设", "级别", "压缩后", "压缩率", "压缩速度", "解压速度", "峰值内存"
+    );
+    for result in &report.results {
+        let level = result
+            .level
+            .map(|level| level.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        if let Some(error) = &result.error {
+            println!(
+                "{:<10} {:<8} {:>6} 失败: {}",
+                result.format,
+                result.preset.name(),
+                level,
+                error
+            );
+            continue;
+        }
+
+        println!(
+            "{:<10} {:<8} {:>6} {:>12} {:>7.1}% {:>10}/s {:>10}/s {:>10}",
+            result.format,
+            result.preset.name(),
+            level,
+            bench::format_bytes(result.compressed_size),
+            result.ratio * 100.0,
+            bench::format_bytes(result.compress_speed as u64),
+            bench::format_bytes(result.decompress_speed as u64),
+            result
+                .peak_memory
+                .map(bench::format_bytes)
+                .unwrap_or_else(|| "-".to_string()),
+        );
+    }
+
+    println!();
+    match &report.recommendation {
+        Some(result) => {
+            println!(
+                "推荐 ({}): {} 预设 {} (ziphere c -f {} --preset {})",
+                goal.name(),
+                result.format,
+                result.preset.name(),
+                result.format,
+                result.preset.name()
+            );
+        }
+        None => println!("没有可推荐的格式: 所有试压缩都失败了"),
+    }
+
+    Ok(())
+}
+
 #[cfg(windows)]
 fn config_command(args: ConfigArgs) -> anyhow::Result<()> {
     use std::process::Command;
@@ -437,6 +1238,22 @@ fn set_file_association(
         CompressionFormat::Xz => "XZ 压缩文件",
         CompressionFormat::Zstd => "Zstandard 压缩文件",
         CompressionFormat::SevenZip => "7-Zip 压缩文件",
+        CompressionFormat::Lz4 => "LZ4 压缩文件",
+        CompressionFormat::TarLz4 => "TAR.LZ4 归档文件",
+        CompressionFormat::Brotli => "Brotli 压缩文件",
+        CompressionFormat::Lzma => "LZMA 压缩文件",
+        CompressionFormat::Lzip => "LZIP 压缩文件",
+        CompressionFormat::TarLzma => "TAR.LZMA 归档文件",
+        CompressionFormat::TarLzip => "TAR.LZ 归档文件",
+        CompressionFormat::TarGz => "TAR.GZ 归档文件",
+        CompressionFormat::TarBz2 => "TAR.BZ2 归档文件",
+        CompressionFormat::TarXz => "TAR.XZ 归档文件",
+        CompressionFormat::TarZst => "TAR.ZST 归档文件",
+        CompressionFormat::Cpio => "CPIO 归档文件",
+        CompressionFormat::Ar => "AR 归档文件",
+        CompressionFormat::Deb => "Debian 软件包",
+        CompressionFormat::Iso => "ISO 光盘镜像",
+        CompressionFormat::Plugin(_) => format.name(),
     };
 
     // 注册文件类型
@@ -460,12 +1277,21 @@ fn set_file_association(
         // 文件查看器模式
         format!("\"{}\" view \"%1\"", exe_path)
     } else {
-        // GUI 模式 (默认)
-        format!("\"{}\" d \"%1\"", exe_path)
+        // GUI 模式 (默认)，智能解压到归档所在目录
+        format!("\"{}\" d --smart \"%1\"", exe_path)
     };
     
     command_key.0.set_value("", &command_str)?;
 
+    // 右键菜单: 智能解压到此处
+    let extract_key = app_key.0.create_subkey("shell\\ZipHereExtract")?;
+    extract_key.0.set_value("", &"使用 ZipHere 解压到此处")?;
+    extract_key.0.set_value("Icon", &format!("{},0", exe_path))?;
+    let extract_command_key = extract_key.0.create_subkey("command")?;
+    extract_command_key
+        .0
+        .set_value("", &format!("\"{}\" d --smart \"%1\"", exe_path))?;
+
     Ok(())
 }
 
@@ -0,0 +1,179 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::conflict::{merge_dir, ConflictResolver};
+
+// 生成临时文件名时尝试的最大次数
+const MAX_TEMP_ATTEMPTS: u32 = 100;
+
+// 同一进程内临时文件和暂存目录的序号，避免并发任务互相覆盖
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+fn temp_name(prefix: &str, name: &str) -> String {
+    format!(
+        ".{}{}-{}-{}.tmp",
+        prefix,
+        name,
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// 原子写入的输出文件
+//
+// 数据先写到目标目录中的临时文件，commit 时同步到磁盘再改名为最终文件名；
+// 未 commit 就被丢弃 (压缩中途出错) 时删除临时文件，不会留下以最终文件名命名的残缺压缩包。
+pub struct AtomicFile {
+    file: Option<File>,
+    temp_path: PathBuf,
+    target: PathBuf,
+}
+
+impl AtomicFile {
+    pub fn create(target: impl AsRef<Path>) -> Result<Self, String> {
+        let target = target.as_ref().to_path_buf();
+        let file_name = target
+            .file_name()
+            .ok_or_else(|| format!("无效的输出路径: {}", target.display()))?
+            .to_string_lossy()
+            .to_string();
+        // 临时文件放在目标目录中，保证改名不跨文件系统
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        for _ in 0..MAX_TEMP_ATTEMPTS {
+            let temp_path = dir.join(temp_name("", &file_name));
+            match OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)
+            {
+                Ok(file) => {
+                    return Ok(AtomicFile {
+                        file: Some(file),
+                        temp_path,
+                        target,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("无法创建 {}: {}", target.display(), e)),
+            }
+        }
+
+        Err(format!("无法为 {} 创建临时文件", target.display()))
+    }
+
+    // 临时文件路径，供外部程序 (插件) 直接写入
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    // 同步到磁盘并改名为最终文件名
+    pub fn commit(mut self) -> Result<(), String> {
+        if let Some(mut file) = self.file.take() {
+            file.flush().map_err(|e| e.to_string())?;
+        }
+        // 重新打开再同步，外部程序可能已替换了临时文件
+        OpenOptions::new()
+            .write(true)
+            .open(&self.temp_path)
+            .and_then(|file| file.sync_all())
+            .map_err(|e| format!("无法写入 {}: {}", self.target.display(), e))?;
+
+        fs::rename(&self.temp_path, &self.target)
+            .map_err(|e| format!("无法保存 {}: {}", self.target.display(), e))?;
+        sync_parent(&self.target);
+        // 改名成功后临时文件已不存在，Drop中的删除不会有影响
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut File> {
+        self.file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("输出文件已关闭"))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl Read for AtomicFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file()?.read(buf)
+    }
+}
+
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file()?.seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        self.file.take();
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
+// 改名后同步所在目录，保证断电后目录项也已落盘；Windows不支持打开目录，忽略失败
+fn sync_parent(path: &Path) {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+}
+
+// 解压暂存目录
+//
+// 在输出目录中创建，解压完成后通过 commit 把条目移入输出目录并处理冲突；
+// 未 commit 就被丢弃 (解压中途出错) 时整个删除，输出目录中不会出现解压了一半的内容。
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    pub fn create(output_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        for _ in 0..MAX_TEMP_ATTEMPTS {
+            let path = output_dir.join(temp_name("ziphere-stage", ""));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(StagingDir { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("无法创建暂存目录: {}", e)),
+            }
+        }
+
+        Err(format!("无法在 {} 中创建暂存目录", output_dir.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // 将暂存的条目移入输出目录
+    pub fn commit(self, output_dir: &Path, conflict: &mut ConflictResolver) -> Result<(), String> {
+        merge_dir(&self.path, output_dir, conflict)
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,450 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::{CompressionFormat, CompressionOptions, CompressionPreset, Compressor};
+
+// 默认采样大小
+pub const DEFAULT_SAMPLE_SIZE: u64 = 16 * 1024 * 1024;
+
+// 每个文件至少采样的字节数，避免大量小文件时配额过小
+const MIN_FILE_SAMPLE: u64 = 64 * 1024;
+
+// 试压缩使用的预设，分别代表快速、默认和最高压缩
+const TRIAL_PRESETS: [CompressionPreset; 3] = [
+    CompressionPreset::Fastest,
+    CompressionPreset::Normal,
+    CompressionPreset::Ultra,
+];
+
+// 均衡目标允许的体积差距: 在最小结果的110%以内选择最快的
+const BALANCED_SIZE_TOLERANCE: f64 = 1.10;
+
+// 推荐目标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BenchGoal {
+    Smallest,
+    Fastest,
+    Balanced,
+}
+
+impl BenchGoal {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "smallest" => Some(BenchGoal::Smallest),
+            "fastest" => Some(BenchGoal::Fastest),
+            "balanced" => Some(BenchGoal::Balanced),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchGoal::Smallest => "smallest",
+            BenchGoal::Fastest => "fastest",
+            BenchGoal::Balanced => "balanced",
+        }
+    }
+}
+
+// 单次试压缩的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub format: String,
+    pub preset: CompressionPreset,
+    pub level: Option<i32>,
+    pub compressed_size: u64,
+    // 压缩后大小与原始大小之比，越小越好
+    pub ratio: f64,
+    // 单位为字节/秒
+    pub compress_speed: f64,
+    pub decompress_speed: f64,
+    // 试压缩期间进程常驻内存的峰值增量，无法测量时为None (见PeakMemory)
+    pub peak_memory: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl BenchResult {
+    fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    // 压缩和解压的总耗时 (秒/字节)
+    fn total_time(&self) -> f64 {
+        1.0 / self.compress_speed + 1.0 / self.decompress_speed
+    }
+}
+
+// 分析报告
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub goal: BenchGoal,
+    pub sample_files: usize,
+    pub sample_size: u64,
+    pub results: Vec<BenchResult>,
+    pub recommendation: Option<BenchResult>,
+}
+
+// 对输入采样后用各候选格式试压缩，并按目标给出推荐
+pub async fn analyze(
+    input_paths: &[&str],
+    formats: &[CompressionFormat],
+    goal: BenchGoal,
+    sample_size: u64,
+) -> Result<BenchReport, String> {
+    let work_dir = std::env::temp_dir().join(format!("ziphere-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&work_dir);
+    fs::create_dir_all(&work_dir).map_err(|e| e.to_string())?;
+
+    let report = run(input_paths, formats, goal, sample_size, &work_dir).await;
+    let _ = fs::remove_dir_all(&work_dir);
+    report
+}
+
+async fn run(
+    input_paths: &[&str],
+    formats: &[CompressionFormat],
+    goal: BenchGoal,
+    sample_size: u64,
+    work_dir: &Path,
+) -> Result<BenchReport, String> {
+    // 归档格式压缩样本目录，流格式压缩拼接后的单个样本文件
+    let sample_dir = work_dir.join("sample");
+    let sample_stream = work_dir.join("sample.bin");
+    let (sample_files, sample_size) =
+        build_sample(input_paths, &sample_dir, &sample_stream, sample_size)?;
+    if sample_size == 0 {
+        return Err("输入中没有可用于分析的数据".to_string());
+    }
+
+    let mut results = Vec::new();
+    for (index, format) in formats.iter().enumerate() {
+        let compressor = Compressor::new(*format);
+        let capabilities = compressor.capabilities();
+        if !capabilities.can_compress || !capabilities.can_decompress {
+            continue;
+        }
+
+        let input = if capabilities.is_archive {
+            &sample_dir
+        } else {
+            &sample_stream
+        };
+
+        // 没有级别的格式只用默认预设试一次
+        let presets: &[CompressionPreset] = if capabilities.level.is_some() {
+            &TRIAL_PRESETS
+        } else {
+            &[CompressionPreset::Normal]
+        };
+
+        let mut tried_levels = Vec::new();
+        for &preset in presets {
+            let Some(options) = compressor.preset_options(preset) else {
+                break;
+            };
+
+            // 级别相同的预设不重复测试
+            let level = capabilities.level.and(options.level());
+            if tried_levels.contains(&level) {
+                continue;
+            }
+            tried_levels.push(level);
+
+            let trial_dir = work_dir.join(format!("trial-{}-{}", index, preset.name()));
+            let result = trial(
+                &compressor,
+                *format,
+                preset,
+                input,
+                sample_size,
+                &trial_dir,
+                options,
+            )
+            .await;
+            let _ = fs::remove_dir_all(&trial_dir);
+            results.push(result);
+        }
+    }
+
+    let recommendation = recommend(&results, goal);
+    Ok(BenchReport {
+        goal,
+        sample_files,
+        sample_size,
+        results,
+        recommendation,
+    })
+}
+
+// 执行一次压缩和解压并计时
+async fn trial(
+    compressor: &Compressor,
+    format: CompressionFormat,
+    preset: CompressionPreset,
+    input: &Path,
+    sample_size: u64,
+    trial_dir: &Path,
+    options: CompressionOptions,
+) -> BenchResult {
+    let mut result = BenchResult {
+        format: format.extension().to_string(),
+        preset,
+        level: compressor.capabilities().level.and(options.level()),
+        compressed_size: 0,
+        ratio: 0.0,
+        compress_speed: 0.0,
+        decompress_speed: 0.0,
+        peak_memory: None,
+        error: None,
+    };
+
+    let archive = trial_dir.join(format!("sample.{}", format.extension()));
+    let output = trial_dir.join("output");
+    if let Err(e) = fs::create_dir_all(&output) {
+        result.error = Some(e.to_string());
+        return result;
+    }
+
+    let peak = PeakMemory::start();
+
+    let started = Instant::now();
+    if let Err(e) = compressor
+        .compress(
+            vec![&input.to_string_lossy()],
+            &archive.to_string_lossy(),
+            Some(options),
+        )
+        .await
+    {
+        result.error = Some(e);
+        return result;
+    }
+    let compress_time = started.elapsed().as_secs_f64();
+
+    let started = Instant::now();
+    if let Err(e) = compressor
+        .decompress(
+            vec![&archive.to_string_lossy()],
+            &output.to_string_lossy(),
+            None,
+        )
+        .await
+    {
+        result.error = Some(e);
+        return result;
+    }
+    let decompress_time = started.elapsed().as_secs_f64();
+
+    result.peak_memory = peak.and_then(|peak| peak.finish());
+    result.compressed_size = fs::metadata(&archive).map(|m| m.len()).unwrap_or(0);
+    result.ratio = result.compressed_size as f64 / sample_size as f64;
+    result.compress_speed = sample_size as f64 / compress_time.max(1e-9);
+    result.decompress_speed = sample_size as f64 / decompress_time.max(1e-9);
+    result
+}
+
+// 按目标从成功的结果中选出推荐项
+fn recommend(results: &[BenchResult], goal: BenchGoal) -> Option<BenchResult> {
+    let succeeded = results.iter().filter(|result| result.succeeded());
+
+    let chosen = match goal {
+        BenchGoal::Smallest => succeeded.min_by(|a, b| {
+            a.compressed_size
+                .cmp(&b.compressed_size)
+                .then(b.compress_speed.total_cmp(&a.compress_speed))
+        }),
+        BenchGoal::Fastest => succeeded.min_by(|a, b| a.total_time().total_cmp(&b.total_time())),
+        BenchGoal::Balanced => {
+            let smallest = results
+                .iter()
+                .filter(|result| result.succeeded())
+                .map(|result| result.compressed_size)
+                .min()?;
+            let limit = (smallest as f64 * BALANCED_SIZE_TOLERANCE) as u64;
+            succeeded
+                .filter(|result| result.compressed_size <= limit)
+                .min_by(|a, b| a.total_time().total_cmp(&b.total_time()))
+        }
+    };
+
+    chosen.cloned()
+}
+
+// 从输入中采样: 每个文件按配额截取开头部分，复制到样本目录并拼接为单个样本文件
+fn build_sample(
+    input_paths: &[&str],
+    sample_dir: &Path,
+    sample_stream: &Path,
+    limit: u64,
+) -> Result<(usize, u64), String> {
+    let mut files = Vec::new();
+    for input_path in input_paths {
+        collect_files(Path::new(input_path), &mut files)?;
+    }
+    files.sort();
+
+    fs::create_dir_all(sample_dir).map_err(|e| e.to_string())?;
+    let mut stream = File::create(sample_stream).map_err(|e| e.to_string())?;
+
+    let quota = (limit / files.len().max(1) as u64).max(MIN_FILE_SAMPLE);
+    let mut total = 0;
+    let mut count = 0;
+
+    for (index, path) in files.iter().enumerate() {
+        if total >= limit {
+            break;
+        }
+
+        let take = quota.min(limit - total);
+        let mut data = Vec::new();
+        File::open(path)
+            .and_then(|file| file.take(take).read_to_end(&mut data))
+            .map_err(|e| e.to_string())?;
+        if data.is_empty() {
+            continue;
+        }
+
+        // 保留原扩展名，部分格式的效果与文件类型有关
+        let name = match path.extension() {
+            Some(ext) => format!("{}.{}", index, ext.to_string_lossy()),
+            None => index.to_string(),
+        };
+        fs::write(sample_dir.join(name), &data).map_err(|e| e.to_string())?;
+        stream.write_all(&data).map_err(|e| e.to_string())?;
+
+        total += data.len() as u64;
+        count += 1;
+    }
+
+    Ok((count, total))
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            collect_files(&entry.path(), files)?;
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
+
+// 测量一段操作期间进程常驻内存的峰值增量
+//
+// 峰值记录是整个进程共用的，同时运行的其他任务使用的内存也会计入。
+// Linux通过 /proc/self/clear_refs 把峰值重置为当前常驻内存，结果为之后的峰值与开始时的差。
+// Windows和macOS无法重置峰值，操作期间没有超过之前的峰值时无从得知实际峰值，结果为None；
+// Windows以开始时的工作集为基准，macOS取不到当前常驻内存，以之前的峰值为基准，结果偏小。
+struct PeakMemory {
+    baseline: u64,
+    previous_peak: u64,
+    // 峰值记录是否已重置为当前常驻内存
+    reset: bool,
+}
+
+impl PeakMemory {
+    fn start() -> Option<Self> {
+        let reset = reset_peak_memory();
+        let (current, peak) = memory_counters()?;
+        Some(PeakMemory {
+            baseline: current,
+            previous_peak: peak,
+            reset,
+        })
+    }
+
+    fn finish(&self) -> Option<u64> {
+        let (_, peak) = memory_counters()?;
+        if !self.reset && peak <= self.previous_peak {
+            return None;
+        }
+        Some(peak.saturating_sub(self.baseline))
+    }
+}
+
+// 把进程的内存峰值记录重置为当前常驻内存，成功时返回true
+#[cfg(target_os = "linux")]
+fn reset_peak_memory() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reset_peak_memory() -> bool {
+    false
+}
+
+// 当前常驻内存和峰值 (字节)
+#[cfg(target_os = "linux")]
+fn memory_counters() -> Option<(u64, u64)> {
+    Some((read_status_field("VmRSS:")?, read_status_field("VmHWM:")?))
+}
+
+// 读取 /proc/self/status 中以kB为单位的字段
+#[cfg(target_os = "linux")]
+fn read_status_field(field: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    let kb: u64 = line[field.len()..]
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(windows)]
+fn memory_counters() -> Option<(u64, u64)> {
+    use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows::Win32::System::Threading::GetCurrentProcess;
+
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let mut counters = PROCESS_MEMORY_COUNTERS {
+        cb: size,
+        ..Default::default()
+    };
+    unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) }.ok()?;
+    Some((
+        counters.WorkingSetSize as u64,
+        counters.PeakWorkingSetSize as u64,
+    ))
+}
+
+// macOS的ru_maxrss以字节为单位；取不到当前常驻内存，以峰值代替
+#[cfg(target_os = "macos")]
+fn memory_counters() -> Option<(u64, u64)> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let peak = usage.ru_maxrss as u64;
+    Some((peak, peak))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn memory_counters() -> Option<(u64, u64)> {
+    None
+}
+
+// 用于显示的字节数
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+use super::sanitize::{NameRules, Sanitizer};
+use super::{DecompressionOptions, ExtractSettings};
+
+// 重命名时尝试的最大序号
+const MAX_RENAME_ATTEMPTS: u32 = 10000;
+
+// 解压时目标文件已存在的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    Rename,
+    KeepNewer,
+    Ask,
+}
+
+impl ConflictPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "overwrite" => Some(ConflictPolicy::Overwrite),
+            "skip" => Some(ConflictPolicy::Skip),
+            "rename" => Some(ConflictPolicy::Rename),
+            "keep_newer" | "newer" => Some(ConflictPolicy::KeepNewer),
+            "ask" => Some(ConflictPolicy::Ask),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Rename => "rename",
+            ConflictPolicy::KeepNewer => "keep_newer",
+            ConflictPolicy::Ask => "ask",
+        }
+    }
+}
+
+// 询问用户时提供的冲突信息，时间为Unix秒
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictInfo {
+    pub path: String,
+    pub existing_size: u64,
+    pub existing_mtime: Option<u64>,
+    pub entry_size: Option<u64>,
+    pub entry_mtime: Option<u64>,
+}
+
+// 用户的回答，choice不能为Ask
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ConflictAnswer {
+    pub choice: ConflictPolicy,
+    pub apply_to_all: bool,
+}
+
+pub type AskHandler = Box<dyn Fn(&ConflictInfo) -> Result<ConflictAnswer, String> + Send + Sync>;
+
+// 询问方式由界面层注册: GUI通过事件往返，命令行读取标准输入
+static ASK_HANDLER: OnceLock<AskHandler> = OnceLock::new();
+
+// 注册询问方式，进程内只能注册一次
+pub fn set_ask_handler(handler: AskHandler) {
+    let _ = ASK_HANDLER.set(handler);
+}
+
+// 一次解压过程中的冲突处理状态，记住"全部应用"的选择和已写出的文件名，
+// 并携带本次解压的设置供各格式读取
+pub struct ConflictResolver {
+    policy: ConflictPolicy,
+    remembered: Option<ConflictPolicy>,
+    names: Sanitizer,
+    settings: ExtractSettings,
+}
+
+impl ConflictResolver {
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self::with_settings(policy, ExtractSettings::default())
+    }
+
+    pub fn with_settings(policy: ConflictPolicy, settings: ExtractSettings) -> Self {
+        ConflictResolver {
+            policy,
+            remembered: None,
+            names: Sanitizer::new(
+                NameRules::select(settings.portable_names),
+                settings.renames.clone(),
+            ),
+            settings,
+        }
+    }
+
+    pub fn from_options(options: Option<&DecompressionOptions>) -> Self {
+        match options {
+            Some(options) => {
+                Self::with_settings(options.conflict_policy(), options.settings().clone())
+            }
+            None => Self::new(ConflictPolicy::default()),
+        }
+    }
+
+    // 本次解压的设置
+    pub fn settings(&self) -> &ExtractSettings {
+        &self.settings
+    }
+
+    // 文件条目在输出目录中的路径: 名称按本系统的规则清理，与本次解压的其他条目重名时加序号，
+    // 有变化的写入重命名报告；None表示路径不安全。已存在的文件之后再由resolve处理
+    pub fn entry_path(&mut self, output_dir: &Path, name: &str) -> Result<Option<PathBuf>, String> {
+        self.names.file_path(output_dir, name)
+    }
+
+    // 目录条目在输出目录中的路径，规则同entry_path，但同名目录直接合并
+    pub fn dir_path(&mut self, output_dir: &Path, name: &str) -> Result<Option<PathBuf>, String> {
+        self.names.dir_path(output_dir, name)
+    }
+
+    // 返回条目实际写入的路径，None表示跳过该条目
+    pub fn resolve(
+        &mut self,
+        target: &Path,
+        entry_size: Option<u64>,
+        entry_mtime: Option<u64>,
+    ) -> Result<Option<PathBuf>, String> {
+        let Ok(existing) = fs::symlink_metadata(target) else {
+            return Ok(Some(target.to_path_buf()));
+        };
+        let existing_mtime = existing.modified().ok().and_then(system_time_secs);
+
+        let policy = match (self.policy, self.remembered) {
+            (ConflictPolicy::Ask, Some(remembered)) => remembered,
+            (ConflictPolicy::Ask, None) => {
+                let handler = ASK_HANDLER
+                    .get()
+                    .ok_or_else(|| format!("文件已存在且无法询问如何处理: {}", target.display()))?;
+                let answer = handler(&ConflictInfo {
+                    path: target.to_string_lossy().to_string(),
+                    existing_size: existing.len(),
+                    existing_mtime,
+                    entry_size,
+                    entry_mtime,
+                })?;
+                if answer.choice == ConflictPolicy::Ask {
+                    return Err("无效的冲突处理方式".to_string());
+                }
+                if answer.apply_to_all {
+                    self.remembered = Some(answer.choice);
+                }
+                answer.choice
+            }
+            (policy, _) => policy,
+        };
+
+        let overwrite = match policy {
+            ConflictPolicy::Skip => false,
+            ConflictPolicy::Rename => return unique_path(target).map(Some),
+            // 无法比较时保留已有文件
+            ConflictPolicy::KeepNewer => match (entry_mtime, existing_mtime) {
+                (Some(entry), Some(existing)) => entry > existing,
+                _ => false,
+            },
+            _ => true,
+        };
+        if !overwrite {
+            return Ok(None);
+        }
+
+        // 先删除已有文件，避免写穿符号链接或硬链接
+        if existing.is_dir() {
+            return Err(format!("目标位置已存在同名目录: {}", target.display()));
+        }
+        fs::remove_file(target).map_err(|e| format!("无法覆盖 {}: {}", target.display(), e))?;
+        Ok(Some(target.to_path_buf()))
+    }
+}
+
+// 生成不冲突的文件名，如 a.txt -> a (1).txt
+fn unique_path(target: &Path) -> Result<PathBuf, String> {
+    let stem = target
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    for index in 1..=MAX_RENAME_ATTEMPTS {
+        let candidate = target.with_file_name(format!("{} ({}){}", stem, index, extension));
+        if fs::symlink_metadata(&candidate).is_err() {
+            return Ok(candidate);
+        }
+    }
+
+    Err(format!("无法为 {} 生成不冲突的文件名", target.display()))
+}
+
+// 文件的修改时间 (Unix秒)，用于流格式没有记录时间时以压缩文件本身的时间代替
+pub fn modified_secs(path: &Path) -> Option<u64> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(system_time_secs)
+}
+
+fn system_time_secs(time: std::time::SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|duration| duration.as_secs())
+}
+
+// 将暂存目录中的内容移入输出目录，逐个文件处理冲突；用于无法逐条目控制写入的解压方式
+pub fn merge_dir(
+    staging: &Path,
+    output_dir: &Path,
+    conflict: &mut ConflictResolver,
+) -> Result<(), String> {
+    for entry in fs::read_dir(staging).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let source = entry.path();
+        let target = output_dir.join(entry.file_name());
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+
+        if file_type.is_dir() {
+            if fs::symlink_metadata(&target).is_ok_and(|metadata| metadata.is_dir()) {
+                merge_dir(&source, &target, conflict)?;
+                continue;
+            }
+            if fs::symlink_metadata(&target).is_err() {
+                fs::rename(&source, &target).map_err(|e| e.to_string())?;
+                continue;
+            }
+        }
+
+        let metadata = entry.metadata().map_err(|e| e.to_string())?;
+        let mtime = metadata.modified().ok().and_then(system_time_secs);
+        if let Some(resolved) = conflict.resolve(&target, Some(metadata.len()), mtime)? {
+            fs::rename(&source, &resolved).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::testing::TempDir;
+    use filetime::FileTime;
+
+    // 创建内容和修改时间已知的已有文件
+    fn existing(dir: &TempDir, name: &str, mtime: i64) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, "existing").unwrap();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        path
+    }
+
+    #[test]
+    fn writes_missing_target() {
+        let dir = TempDir::new("conflict-missing");
+        let target = dir.join("new.txt");
+        for policy in [ConflictPolicy::Skip, ConflictPolicy::Rename] {
+            let resolved = ConflictResolver::new(policy)
+                .resolve(&target, None, None)
+                .unwrap();
+            assert_eq!(resolved, Some(target.clone()));
+        }
+    }
+
+    #[test]
+    fn skip_keeps_existing() {
+        let dir = TempDir::new("conflict-skip");
+        let target = existing(&dir, "a.txt", 1_000);
+        let resolved = ConflictResolver::new(ConflictPolicy::Skip)
+            .resolve(&target, Some(4), Some(2_000))
+            .unwrap();
+        assert_eq!(resolved, None);
+        assert_eq!(fs::read_to_string(&target).unwrap(), "existing");
+    }
+
+    // 覆盖前删除已有文件，不会写穿指向别处的符号链接；同名目录不能被覆盖
+    #[test]
+    fn overwrite_removes_existing() {
+        let dir = TempDir::new("conflict-overwrite");
+        let target = existing(&dir, "a.txt", 1_000);
+        let mut conflict = ConflictResolver::new(ConflictPolicy::Overwrite);
+        assert_eq!(
+            conflict.resolve(&target, None, None).unwrap(),
+            Some(target.clone())
+        );
+        assert!(!target.exists());
+
+        #[cfg(unix)]
+        {
+            let outside = existing(&dir, "outside.txt", 1_000);
+            let link = dir.join("link.txt");
+            std::os::unix::fs::symlink(&outside, &link).unwrap();
+            assert_eq!(
+                conflict.resolve(&link, None, None).unwrap(),
+                Some(link.clone())
+            );
+            assert!(fs::symlink_metadata(&link).is_err());
+            assert_eq!(fs::read_to_string(&outside).unwrap(), "existing");
+        }
+
+        fs::create_dir(dir.join("sub")).unwrap();
+        assert!(conflict.resolve(&dir.join("sub"), None, None).is_err());
+    }
+
+    // 重命名时跳过已被占用的序号，没有扩展名的文件同样加序号
+    #[test]
+    fn rename_numbers_targets() {
+        let dir = TempDir::new("conflict-rename");
+        let target = existing(&dir, "a.txt", 1_000);
+        existing(&dir, "a (1).txt", 1_000);
+        let readme = existing(&dir, "README", 1_000);
+
+        let mut conflict = ConflictResolver::new(ConflictPolicy::Rename);
+        assert_eq!(
+            conflict.resolve(&target, None, None).unwrap(),
+            Some(dir.join("a (2).txt"))
+        );
+        assert_eq!(
+            conflict.resolve(&readme, None, None).unwrap(),
+            Some(dir.join("README (1)"))
+        );
+        assert_eq!(fs::read_to_string(&target).unwrap(), "existing");
+    }
+
+    // 只有条目比已有文件新时才覆盖，时间未知时保留已有文件
+    #[test]
+    fn keep_newer_compares_mtimes() {
+        let dir = TempDir::new("conflict-newer");
+        let target = existing(&dir, "a.txt", 1_000);
+        let mut conflict = ConflictResolver::new(ConflictPolicy::KeepNewer);
+
+        for entry_mtime in [Some(500), Some(1_000), None] {
+            assert_eq!(conflict.resolve(&target, None, entry_mtime).unwrap(), None);
+            assert!(target.exists());
+        }
+        assert_eq!(
+            conflict.resolve(&target, None, Some(2_000)).unwrap(),
+            Some(target.clone())
+        );
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn parses_policy_names() {
+        for policy in [
+            ConflictPolicy::Overwrite,
+            ConflictPolicy::Skip,
+            ConflictPolicy::Rename,
+            ConflictPolicy::KeepNewer,
+            ConflictPolicy::Ask,
+        ] {
+            assert_eq!(ConflictPolicy::from_name(policy.name()), Some(policy));
+        }
+        assert_eq!(
+            ConflictPolicy::from_name("Keep-Newer"),
+            Some(ConflictPolicy::KeepNewer)
+        );
+        assert_eq!(ConflictPolicy::from_name("merge"), None);
+    }
+}
@@ -0,0 +1,325 @@
+use flate2::Crc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::conflict::modified_secs;
+use super::{safe_join, stays_inside};
+
+// 计算CRC时每次读取的大小
+const CRC_BUFFER_SIZE: usize = 64 * 1024;
+
+// 日志中的一条记录，每行一条JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Record {
+    // 第一行，标识日志对应的归档
+    Archive {
+        size: u64,
+        mtime: Option<u64>,
+    },
+    // 开始写入条目，path为相对输出目录的实际写入位置
+    Begin {
+        entry: String,
+        path: String,
+    },
+    // 条目写入完成
+    Done {
+        entry: String,
+        path: String,
+        size: u64,
+        crc: u32,
+    },
+}
+
+// 断点续解日志
+//
+// 保存在输出目录中，记录已完成条目的大小和CRC。重新解压同一归档时，磁盘上的文件仍与记录一致的条目被跳过；
+// 开始了但没有完成的条目是上次中断时写了一半的文件，先删除再重新解压。整个归档解压完成后删除日志。
+pub struct Journal {
+    file: File,
+    path: PathBuf,
+    output_dir: PathBuf,
+    done: HashMap<String, (String, u64, u32)>,
+}
+
+impl Journal {
+    pub fn open(output_dir: &Path, archive_path: &str) -> Result<Self, String> {
+        let archive = Path::new(archive_path);
+        let archive_name = archive
+            .file_name()
+            .ok_or("无效的文件名")?
+            .to_string_lossy()
+            .to_string();
+        let path = output_dir.join(format!(".{}.ziphere-journal", archive_name));
+        let identity = Record::Archive {
+            size: fs::metadata(archive).map_err(|e| e.to_string())?.len(),
+            mtime: modified_secs(archive),
+        };
+
+        // 读取上次的记录，归档已变化时全部作废
+        let mut done = HashMap::new();
+        let mut started = HashMap::new();
+        if let Ok(file) = File::open(&path) {
+            let mut records = BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .map(|line| serde_json::from_str::<Record>(&line));
+            if matches!(records.next(), Some(Ok(ref record)) if *record == identity) {
+                // 中断时最后一行可能只写了一半，遇到无法解析的行就停止
+                for record in records.map_while(Result::ok) {
+                    match record {
+                        Record::Begin { entry, path } => {
+                            started.insert(entry, path);
+                        }
+                        Record::Done {
+                            entry,
+                            path,
+                            size,
+                            crc,
+                        } => {
+                            started.remove(&entry);
+                            done.insert(entry, (path, size, crc));
+                        }
+                        Record::Archive { .. } => break,
+                    }
+                }
+            }
+        }
+
+        // 删除上次写了一半的文件；日志可能被改动，只处理仍在输出目录中的路径
+        for partial in started.values() {
+            if let Some(target) = target_path(output_dir, partial) {
+                let _ = fs::remove_file(target);
+            }
+        }
+        done.retain(|_, (path, _, _)| target_path(output_dir, path).is_some());
+
+        // 重写日志，只保留有效的完成记录
+        let mut journal = Journal {
+            file: File::create(&path).map_err(|e| format!("无法创建解压日志: {}", e))?,
+            path,
+            output_dir: output_dir.to_path_buf(),
+            done: HashMap::new(),
+        };
+        journal.append(&identity)?;
+        for (entry, (path, size, crc)) in done {
+            journal.append(&Record::Done {
+                entry: entry.clone(),
+                path: path.clone(),
+                size,
+                crc,
+            })?;
+            journal.done.insert(entry, (path, size, crc));
+        }
+
+        Ok(journal)
+    }
+
+    // 条目是否已在上次完成，可以跳过；磁盘上的文件必须仍与记录的大小和CRC一致，
+    // 不一致时删除该文件，重新解压时不会被当作冲突
+    pub fn skip(&self, entry: &str) -> bool {
+        let Some((path, size, crc)) = self.done.get(entry) else {
+            return false;
+        };
+        let Some(target) = target_path(&self.output_dir, path) else {
+            return false;
+        };
+        let intact = fs::metadata(&target)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.len() == *size)
+            && file_crc(&target).is_ok_and(|actual| actual == *crc);
+        if !intact {
+            let _ = fs::remove_file(&target);
+        }
+        intact
+    }
+
+    pub fn begin(&mut self, entry: &str, target: &Path) -> Result<(), String> {
+        let path = self.relative(target);
+        self.append(&Record::Begin {
+            entry: entry.to_string(),
+            path,
+        })
+    }
+
+    // 记录条目完成，大小和CRC从写入的文件计算
+    pub fn finish(&mut self, entry: &str, target: &Path) -> Result<(), String> {
+        let size = fs::metadata(target).map_err(|e| e.to_string())?.len();
+        let crc = file_crc(target)?;
+        let path = self.relative(target);
+        self.append(&Record::Done {
+            entry: entry.to_string(),
+            path,
+            size,
+            crc,
+        })
+    }
+
+    // 归档全部解压完成，删除日志
+    pub fn close(self) -> Result<(), String> {
+        drop(self.file);
+        fs::remove_file(&self.path).map_err(|e| format!("无法删除解压日志: {}", e))
+    }
+
+    fn relative(&self, target: &Path) -> String {
+        target
+            .strip_prefix(&self.output_dir)
+            .unwrap_or(target)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    // 每条记录立即写出，进程中断后仍能读到；断电时可能丢失的数据由恢复时的CRC校验发现
+    fn append(&mut self, record: &Record) -> Result<(), String> {
+        let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        writeln!(self.file, "{}", line)
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("无法写入解压日志: {}", e))
+    }
+}
+
+// 日志中记录的路径在输出目录中的位置，路径逃出输出目录时返回None
+fn target_path(output_dir: &Path, path: &str) -> Option<PathBuf> {
+    safe_join(output_dir, path).filter(|target| stays_inside(output_dir, target))
+}
+
+fn file_crc(path: &Path) -> Result<u32, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut crc = Crc::new();
+    let mut buffer = vec![0u8; CRC_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(crc.sum());
+        }
+        crc.update(&buffer[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::TempDir;
+    use crate::comde::z_tar::TarCompressor;
+    use crate::comde::DecompressionOptions;
+
+    fn journal_path(output: &Path) -> PathBuf {
+        output.join(".data.tar.ziphere-journal")
+    }
+
+    // 模拟中断: a.txt已完成，b.txt只写了一半，日志未关闭
+    fn interrupt(archive: &Path, output: &Path) {
+        let mut journal = Journal::open(output, archive.to_str().unwrap()).unwrap();
+        let a = output.join("a.txt");
+        journal.begin("a.txt", &a).unwrap();
+        fs::write(&a, "aaaa").unwrap();
+        journal.finish("a.txt", &a).unwrap();
+        let b = output.join("b.txt");
+        journal.begin("b.txt", &b).unwrap();
+        fs::write(&b, "b").unwrap();
+    }
+
+    fn tar_archive(path: &Path) {
+        let mut builder = tar::Builder::new(File::create(path).unwrap());
+        for (name, data) in [("a.txt", "aaaa"), ("b.txt", "bbbb")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    // 重新打开日志时跳过已完成且完好的条目，删除写了一半的文件
+    #[test]
+    fn reopen_skips_done_and_removes_partial() {
+        let dir = TempDir::new("journal-reopen");
+        let archive = dir.join("data.tar");
+        tar_archive(&archive);
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        interrupt(&archive, &output);
+
+        let journal = Journal::open(&output, archive.to_str().unwrap()).unwrap();
+        assert!(journal.skip("a.txt"));
+        assert!(!journal.skip("b.txt"));
+        assert!(!output.join("b.txt").exists());
+
+        // 已完成的文件被改动后不再跳过，并被删除
+        fs::write(output.join("a.txt"), "AAAA").unwrap();
+        assert!(!journal.skip("a.txt"));
+        assert!(!output.join("a.txt").exists());
+    }
+
+    // 中断后续解只解压未完成的条目，完成后删除日志
+    #[test]
+    fn resumes_interrupted_tar() {
+        let dir = TempDir::new("journal-resume");
+        let archive = dir.join("data.tar");
+        tar_archive(&archive);
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        interrupt(&archive, &output);
+
+        // 按重命名策略解压，已完成的条目若被重新解压会写出 a (1).txt
+        let options = DecompressionOptions::Tar {
+            conflict: ConflictPolicy::Rename,
+            settings: Default::default(),
+        };
+        TarCompressor::new()
+            .decompress_resumable(
+                vec![archive.to_str().unwrap()],
+                output.to_str().unwrap(),
+                Some(options),
+            )
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "aaaa");
+        assert_eq!(fs::read_to_string(output.join("b.txt")).unwrap(), "bbbb");
+        assert!(!output.join("a (1).txt").exists());
+        assert!(!output.join("b (1).txt").exists());
+        assert!(!journal_path(&output).exists());
+    }
+
+    // 日志中指向输出目录之外的路径不会被删除
+    #[test]
+    fn ignores_paths_outside_output_dir() {
+        let dir = TempDir::new("journal-escape");
+        let archive = dir.join("data.tar");
+        tar_archive(&archive);
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        let victim = dir.join("victim.txt");
+        fs::write(&victim, "keep").unwrap();
+
+        drop(Journal::open(&output, archive.to_str().unwrap()).unwrap());
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(journal_path(&output))
+            .unwrap();
+        for path in ["../victim.txt", &victim.to_string_lossy()] {
+            let record = Record::Begin {
+                entry: path.to_string(),
+                path: path.to_string(),
+            };
+            writeln!(file, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+        }
+        let record = Record::Done {
+            entry: "victim".to_string(),
+            path: "../victim.txt".to_string(),
+            size: 0,
+            crc: 0,
+        };
+        writeln!(file, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+        drop(file);
+
+        let journal = Journal::open(&output, archive.to_str().unwrap()).unwrap();
+        assert!(!journal.skip("victim"));
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep");
+    }
+}
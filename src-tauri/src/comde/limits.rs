@@ -0,0 +1,436 @@
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use super::bench::format_bytes;
+
+// 解压量低于此值时不检查压缩比，避免小文件 (如全零的几KB文件) 被误判
+const RATIO_GRACE_BYTES: u64 = 64 * 1024 * 1024;
+
+// 解压限制，None表示不限制；反序列化时缺少的字段使用默认值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractLimits {
+    // 单个归档解压出的总字节数
+    pub max_total_size: Option<u64>,
+    // 单个条目解压后的字节数
+    pub max_entry_size: Option<u64>,
+    // 单个归档中的条目数
+    pub max_entries: Option<u64>,
+    // 条目路径的目录嵌套层数
+    pub max_depth: Option<u32>,
+    // 解压总字节数与压缩文件大小之比
+    pub max_ratio: Option<u64>,
+}
+
+impl ExtractLimits {
+    // 默认只限制正常归档不会触及的条目数、嵌套层数和压缩比
+    pub const DEFAULT: ExtractLimits = ExtractLimits {
+        max_total_size: None,
+        max_entry_size: None,
+        max_entries: Some(1_000_000),
+        max_depth: Some(256),
+        max_ratio: Some(1000),
+    };
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// 触及的限制及其上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    TotalSize(u64),
+    EntrySize(u64),
+    Entries(u64),
+    Depth(u32),
+    Ratio(u64),
+}
+
+// 所有限制错误的消息前缀，界面层据此识别
+pub const LIMIT_ERROR_PREFIX: &str = "超出解压限制";
+
+pub fn is_limit_error(message: &str) -> bool {
+    message.starts_with(LIMIT_ERROR_PREFIX)
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", LIMIT_ERROR_PREFIX)?;
+        match self {
+            LimitExceeded::TotalSize(max) => write!(f, "解压总大小超过 {}", format_bytes(*max)),
+            LimitExceeded::EntrySize(max) => write!(f, "单个条目超过 {}", format_bytes(*max)),
+            LimitExceeded::Entries(max) => write!(f, "条目数超过 {}", max),
+            LimitExceeded::Depth(max) => write!(f, "目录嵌套超过 {} 层", max),
+            LimitExceeded::Ratio(max) => write!(f, "压缩比超过 {}:1，疑似解压炸弹", max),
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl From<LimitExceeded> for String {
+    fn from(e: LimitExceeded) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<LimitExceeded> for io::Error {
+    fn from(e: LimitExceeded) -> Self {
+        io::Error::other(e)
+    }
+}
+
+// 单个归档解压过程中的限制检查状态
+//
+// 解码器的输出经 reader/stream_reader 读取，每读到一块数据就累计并检查，超限时读取立即失败，
+// 不会先把整个条目解压出来。触及限制后，丢弃时删除本次已创建的文件。
+pub struct Limiter {
+    limits: ExtractLimits,
+    archive_size: u64,
+    total: Cell<u64>,
+    entries: Cell<u64>,
+    exceeded: Cell<Option<LimitExceeded>>,
+    created: RefCell<Vec<PathBuf>>,
+}
+
+impl Limiter {
+    pub fn new(archive_path: &str, limits: ExtractLimits) -> Self {
+        Self::with_limits(
+            limits,
+            fs::metadata(archive_path).map(|m| m.len()).unwrap_or(0),
+        )
+    }
+
+    pub fn with_limits(limits: ExtractLimits, archive_size: u64) -> Self {
+        Limiter {
+            limits,
+            archive_size,
+            total: Cell::new(0),
+            entries: Cell::new(0),
+            exceeded: Cell::new(None),
+            created: RefCell::new(Vec::new()),
+        }
+    }
+
+    // 开始一个条目，检查条目数、嵌套层数和头部中声明的大小
+    pub fn begin_entry(&self, name: &str, size: Option<u64>) -> Result<(), LimitExceeded> {
+        let entries = self.entries.get() + 1;
+        self.entries.set(entries);
+        if let Some(max) = self.limits.max_entries.filter(|max| entries > *max) {
+            return self.fail(LimitExceeded::Entries(max));
+        }
+
+        let depth = Path::new(name)
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .count();
+        if let Some(max) = self.limits.max_depth.filter(|max| depth > *max as usize) {
+            return self.fail(LimitExceeded::Depth(max));
+        }
+
+        match size {
+            Some(size) => self.check_entry(size),
+            None => Ok(()),
+        }
+    }
+
+    // 检查尚未经过reader累计的条目数据，用于只能整体解码到内存的格式
+    pub fn check_entry(&self, size: u64) -> Result<(), LimitExceeded> {
+        self.check(self.total.get().saturating_add(size), Some(size))
+    }
+
+    // 读取单个条目的数据，同时检查条目大小
+    pub fn reader<R: Read>(&self, inner: R) -> LimitedReader<'_, R> {
+        LimitedReader {
+            inner,
+            limiter: self,
+            read: 0,
+            is_entry: true,
+        }
+    }
+
+    // 读取包含多个条目的解码数据流 (如tar)，只检查总大小和压缩比
+    pub fn stream_reader<R: Read>(&self, inner: R) -> LimitedReader<'_, R> {
+        LimitedReader {
+            inner,
+            limiter: self,
+            read: 0,
+            is_entry: false,
+        }
+    }
+
+    // 检查尚未经过stream_reader累计的数据流，只检查总大小和压缩比
+    pub fn check_stream(&self, pending: u64) -> Result<(), LimitExceeded> {
+        self.check(self.total.get().saturating_add(pending), None)
+    }
+
+    // 记录本次创建的文件，触及限制时删除
+    pub fn track(&self, path: &Path) {
+        self.created.borrow_mut().push(path.to_path_buf());
+    }
+
+    // 触及限制时返回限制错误，其余错误原样返回；部分解码器会把读取错误包装成自己的消息
+    pub fn error(&self, e: impl ToString) -> String {
+        match self.exceeded.get() {
+            Some(exceeded) => exceeded.to_string(),
+            None => e.to_string(),
+        }
+    }
+
+    // 已在暂存目录中的文件 (插件解压) 按同样的限制检查
+    pub fn check_dir(&self, dir: &Path) -> Result<(), String> {
+        self.check_dir_at(dir, dir)
+    }
+
+    fn check_dir_at(&self, root: &Path, dir: &Path) -> Result<(), String> {
+        for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let metadata = fs::symlink_metadata(&path).map_err(|e| e.to_string())?;
+            let name = path.strip_prefix(root).unwrap_or(&path).to_string_lossy();
+            self.begin_entry(&name, None)?;
+            if metadata.is_dir() {
+                self.check_dir_at(root, &path)?;
+            } else {
+                self.add(metadata.len(), Some(metadata.len()))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&self, bytes: u64, entry_size: Option<u64>) -> Result<(), LimitExceeded> {
+        let total = self.total.get().saturating_add(bytes);
+        self.total.set(total);
+        self.check(total, entry_size)
+    }
+
+    fn check(&self, total: u64, entry_size: Option<u64>) -> Result<(), LimitExceeded> {
+        let limits = &self.limits;
+        if let (Some(max), Some(size)) = (limits.max_entry_size, entry_size) {
+            if size > max {
+                return self.fail(LimitExceeded::EntrySize(max));
+            }
+        }
+        if let Some(max) = limits.max_total_size.filter(|max| total > *max) {
+            return self.fail(LimitExceeded::TotalSize(max));
+        }
+        // 压缩文件大小未知时无法计算压缩比
+        if let Some(max) = limits.max_ratio {
+            if total > RATIO_GRACE_BYTES
+                && self.archive_size > 0
+                && total > self.archive_size.saturating_mul(max)
+            {
+                return self.fail(LimitExceeded::Ratio(max));
+            }
+        }
+        Ok(())
+    }
+
+    fn fail(&self, exceeded: LimitExceeded) -> Result<(), LimitExceeded> {
+        self.exceeded.set(Some(exceeded));
+        Err(exceeded)
+    }
+}
+
+impl Drop for Limiter {
+    fn drop(&mut self) {
+        if self.exceeded.get().is_none() {
+            return;
+        }
+        for path in self.created.get_mut().drain(..).rev() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// 累计读取量并检查限制的读取器
+pub struct LimitedReader<'a, R> {
+    inner: R,
+    limiter: &'a Limiter,
+    read: u64,
+    is_entry: bool,
+}
+
+impl<R> LimitedReader<'_, R> {
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: Read> Read for LimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        let entry_size = self.is_entry.then_some(self.read);
+        self.limiter.add(read as u64, entry_size)?;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::{ConflictPolicy, ConflictResolver};
+    use crate::comde::testing::TempDir;
+    use crate::comde::z_gzip::GzipCompressor;
+    use crate::comde::z_tar::TarCompressor;
+    use crate::comde::{ArchiveReader, ComdeAble, DecompressionOptions, ExtractSettings};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn settings(limits: ExtractLimits) -> ExtractSettings {
+        ExtractSettings {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    // 按给定的条目名称构建tar归档，每个条目含少量数据
+    fn tar_archive(path: &Path, names: &[&str]) {
+        let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+        for name in names {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, &b"data"[..])
+                .unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn extract_tar(archive: &Path, output: &Path, limits: ExtractLimits) -> Result<(), String> {
+        TarCompressor::new().extract(
+            archive.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &[],
+            &mut ConflictResolver::with_settings(ConflictPolicy::Overwrite, settings(limits)),
+        )
+    }
+
+    // 读取超过总大小时立即失败，丢弃时删除已记录的文件
+    #[test]
+    fn reader_stops_at_total_size() {
+        let dir = TempDir::new("limits-reader");
+        let partial = dir.join("partial.bin");
+        fs::write(&partial, "partial").unwrap();
+
+        let limits = ExtractLimits {
+            max_total_size: Some(1000),
+            ..ExtractLimits::DEFAULT
+        };
+        let limiter = Limiter::with_limits(limits, 10);
+        limiter.track(&partial);
+        let e = io::copy(
+            &mut limiter.reader(io::repeat(0).take(4096)),
+            &mut io::sink(),
+        )
+        .unwrap_err();
+        let message = limiter.error(e);
+        assert!(is_limit_error(&message), "{}", message);
+        assert_eq!(message, LimitExceeded::TotalSize(1000).to_string());
+
+        drop(limiter);
+        assert!(!partial.exists());
+    }
+
+    // 未触及限制时保留已创建的文件
+    #[test]
+    fn keeps_outputs_within_limits() {
+        let dir = TempDir::new("limits-ok");
+        let output = dir.join("output.bin");
+        fs::write(&output, "data").unwrap();
+
+        let limiter = Limiter::with_limits(ExtractLimits::DEFAULT, 10);
+        limiter.track(&output);
+        io::copy(
+            &mut limiter.reader(io::repeat(0).take(4096)),
+            &mut io::sink(),
+        )
+        .unwrap();
+        drop(limiter);
+        assert!(output.exists());
+    }
+
+    // 体积很小但压缩比极高的gzip流按压缩比拒绝，部分输出被删除
+    #[tokio::test]
+    async fn rejects_high_ratio_stream() {
+        let dir = TempDir::new("limits-ratio");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+        let zeros = vec![0u8; 1024 * 1024];
+        for _ in 0..80 {
+            encoder.write_all(&zeros).unwrap();
+        }
+        let archive = dir.join("zeros.gz");
+        fs::write(&archive, encoder.finish().unwrap()).unwrap();
+
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        let options = DecompressionOptions::Gzip {
+            restore_name: false,
+            conflict: ConflictPolicy::Overwrite,
+            settings: settings(ExtractLimits {
+                max_ratio: Some(100),
+                ..ExtractLimits::DEFAULT
+            }),
+        };
+        let e = GzipCompressor::new()
+            .decompress(
+                vec![archive.to_str().unwrap()],
+                output.to_str().unwrap(),
+                Some(options),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(e, LimitExceeded::Ratio(100).to_string());
+        assert!(!output.join("zeros").exists());
+    }
+
+    // 条目过多的归档在超出时停止，之前写出的条目被删除
+    #[test]
+    fn rejects_too_many_entries() {
+        let dir = TempDir::new("limits-entries");
+        let archive = dir.join("many.tar");
+        tar_archive(&archive, &["a.txt", "b.txt", "c.txt"]);
+
+        let output = dir.join("out");
+        let limits = ExtractLimits {
+            max_entries: Some(2),
+            ..ExtractLimits::DEFAULT
+        };
+        let e = extract_tar(&archive, &output, limits).unwrap_err();
+        assert_eq!(e, LimitExceeded::Entries(2).to_string());
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(!output.join(name).exists(), "{}", name);
+        }
+
+        extract_tar(&archive, &output, ExtractLimits::DEFAULT).unwrap();
+        assert!(output.join("c.txt").exists());
+    }
+
+    // 嵌套过深的条目被拒绝，之前写出的条目被删除
+    #[test]
+    fn rejects_deep_entries() {
+        let dir = TempDir::new("limits-depth");
+        let archive = dir.join("deep.tar");
+        tar_archive(&archive, &["top.txt", "a/b/c/deep.txt"]);
+
+        let output = dir.join("out");
+        let limits = ExtractLimits {
+            max_depth: Some(3),
+            ..ExtractLimits::DEFAULT
+        };
+        let e = extract_tar(&archive, &output, limits).unwrap_err();
+        assert!(is_limit_error(&e), "{}", e);
+        assert_eq!(e, LimitExceeded::Depth(3).to_string());
+        assert!(!output.join("top.txt").exists());
+        assert!(!output.join("a/b/c/deep.txt").exists());
+    }
+}
@@ -1,61 +1,457 @@
 // Export compression format related modules
+pub mod atomic;
+pub mod bench;
+pub mod conflict;
+pub mod journal;
+pub mod limits;
+pub mod plugin;
+pub mod posix;
+pub mod preflight;
+pub mod reproducible;
+pub mod sanitize;
+pub mod smart;
 pub mod z_7zip;
+pub mod z_ar;
+pub mod z_brotli;
 pub mod z_bzip2;
+pub mod z_cpio;
+pub mod z_deb;
 pub mod z_gzip;
+pub mod z_iso;
+pub mod z_lz4;
+pub mod z_lzip;
+pub mod z_lzma;
 pub mod z_tar;
 pub mod z_xz;
 pub mod z_zip;
 pub mod z_zstd;
 
+#[cfg(test)]
+mod testing;
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use atomic::StagingDir;
+use conflict::{ConflictPolicy, ConflictResolver};
+use limits::ExtractLimits;
+use posix::TarMetadata;
+use sanitize::RenameLog;
+
 // Import compressor implementations
+use plugin::PluginCompressor;
 use z_7zip::SevenZipCompressor;
+use z_ar::ArReader;
+use z_brotli::BrotliCompressor;
 use z_bzip2::Bzip2Compressor;
+use z_cpio::CpioReader;
+use z_deb::DebReader;
 use z_gzip::GzipCompressor;
-use z_tar::TarCompressor;
+use z_iso::IsoReader;
+use z_lz4::Lz4Compressor;
+use z_lzip::LzipCompressor;
+use z_lzma::LzmaCompressor;
+use z_tar::{TarCodec, TarCompressor};
 use z_xz::XzCompressor;
 use z_zip::ZipCompressor;
 use z_zstd::ZstdCompressor;
 
-// Compression options
+pub use z_gzip::GzipHeaderInfo;
+pub use z_tar::TarFormat;
+pub use z_xz::{XzCheck, XzFilter};
+pub use z_zip::ZipNameEncoding;
+
+// Format-independent compression settings; each compression carries its own copy, so jobs
+// running at the same time never see each other's settings
+#[derive(Debug, Clone, Default)]
+pub struct CompressSettings {
+    // POSIX metadata recorded in tar archives
+    pub tar_metadata: TarMetadata,
+    // Sorted entries, fixed owners and modes, mtimes clamped to SOURCE_DATE_EPOCH
+    pub reproducible: bool,
+    // Header format of new tar archives
+    pub tar_format: TarFormat,
+}
+
+// Compression options, each carrying the settings of this compression
 pub enum CompressionOptions {
     Zip {
         level: u32,
         password: Option<String>,
+        settings: CompressSettings,
     },
     Tar {
         level: u32,
+        settings: CompressSettings,
     },
     Gzip {
         level: u32,
+        comment: Option<String>,
+        settings: CompressSettings,
     },
     Bzip2 {
         level: u32,
+        settings: CompressSettings,
     },
     Xz {
         level: u32,
+        extreme: bool,
+        filters: Vec<XzFilter>,
+        dict_size: Option<u32>,
+        check: XzCheck,
+        settings: CompressSettings,
     },
     Zstd {
-        level: u32,
+        level: i32,
+        dictionary: Option<String>,
+        long_distance_matching: bool,
+        window_log: Option<u32>,
+        checksum: bool,
+        settings: CompressSettings,
     },
     SevenZip {
         level: u32,
         password: Option<String>,
+        settings: CompressSettings,
+    },
+    Lz4 {
+        level: u32,
+        checksum: bool,
+        settings: CompressSettings,
+    },
+    Brotli {
+        quality: u32,
+        window_size: u32,
+        settings: CompressSettings,
+    },
+    Lzma {
+        level: u32,
+        settings: CompressSettings,
     },
+    Lzip {
+        level: u32,
+        settings: CompressSettings,
+    },
+    // Formats provided by external plugins
+    Plugin {
+        level: Option<i32>,
+        password: Option<String>,
+        settings: CompressSettings,
+    },
+}
+
+// Format-independent extraction settings, carried by each extraction like CompressSettings
+#[derive(Debug, Clone, Default)]
+pub struct ExtractSettings {
+    pub limits: ExtractLimits,
+    // Clean entry names by the rules of every system, not only this one
+    pub portable_names: bool,
+    // How POSIX metadata recorded in tar archives is restored
+    pub tar_metadata: TarMetadata,
+    // Entries renamed while cleaning names; the caller keeps a clone and writes the report
+    pub renames: RenameLog,
 }
 
-// Decompression options
+// Decompression options, each carrying how to handle files that already exist and the
+// settings of this extraction
+#[derive(Clone)]
 pub enum DecompressionOptions {
-    Zip { password: Option<String> },
-    Tar {},
-    Gzip {},
-    Bzip2 {},
-    Xz {},
-    Zstd {},
-    SevenZip { password: Option<String> },
+    Zip {
+        password: Option<String>,
+        encoding: ZipNameEncoding,
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Tar {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Gzip {
+        restore_name: bool,
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Bzip2 {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Xz {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Zstd {
+        dictionary: Option<String>,
+        window_log_max: Option<u32>,
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    SevenZip {
+        password: Option<String>,
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Lz4 {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Brotli {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Lzma {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Lzip {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    // Read-only archive formats (cpio, ar, deb, iso)
+    Archive {
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+    Plugin {
+        password: Option<String>,
+        conflict: ConflictPolicy,
+        settings: ExtractSettings,
+    },
+}
+
+impl DecompressionOptions {
+    // Conflict policy, shared by every format
+    pub fn conflict_policy(&self) -> ConflictPolicy {
+        match self {
+            DecompressionOptions::Zip { conflict, .. }
+            | DecompressionOptions::Tar { conflict, .. }
+            | DecompressionOptions::Gzip { conflict, .. }
+            | DecompressionOptions::Bzip2 { conflict, .. }
+            | DecompressionOptions::Xz { conflict, .. }
+            | DecompressionOptions::Zstd { conflict, .. }
+            | DecompressionOptions::SevenZip { conflict, .. }
+            | DecompressionOptions::Lz4 { conflict, .. }
+            | DecompressionOptions::Brotli { conflict, .. }
+            | DecompressionOptions::Lzma { conflict, .. }
+            | DecompressionOptions::Lzip { conflict, .. }
+            | DecompressionOptions::Archive { conflict, .. }
+            | DecompressionOptions::Plugin { conflict, .. } => *conflict,
+        }
+    }
+
+    pub fn set_conflict_policy(&mut self, policy: ConflictPolicy) {
+        match self {
+            DecompressionOptions::Zip { conflict, .. }
+            | DecompressionOptions::Tar { conflict, .. }
+            | DecompressionOptions::Gzip { conflict, .. }
+            | DecompressionOptions::Bzip2 { conflict, .. }
+            | DecompressionOptions::Xz { conflict, .. }
+            | DecompressionOptions::Zstd { conflict, .. }
+            | DecompressionOptions::SevenZip { conflict, .. }
+            | DecompressionOptions::Lz4 { conflict, .. }
+            | DecompressionOptions::Brotli { conflict, .. }
+            | DecompressionOptions::Lzma { conflict, .. }
+            | DecompressionOptions::Lzip { conflict, .. }
+            | DecompressionOptions::Archive { conflict, .. }
+            | DecompressionOptions::Plugin { conflict, .. } => *conflict = policy,
+        }
+    }
+
+    // Extraction settings, shared by every format
+    pub fn settings(&self) -> &ExtractSettings {
+        match self {
+            DecompressionOptions::Zip { settings, .. }
+            | DecompressionOptions::Tar { settings, .. }
+            | DecompressionOptions::Gzip { settings, .. }
+            | DecompressionOptions::Bzip2 { settings, .. }
+            | DecompressionOptions::Xz { settings, .. }
+            | DecompressionOptions::Zstd { settings, .. }
+            | DecompressionOptions::SevenZip { settings, .. }
+            | DecompressionOptions::Lz4 { settings, .. }
+            | DecompressionOptions::Brotli { settings, .. }
+            | DecompressionOptions::Lzma { settings, .. }
+            | DecompressionOptions::Lzip { settings, .. }
+            | DecompressionOptions::Archive { settings, .. }
+            | DecompressionOptions::Plugin { settings, .. } => settings,
+        }
+    }
+}
+
+// Format-independent compression presets, mapped to native settings by each compressor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionPreset {
+    Store,
+    Fastest,
+    Fast,
+    Normal,
+    High,
+    Ultra,
+}
+
+impl CompressionPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionPreset::Store => "store",
+            CompressionPreset::Fastest => "fastest",
+            CompressionPreset::Fast => "fast",
+            CompressionPreset::Normal => "normal",
+            CompressionPreset::High => "high",
+            CompressionPreset::Ultra => "ultra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all_presets()
+            .into_iter()
+            .find(|preset| preset.name() == name.to_lowercase())
+    }
+
+    pub fn all_presets() -> Vec<Self> {
+        vec![
+            CompressionPreset::Store,
+            CompressionPreset::Fastest,
+            CompressionPreset::Fast,
+            CompressionPreset::Normal,
+            CompressionPreset::High,
+            CompressionPreset::Ultra,
+        ]
+    }
+
+    // Pick a level from a per-preset table ordered store..ultra
+    pub fn pick<T: Copy>(&self, levels: [T; 6]) -> T {
+        levels[*self as usize]
+    }
+}
+
+impl CompressionOptions {
+    // The raw, format-specific level, if the format has one
+    pub fn level(&self) -> Option<i32> {
+        match self {
+            CompressionOptions::Zip { level, .. }
+            | CompressionOptions::Tar { level, .. }
+            | CompressionOptions::Gzip { level, .. }
+            | CompressionOptions::Bzip2 { level, .. }
+            | CompressionOptions::Xz { level, .. }
+            | CompressionOptions::SevenZip { level, .. }
+            | CompressionOptions::Lz4 { level, .. }
+            | CompressionOptions::Lzma { level, .. }
+            | CompressionOptions::Lzip { level, .. } => Some(*level as i32),
+            CompressionOptions::Brotli { quality, .. } => Some(*quality as i32),
+            CompressionOptions::Zstd { level, .. } => Some(*level),
+            CompressionOptions::Plugin { level, .. } => *level,
+        }
+    }
+
+    // Replace the level with a raw, format-specific value
+    pub fn set_level(&mut self, raw: i32) {
+        // Negative levels are only meaningful for ZSTD
+        let unsigned = raw.max(0) as u32;
+        match self {
+            CompressionOptions::Zip { level, .. }
+            | CompressionOptions::Tar { level, .. }
+            | CompressionOptions::Gzip { level, .. }
+            | CompressionOptions::Bzip2 { level, .. }
+            | CompressionOptions::Xz { level, .. }
+            | CompressionOptions::SevenZip { level, .. }
+            | CompressionOptions::Lz4 { level, .. }
+            | CompressionOptions::Lzma { level, .. }
+            | CompressionOptions::Lzip { level, .. } => *level = unsigned,
+            CompressionOptions::Brotli { quality, .. } => *quality = unsigned,
+            CompressionOptions::Zstd { level, .. } => *level = raw,
+            CompressionOptions::Plugin { level, .. } => *level = Some(raw),
+        }
+    }
+
+    // Compression settings, shared by every format
+    pub fn settings(&self) -> &CompressSettings {
+        match self {
+            CompressionOptions::Zip { settings, .. }
+            | CompressionOptions::Tar { settings, .. }
+            | CompressionOptions::Gzip { settings, .. }
+            | CompressionOptions::Bzip2 { settings, .. }
+            | CompressionOptions::Xz { settings, .. }
+            | CompressionOptions::Zstd { settings, .. }
+            | CompressionOptions::SevenZip { settings, .. }
+            | CompressionOptions::Lz4 { settings, .. }
+            | CompressionOptions::Brotli { settings, .. }
+            | CompressionOptions::Lzma { settings, .. }
+            | CompressionOptions::Lzip { settings, .. }
+            | CompressionOptions::Plugin { settings, .. } => settings,
+        }
+    }
+
+    pub fn set_settings(&mut self, new_settings: CompressSettings) {
+        match self {
+            CompressionOptions::Zip { settings, .. }
+            | CompressionOptions::Tar { settings, .. }
+            | CompressionOptions::Gzip { settings, .. }
+            | CompressionOptions::Bzip2 { settings, .. }
+            | CompressionOptions::Xz { settings, .. }
+            | CompressionOptions::Zstd { settings, .. }
+            | CompressionOptions::SevenZip { settings, .. }
+            | CompressionOptions::Lz4 { settings, .. }
+            | CompressionOptions::Brotli { settings, .. }
+            | CompressionOptions::Lzma { settings, .. }
+            | CompressionOptions::Lzip { settings, .. }
+            | CompressionOptions::Plugin { settings, .. } => *settings = new_settings,
+        }
+    }
+}
+
+// Range of compression levels a format accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelRange {
+    pub min: i32,
+    pub max: i32,
+    pub default: i32,
+}
+
+// What a compressor can do, as declared by the compressor itself
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub can_compress: bool,
+    pub can_decompress: bool,
+    // Archives hold many entries, streams wrap a single file
+    pub is_archive: bool,
+    // Whether several input paths can go into one output
+    pub multi_input: bool,
+    pub supports_password: bool,
+    pub level: Option<LevelRange>,
+    // Compression methods used when writing
+    pub methods: Vec<&'static str>,
+    // Option fields honored from CompressionOptions / DecompressionOptions
+    pub compress_options: Vec<&'static str>,
+    pub decompress_options: Vec<&'static str>,
+}
+
+impl Capabilities {
+    // Read-only archive formats (listing and extraction only)
+    pub fn read_only_archive() -> Self {
+        Capabilities {
+            can_compress: false,
+            can_decompress: true,
+            is_archive: true,
+            multi_input: false,
+            supports_password: false,
+            level: None,
+            methods: Vec::new(),
+            compress_options: Vec::new(),
+            decompress_options: Vec::new(),
+        }
+    }
 }
 
 // Compression/decompression interface
 pub trait ComdeAble {
+    fn capabilities(&self) -> Capabilities;
+
+    // Native options for a preset; None for formats that cannot compress
+    fn preset_options(&self, _preset: CompressionPreset) -> Option<CompressionOptions> {
+        None
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -71,6 +467,89 @@ pub trait ComdeAble {
     ) -> Result<(), String>;
 }
 
+// Archive entry as shown when listing an archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub mtime: Option<u64>,
+}
+
+// Listing and selective extraction interface for archive formats
+pub trait ArchiveReader {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String>;
+
+    // Extract the selected entries; an empty selection extracts everything.
+    // Files that already exist are handled by the conflict resolver
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String>;
+}
+
+// Normalize an entry path for listing and matching ("./usr/bin/" -> "usr/bin")
+pub fn normalize_entry_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Whether an entry is selected; selecting a directory selects everything below it
+pub fn is_selected(path: &str, selection: &[String]) -> bool {
+    if selection.is_empty() {
+        return true;
+    }
+
+    let path = normalize_entry_path(path);
+    selection.iter().any(|selected| {
+        let selected = normalize_entry_path(selected);
+        path == selected || path.starts_with(&format!("{}/", selected))
+    })
+}
+
+// Join an entry path to the output directory, rejecting paths that would escape it
+pub fn safe_join(output_dir: &Path, path: &str) -> Option<PathBuf> {
+    let mut joined = output_dir.to_path_buf();
+    for component in Path::new(&normalize_entry_path(path)).components() {
+        match component {
+            Component::Normal(part) => joined.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if joined == output_dir {
+        return None;
+    }
+    Some(joined)
+}
+
+// Whether writing target stays inside output_dir once symlinks among its existing parents are
+// resolved; an earlier entry may have created a link pointing out of the output directory
+pub fn stays_inside(output_dir: &Path, target: &Path) -> bool {
+    let mut ancestor = target.parent();
+    while let Some(dir) = ancestor {
+        // Nothing below the output directory exists yet
+        if dir == output_dir || !dir.starts_with(output_dir) {
+            return true;
+        }
+        if fs::symlink_metadata(dir).is_ok() {
+            return match (dir.canonicalize(), output_dir.canonicalize()) {
+                (Ok(real), Ok(root)) => real.starts_with(root),
+                _ => false,
+            };
+        }
+        ancestor = dir.parent();
+    }
+    true
+}
+
 // Supported compression formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CompressionFormat {
@@ -81,6 +560,23 @@ pub enum CompressionFormat {
     Xz,
     Zstd,
     SevenZip,
+    Lz4,
+    TarLz4,
+    Brotli,
+    Lzma,
+    Lzip,
+    TarLzma,
+    TarLzip,
+    TarGz,
+    TarBz2,
+    TarXz,
+    TarZst,
+    Cpio,
+    Ar,
+    Deb,
+    Iso,
+    // Format provided by an external plugin, indexing plugin::plugin_formats()
+    Plugin(usize),
 }
 
 impl CompressionFormat {
@@ -94,11 +590,32 @@ impl CompressionFormat {
             CompressionFormat::Xz => "xz",
             CompressionFormat::Zstd => "zst",
             CompressionFormat::SevenZip => "7z",
+            CompressionFormat::Lz4 => "lz4",
+            CompressionFormat::TarLz4 => "tar.lz4",
+            CompressionFormat::Brotli => "br",
+            CompressionFormat::Lzma => "lzma",
+            CompressionFormat::Lzip => "lz",
+            CompressionFormat::TarLzma => "tar.lzma",
+            CompressionFormat::TarLzip => "tar.lz",
+            CompressionFormat::TarGz => "tar.gz",
+            CompressionFormat::TarBz2 => "tar.bz2",
+            CompressionFormat::TarXz => "tar.xz",
+            CompressionFormat::TarZst => "tar.zst",
+            CompressionFormat::Cpio => "cpio",
+            CompressionFormat::Ar => "ar",
+            CompressionFormat::Deb => "deb",
+            CompressionFormat::Iso => "iso",
+            CompressionFormat::Plugin(index) => &plugin::plugin_formats()[*index].extension,
         }
     }
 
-    // Get format from file extension
+    // Get format from file extension, built-in formats take precedence over plugins
     pub fn from_extension(ext: &str) -> Option<Self> {
+        Self::builtin_from_extension(ext).or_else(|| plugin::find_plugin(ext).map(Self::Plugin))
+    }
+
+    // Get a built-in format from file extension
+    pub fn builtin_from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
             "zip" => Some(Self::Zip),
             "tar" => Some(Self::Tar),
@@ -107,12 +624,107 @@ impl CompressionFormat {
             "xz" => Some(Self::Xz),
             "zst" | "zstd" => Some(Self::Zstd),
             "7z" => Some(Self::SevenZip),
+            "lz4" => Some(Self::Lz4),
+            "tar.lz4" | "tlz4" => Some(Self::TarLz4),
+            "br" | "brotli" => Some(Self::Brotli),
+            "lzma" => Some(Self::Lzma),
+            "lz" | "lzip" => Some(Self::Lzip),
+            "tar.lzma" | "tlz" => Some(Self::TarLzma),
+            "tar.lz" => Some(Self::TarLzip),
+            "tar.gz" | "tgz" => Some(Self::TarGz),
+            "tar.bz2" | "tbz2" | "tbz" => Some(Self::TarBz2),
+            "tar.xz" | "txz" => Some(Self::TarXz),
+            "tar.zst" | "tzst" => Some(Self::TarZst),
+            "cpio" => Some(Self::Cpio),
+            "ar" | "a" => Some(Self::Ar),
+            "deb" | "udeb" => Some(Self::Deb),
+            "iso" => Some(Self::Iso),
             _ => None,
         }
     }
 
-    // Get all supported formats
+    // Get format from a file path, matching compound tarball suffixes like .tar.lz4 first
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?.to_lowercase();
+
+        for format in Self::builtin_formats() {
+            if format.is_compound() && file_name.ends_with(&format!(".{}", format.extension())) {
+                return Some(format);
+            }
+        }
+
+        let ext = Path::new(&file_name).extension()?.to_str()?;
+        Self::from_extension(ext)
+            .or_else(|| plugin::find_plugin_by_name(&file_name).map(Self::Plugin))
+    }
+
+    // Get format from the path, falling back to the leading bytes for archives
+    // that usually come without an extension (e.g. initramfs images)
+    pub fn detect(path: &Path) -> Option<Self> {
+        Self::from_path(path).or_else(|| {
+            let mut file = File::open(path).ok()?;
+            let mut magic = [0u8; 8];
+            let len = file.read(&mut magic).ok()?;
+            let magic = &magic[..len];
+
+            if magic.starts_with(b"!<arch>\n") {
+                return Some(Self::Ar);
+            }
+            if magic.starts_with(b"0707") {
+                return Some(Self::Cpio);
+            }
+
+            // ISO 9660 volume descriptors start at sector 16
+            let mut identifier = [0u8; 5];
+            if file.seek(SeekFrom::Start(16 * 2048 + 1)).is_ok()
+                && file.read_exact(&mut identifier).is_ok()
+                && &identifier == b"CD001"
+            {
+                return Some(Self::Iso);
+            }
+
+            // Finally let plugins inspect the content
+            plugin::detect_plugin(path).map(Self::Plugin)
+        })
+    }
+
+    // Whether the format is a tar archive wrapped in a stream compressor
+    pub fn is_compound(&self) -> bool {
+        matches!(
+            self,
+            CompressionFormat::TarLz4
+                | CompressionFormat::TarLzma
+                | CompressionFormat::TarLzip
+                | CompressionFormat::TarGz
+                | CompressionFormat::TarBz2
+                | CompressionFormat::TarXz
+                | CompressionFormat::TarZst
+        )
+    }
+
+    // Whether the format can only be read, not created
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            CompressionFormat::Plugin(index) => !plugin::plugin_formats()[*index].can_compress,
+            _ => matches!(
+                self,
+                CompressionFormat::Cpio
+                    | CompressionFormat::Ar
+                    | CompressionFormat::Deb
+                    | CompressionFormat::Iso
+            ),
+        }
+    }
+
+    // Get all supported formats, followed by plugin formats
     pub fn all_formats() -> Vec<Self> {
+        let mut formats = Self::builtin_formats();
+        formats.extend((0..plugin::plugin_formats().len()).map(Self::Plugin));
+        formats
+    }
+
+    // Get the built-in formats only, without starting any plugin
+    pub fn builtin_formats() -> Vec<Self> {
         vec![
             Self::Zip,
             Self::Tar,
@@ -121,6 +733,21 @@ impl CompressionFormat {
             Self::Xz,
             Self::Zstd,
             Self::SevenZip,
+            Self::Lz4,
+            Self::TarLz4,
+            Self::Brotli,
+            Self::Lzma,
+            Self::Lzip,
+            Self::TarLzma,
+            Self::TarLzip,
+            Self::TarGz,
+            Self::TarBz2,
+            Self::TarXz,
+            Self::TarZst,
+            Self::Cpio,
+            Self::Ar,
+            Self::Deb,
+            Self::Iso,
         ]
     }
 
@@ -134,6 +761,22 @@ impl CompressionFormat {
             CompressionFormat::Xz => "XZ",
             CompressionFormat::Zstd => "ZSTD",
             CompressionFormat::SevenZip => "7ZIP",
+            CompressionFormat::Lz4 => "LZ4",
+            CompressionFormat::TarLz4 => "TAR.LZ4",
+            CompressionFormat::Brotli => "BROTLI",
+            CompressionFormat::Lzma => "LZMA",
+            CompressionFormat::Lzip => "LZIP",
+            CompressionFormat::TarLzma => "TAR.LZMA",
+            CompressionFormat::TarLzip => "TAR.LZ",
+            CompressionFormat::TarGz => "TAR.GZ",
+            CompressionFormat::TarBz2 => "TAR.BZ2",
+            CompressionFormat::TarXz => "TAR.XZ",
+            CompressionFormat::TarZst => "TAR.ZST",
+            CompressionFormat::Cpio => "CPIO",
+            CompressionFormat::Ar => "AR",
+            CompressionFormat::Deb => "DEB",
+            CompressionFormat::Iso => "ISO",
+            CompressionFormat::Plugin(index) => &plugin::plugin_formats()[*index].name,
         }
     }
 }
@@ -148,6 +791,15 @@ pub enum Compressor {
     Xz(XzCompressor),
     Zstd(ZstdCompressor),
     SevenZip(SevenZipCompressor),
+    Lz4(Lz4Compressor),
+    Brotli(BrotliCompressor),
+    Lzma(LzmaCompressor),
+    Lzip(LzipCompressor),
+    Cpio(CpioReader),
+    Ar(ArReader),
+    Deb(DebReader),
+    Iso(IsoReader),
+    Plugin(PluginCompressor),
 }
 
 impl Compressor {
@@ -161,6 +813,22 @@ impl Compressor {
             CompressionFormat::Xz => Self::Xz(XzCompressor::new()),
             CompressionFormat::Zstd => Self::Zstd(ZstdCompressor::new()),
             CompressionFormat::SevenZip => Self::SevenZip(SevenZipCompressor::new()),
+            CompressionFormat::Lz4 => Self::Lz4(Lz4Compressor::new()),
+            CompressionFormat::TarLz4 => Self::Tar(TarCompressor::with_codec(TarCodec::Lz4)),
+            CompressionFormat::Brotli => Self::Brotli(BrotliCompressor::new()),
+            CompressionFormat::Lzma => Self::Lzma(LzmaCompressor::new()),
+            CompressionFormat::Lzip => Self::Lzip(LzipCompressor::new()),
+            CompressionFormat::TarLzma => Self::Tar(TarCompressor::with_codec(TarCodec::Lzma)),
+            CompressionFormat::TarLzip => Self::Tar(TarCompressor::with_codec(TarCodec::Lzip)),
+            CompressionFormat::TarGz => Self::Tar(TarCompressor::with_codec(TarCodec::Gzip)),
+            CompressionFormat::TarBz2 => Self::Tar(TarCompressor::with_codec(TarCodec::Bzip2)),
+            CompressionFormat::TarXz => Self::Tar(TarCompressor::with_codec(TarCodec::Xz)),
+            CompressionFormat::TarZst => Self::Tar(TarCompressor::with_codec(TarCodec::Zstd)),
+            CompressionFormat::Cpio => Self::Cpio(CpioReader::new()),
+            CompressionFormat::Ar => Self::Ar(ArReader::new()),
+            CompressionFormat::Deb => Self::Deb(DebReader::new()),
+            CompressionFormat::Iso => Self::Iso(IsoReader::new()),
+            CompressionFormat::Plugin(index) => Self::Plugin(PluginCompressor::new(index)),
         }
     }
 
@@ -179,6 +847,15 @@ impl Compressor {
             Self::Xz(c) => c.compress(input_paths, output_path, options).await,
             Self::Zstd(c) => c.compress(input_paths, output_path, options).await,
             Self::SevenZip(c) => c.compress(input_paths, output_path, options).await,
+            Self::Lz4(c) => c.compress(input_paths, output_path, options).await,
+            Self::Brotli(c) => c.compress(input_paths, output_path, options).await,
+            Self::Lzma(c) => c.compress(input_paths, output_path, options).await,
+            Self::Lzip(c) => c.compress(input_paths, output_path, options).await,
+            Self::Cpio(c) => c.compress(input_paths, output_path, options).await,
+            Self::Ar(c) => c.compress(input_paths, output_path, options).await,
+            Self::Deb(c) => c.compress(input_paths, output_path, options).await,
+            Self::Iso(c) => c.compress(input_paths, output_path, options).await,
+            Self::Plugin(c) => c.compress(input_paths, output_path, options).await,
         }
     }
 
@@ -197,6 +874,136 @@ impl Compressor {
             Self::Xz(c) => c.decompress(input_paths, output_path, options).await,
             Self::Zstd(c) => c.decompress(input_paths, output_path, options).await,
             Self::SevenZip(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Lz4(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Brotli(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Lzma(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Lzip(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Cpio(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Ar(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Deb(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Iso(c) => c.decompress(input_paths, output_path, options).await,
+            Self::Plugin(c) => c.decompress(input_paths, output_path, options).await,
+        }
+    }
+
+    // Decompress into a staging directory inside output_path and move the entries in
+    // only once every input has been extracted, so a failure leaves output_path untouched
+    pub async fn decompress_staged(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        mut options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        // Conflicts are handled when moving entries in, the staging directory starts empty
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        if let Some(options) = options.as_mut() {
+            options.set_conflict_policy(ConflictPolicy::Overwrite);
+        }
+
+        let output_dir = Path::new(output_path);
+        let staging = StagingDir::create(output_dir)?;
+        self.decompress(input_paths, &staging.path().to_string_lossy(), options)
+            .await?;
+        staging.commit(output_dir, &mut conflict)
+    }
+
+    // Decompress with a journal of finished entries in output_path; running it again after
+    // an interruption skips the entries that are already intact on disk
+    pub async fn decompress_resumable(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        match self {
+            Self::Zip(c) => c.decompress_resumable(input_paths, output_path, options),
+            Self::Tar(c) => c.decompress_resumable(input_paths, output_path, options),
+            _ => Err("该格式不支持断点续解，仅支持zip和tar系列格式".to_string()),
+        }
+    }
+
+    // Capabilities declared by the underlying compressor
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Self::Zip(c) => c.capabilities(),
+            Self::Tar(c) => c.capabilities(),
+            Self::Gzip(c) => c.capabilities(),
+            Self::Bzip2(c) => c.capabilities(),
+            Self::Xz(c) => c.capabilities(),
+            Self::Zstd(c) => c.capabilities(),
+            Self::SevenZip(c) => c.capabilities(),
+            Self::Lz4(c) => c.capabilities(),
+            Self::Brotli(c) => c.capabilities(),
+            Self::Lzma(c) => c.capabilities(),
+            Self::Lzip(c) => c.capabilities(),
+            Self::Cpio(c) => c.capabilities(),
+            Self::Ar(c) => c.capabilities(),
+            Self::Deb(c) => c.capabilities(),
+            Self::Iso(c) => c.capabilities(),
+            Self::Plugin(c) => c.capabilities(),
+        }
+    }
+
+    // Native compression options for a preset
+    pub fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        match self {
+            Self::Zip(c) => c.preset_options(preset),
+            Self::Tar(c) => c.preset_options(preset),
+            Self::Gzip(c) => c.preset_options(preset),
+            Self::Bzip2(c) => c.preset_options(preset),
+            Self::Xz(c) => c.preset_options(preset),
+            Self::Zstd(c) => c.preset_options(preset),
+            Self::SevenZip(c) => c.preset_options(preset),
+            Self::Lz4(c) => c.preset_options(preset),
+            Self::Brotli(c) => c.preset_options(preset),
+            Self::Lzma(c) => c.preset_options(preset),
+            Self::Lzip(c) => c.preset_options(preset),
+            Self::Cpio(c) => c.preset_options(preset),
+            Self::Ar(c) => c.preset_options(preset),
+            Self::Deb(c) => c.preset_options(preset),
+            Self::Iso(c) => c.preset_options(preset),
+            Self::Plugin(c) => c.preset_options(preset),
+        }
+    }
+
+    // Capabilities of the built-in formats, in display order; help text uses this so
+    // printing it never starts a plugin
+    pub fn builtin_capabilities() -> Vec<(CompressionFormat, Capabilities)> {
+        CompressionFormat::builtin_formats()
+            .into_iter()
+            .map(|format| (format, Self::new(format).capabilities()))
+            .collect()
+    }
+
+    // List archive contents, for formats that support it
+    pub fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        match self {
+            Self::Tar(c) => c.list(archive_path),
+            Self::Cpio(c) => c.list(archive_path),
+            Self::Ar(c) => c.list(archive_path),
+            Self::Deb(c) => c.list(archive_path),
+            Self::Iso(c) => c.list(archive_path),
+            Self::Plugin(c) => c.list(archive_path),
+            _ => Err("该格式不支持查看内容".to_string()),
+        }
+    }
+
+    // Extract selected entries, for formats that support it
+    pub fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        match self {
+            Self::Tar(c) => c.extract(archive_path, output_path, selection, conflict),
+            Self::Cpio(c) => c.extract(archive_path, output_path, selection, conflict),
+            Self::Ar(c) => c.extract(archive_path, output_path, selection, conflict),
+            Self::Deb(c) => c.extract(archive_path, output_path, selection, conflict),
+            Self::Iso(c) => c.extract(archive_path, output_path, selection, conflict),
+            Self::Plugin(c) => c.extract(archive_path, output_path, selection, conflict),
+            _ => Err("该格式不支持选择性解压".to_string()),
         }
     }
 }
@@ -0,0 +1,606 @@
+// 外部格式插件
+//
+// 插件是放在插件目录中的可执行文件，默认目录为程序所在目录下的 plugins，
+// 可通过环境变量 ZIPHERE_PLUGINS_DIR 指定。
+//
+// 通信协议 (JSON over stdio，版本1):
+// 每次调用启动一次插件进程，不带命令行参数；向标准输入写入一个JSON请求后关闭，
+// 插件向标准输出写入一个JSON响应后退出。所有请求都包含 "version": 1 与 "command"。
+// 插件以非零状态退出或响应中包含 "error" 字段时视为失败，错误信息取自 "error" 或标准错误输出。
+// info、detect和list须在限定时间内完成，超时的插件进程会被终止；extract和compress的耗时
+// 取决于数据量，不设上限。
+//
+// info的结果缓存在插件目录的 .ziphere-cache.json 中，插件文件的大小和修改时间不变时
+// 不再启动插件询问，程序启动时不必逐个运行插件。
+//
+// info      请求: {}
+//           响应: {"formats": [{"extension": "rar", "name": "RAR", "aliases": [],
+//                   "can_compress": false, "can_decompress": true, "is_archive": true,
+//                   "multi_input": false, "supports_password": true,
+//                   "level": null 或 {"min": 0, "max": 5, "default": 3},
+//                   "methods": ["rar"]}]}
+// detect    请求: {"path": "..."}
+//           响应: {"format": "rar"} 或 {"format": null}
+// list      请求: {"format": "rar", "path": "...", "password": null}
+//           响应: {"entries": [{"path": "a/b.txt", "size": 12, "is_dir": false, "mtime": 1700000000}]}
+// extract   请求: {"format": "rar", "path": "...", "output": "...", "selection": [], "password": null}
+//           响应: {}  (selection为空时解压全部，选中目录时包含其下所有条目)
+// compress  请求: {"format": "rar", "inputs": ["..."], "output": "...", "level": null, "password": null}
+//           响应: {}
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use super::atomic::{AtomicFile, StagingDir};
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::{
+    ArchiveEntry, ArchiveReader, Capabilities, ComdeAble, CompressionFormat, CompressionOptions,
+    CompressionPreset, DecompressionOptions, LevelRange,
+};
+
+// 协议版本
+const PROTOCOL_VERSION: u32 = 1;
+
+// 插件目录的环境变量
+const PLUGINS_DIR_ENV: &str = "ZIPHERE_PLUGINS_DIR";
+
+// 插件信息缓存的文件名，位于插件目录中
+const CACHE_FILE: &str = ".ziphere-cache.json";
+
+// info和detect的超时时间
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// list的超时时间
+const LIST_TIMEOUT: Duration = Duration::from_secs(120);
+
+// 等待插件退出时的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// 插件在info响应中声明的格式
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginFormat {
+    pub extension: String,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub can_compress: bool,
+    #[serde(default = "default_true")]
+    pub can_decompress: bool,
+    #[serde(default = "default_true")]
+    pub is_archive: bool,
+    #[serde(default)]
+    pub multi_input: bool,
+    #[serde(default)]
+    pub supports_password: bool,
+    #[serde(default)]
+    pub level: Option<LevelRange>,
+    #[serde(default)]
+    pub methods: Vec<String>,
+    // 提供该格式的插件程序
+    #[serde(skip)]
+    pub program: PathBuf,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl PluginFormat {
+    fn matches(&self, ext: &str) -> bool {
+        self.extension.eq_ignore_ascii_case(ext)
+            || self
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(ext))
+    }
+}
+
+#[derive(Deserialize)]
+struct InfoResponse {
+    formats: Vec<PluginFormat>,
+}
+
+// 缓存的一次成功info调用，插件文件的大小和修改时间都不变时有效
+#[derive(Serialize, Deserialize)]
+struct CachedInfo {
+    size: u64,
+    modified: u128,
+    response: Value,
+}
+
+#[derive(Deserialize)]
+struct DetectResponse {
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListResponse {
+    entries: Vec<PluginEntry>,
+}
+
+#[derive(Deserialize)]
+struct PluginEntry {
+    path: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    is_dir: bool,
+    #[serde(default)]
+    mtime: Option<u64>,
+}
+
+// 已发现的插件格式，进程内只扫描一次
+static REGISTRY: OnceLock<Vec<PluginFormat>> = OnceLock::new();
+
+// 所有插件格式，按插件文件名排序
+pub fn plugin_formats() -> &'static [PluginFormat] {
+    REGISTRY.get_or_init(discover)
+}
+
+// 插件目录
+pub fn plugins_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(PLUGINS_DIR_ENV) {
+        return Some(PathBuf::from(dir));
+    }
+
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.parent()?.join("plugins"))
+}
+
+// 根据扩展名或别名查找插件格式
+pub fn find_plugin(ext: &str) -> Option<usize> {
+    plugin_formats()
+        .iter()
+        .position(|format| format.matches(ext))
+}
+
+// 根据文件名后缀查找插件格式 (支持 "tar.foo" 这类带点的扩展名)
+pub fn find_plugin_by_name(file_name: &str) -> Option<usize> {
+    plugin_formats().iter().position(|format| {
+        std::iter::once(&format.extension)
+            .chain(format.aliases.iter())
+            .any(|ext| file_name.ends_with(&format!(".{}", ext.to_lowercase())))
+    })
+}
+
+// 依次询问插件能否识别文件内容
+pub fn detect_plugin(path: &Path) -> Option<usize> {
+    let formats = plugin_formats();
+    let mut asked = Vec::new();
+
+    for format in formats.iter().filter(|format| format.can_decompress) {
+        if asked.contains(&&format.program) {
+            continue;
+        }
+        asked.push(&format.program);
+
+        let Ok(response) = call(&format.program, "detect", json!({ "path": path })) else {
+            continue;
+        };
+        let Ok(detected) = serde_json::from_value::<DetectResponse>(response) else {
+            continue;
+        };
+        if let Some(ext) = detected.format {
+            if let Some(index) = formats.iter().position(|candidate| {
+                candidate.program == format.program && candidate.matches(&ext)
+            }) {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+// 扫描插件目录，忽略无法启动或响应无效的插件
+fn discover() -> Vec<PluginFormat> {
+    let Some(dir) = plugins_dir() else {
+        return Vec::new();
+    };
+    let Ok(read_dir) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut programs: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_executable(path))
+        .collect();
+    programs.sort();
+
+    let cache_path = dir.join(CACHE_FILE);
+    let mut cache: HashMap<String, CachedInfo> = fs::read(&cache_path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default();
+    let mut cache_changed = false;
+
+    let mut formats: Vec<PluginFormat> = Vec::new();
+    for program in programs {
+        let Some((size, modified)) = file_stamp(&program) else {
+            continue;
+        };
+        let key = program.to_string_lossy().to_string();
+        let cached = cache
+            .get(&key)
+            .filter(|cached| cached.size == size && cached.modified == modified);
+        let response = match cached {
+            Some(cached) => Some(cached.response.clone()),
+            // 失败或超时的调用不缓存，下次启动时重试
+            None => {
+                let response = call(&program, "info", json!({})).ok();
+                if let Some(response) = &response {
+                    cache.insert(
+                        key,
+                        CachedInfo {
+                            size,
+                            modified,
+                            response: response.clone(),
+                        },
+                    );
+                    cache_changed = true;
+                }
+                response
+            }
+        };
+
+        let Some(info) =
+            response.and_then(|response| serde_json::from_value::<InfoResponse>(response).ok())
+        else {
+            continue;
+        };
+
+        for mut format in info.formats {
+            // 内置格式和先注册的插件优先
+            if format.extension.is_empty()
+                || CompressionFormat::builtin_from_extension(&format.extension).is_some()
+                || formats.iter().any(|known| known.matches(&format.extension))
+            {
+                continue;
+            }
+            format.extension = format.extension.to_lowercase();
+            format.program = program.clone();
+            formats.push(format);
+        }
+    }
+
+    // 插件目录不可写时每次启动重新询问
+    if cache_changed {
+        cache.retain(|key, _| Path::new(key).exists());
+        if let Ok(data) = serde_json::to_vec(&cache) {
+            let _ = fs::write(&cache_path, data);
+        }
+    }
+
+    formats
+}
+
+// 文件的大小和修改时间 (纳秒)，用于判断插件是否更新
+fn file_stamp(path: &Path) -> Option<(u64, u128)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_nanos()))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| matches!(ext.to_lowercase().as_str(), "exe" | "bat" | "cmd"))
+            .unwrap_or(false)
+}
+
+// 各命令的超时时间，None表示不限
+fn command_timeout(command: &str) -> Option<Duration> {
+    match command {
+        "info" | "detect" => Some(QUERY_TIMEOUT),
+        "list" => Some(LIST_TIMEOUT),
+        _ => None,
+    }
+}
+
+// 执行一次插件调用
+fn call(program: &Path, command: &str, mut request: Value) -> Result<Value, String> {
+    request["version"] = json!(PROTOCOL_VERSION);
+    request["command"] = json!(command);
+
+    let mut child = Command::new(program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法启动插件 {}: {}", program.display(), e))?;
+
+    // 输出在后台线程中读取，插件写满管道时不会因等待而卡住
+    let stdout = child.stdout.take().map(read_pipe);
+    let stderr = child.stderr.take().map(read_pipe);
+
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = stdin.write_all(request.to_string().as_bytes()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("无法向插件写入请求: {}", e));
+        }
+    }
+
+    let status = wait_child(&mut child, program, command_timeout(command))?;
+    let stdout = stdout
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    let stderr = stderr
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    let response: Option<Value> = serde_json::from_slice(&stdout).ok();
+
+    if let Some(error) = response
+        .as_ref()
+        .and_then(|response| response.get("error"))
+        .and_then(|error| error.as_str())
+    {
+        return Err(format!("插件错误: {}", error));
+    }
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(format!("插件执行失败 ({}): {}", status, stderr.trim()));
+    }
+
+    response.ok_or_else(|| format!("插件 {} 返回了无效的响应", program.display()))
+}
+
+// 在后台线程中读完管道
+fn read_pipe<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = pipe.read_to_end(&mut data);
+        data
+    })
+}
+
+// 等待插件退出，超时时终止进程
+fn wait_child(
+    child: &mut Child,
+    program: &Path,
+    timeout: Option<Duration>,
+) -> Result<ExitStatus, String> {
+    let Some(timeout) = timeout else {
+        return child.wait().map_err(|e| e.to_string());
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!(
+                "插件 {} 在{}秒内没有响应，已终止",
+                program.display(),
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// 由插件提供的格式
+#[derive(Clone)]
+pub struct PluginCompressor {
+    index: usize,
+}
+
+impl PluginCompressor {
+    pub fn new(index: usize) -> Self {
+        PluginCompressor { index }
+    }
+
+    fn format(&self) -> &'static PluginFormat {
+        &plugin_formats()[self.index]
+    }
+
+    fn call(&self, command: &str, mut request: Value) -> Result<Value, String> {
+        let format = self.format();
+        request["format"] = json!(format.extension);
+        call(&format.program, command, request)
+    }
+
+    fn list_with_password(
+        &self,
+        archive_path: &str,
+        password: Option<&str>,
+    ) -> Result<Vec<ArchiveEntry>, String> {
+        let response = self.call(
+            "list",
+            json!({ "path": archive_path, "password": password }),
+        )?;
+        let listing: ListResponse =
+            serde_json::from_value(response).map_err(|e| format!("插件返回了无效的列表: {}", e))?;
+
+        Ok(listing
+            .entries
+            .into_iter()
+            .map(|entry| ArchiveEntry {
+                path: entry.path,
+                size: entry.size,
+                is_dir: entry.is_dir,
+                mtime: entry.mtime,
+            })
+            .collect())
+    }
+
+    fn extract_with_password(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        password: Option<&str>,
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        // 插件直接写文件，先解压到输出目录下的暂存目录，再逐个移入并处理冲突
+        let output_dir = Path::new(output_path);
+        let staging = StagingDir::create(output_dir)?;
+
+        self.call(
+            "extract",
+            json!({
+                "path": archive_path,
+                "output": staging.path(),
+                "selection": selection,
+                "password": password,
+            }),
+        )?;
+        // 插件在外部进程中解码，无法逐块检查，移入前按解压限制检查暂存的结果
+        Limiter::new(archive_path, conflict.settings().limits).check_dir(staging.path())?;
+        staging.commit(output_dir, conflict)
+    }
+}
+
+impl ArchiveReader for PluginCompressor {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        self.list_with_password(archive_path, None)
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        self.extract_with_password(archive_path, output_path, selection, None, conflict)
+    }
+}
+
+impl ComdeAble for PluginCompressor {
+    fn capabilities(&self) -> Capabilities {
+        let format = self.format();
+
+        let mut compress_options = Vec::new();
+        if format.level.is_some() {
+            compress_options.push("level");
+        }
+        if format.supports_password {
+            compress_options.push("password");
+        }
+
+        Capabilities {
+            can_compress: format.can_compress,
+            can_decompress: format.can_decompress,
+            is_archive: format.is_archive,
+            multi_input: format.multi_input,
+            supports_password: format.supports_password,
+            level: format.level,
+            methods: format
+                .methods
+                .iter()
+                .map(|method| method.as_str())
+                .collect(),
+            compress_options,
+            decompress_options: if format.supports_password {
+                vec!["password"]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        let format = self.format();
+        if !format.can_compress {
+            return None;
+        }
+
+        // 在插件声明的级别范围内按预设取值
+        let level = format.level.map(|level| {
+            preset.pick([
+                level.min,
+                level.min,
+                (level.min + level.default) / 2,
+                level.default,
+                (level.default + level.max) / 2,
+                level.max,
+            ])
+        });
+        Some(CompressionOptions::Plugin {
+            level,
+            password: None,
+            settings: Default::default(),
+        })
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        if !self.format().can_compress {
+            return Err(format!("{}为只读格式，不支持压缩", self.format().name));
+        }
+
+        let (level, password) = match options {
+            Some(CompressionOptions::Plugin {
+                level, password, ..
+            }) => (level, password),
+            _ => (self.format().level.map(|level| level.default), None),
+        };
+
+        // 插件写入临时文件，成功后才移动到最终位置
+        let output_file = AtomicFile::create(output_path)?;
+        self.call(
+            "compress",
+            json!({
+                "inputs": input_paths,
+                "output": output_file.temp_path(),
+                "level": level,
+                "password": password,
+            }),
+        )?;
+        output_file.commit()
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        let password = match options {
+            Some(DecompressionOptions::Plugin { password, .. }) => password,
+            _ => None,
+        };
+
+        for input_path in input_paths {
+            self.extract_with_password(
+                input_path,
+                output_path,
+                &[],
+                password.as_deref(),
+                &mut conflict,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,763 @@
+use filetime::FileTime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tar::{Archive, Entry, Header};
+
+// PAX扩展头中保存扩展属性和ACL的键，与GNU tar、star、bsdtar相同
+const PAX_XATTR_PREFIX: &str = "SCHILY.xattr.";
+const PAX_ACL_ACCESS: &str = "SCHILY.acl.access";
+const PAX_ACL_DEFAULT: &str = "SCHILY.acl.default";
+const PAX_UNAME: &str = "uname";
+const PAX_GNAME: &str = "gname";
+const PAX_MTIME: &str = "mtime";
+
+// Linux以扩展属性的形式保存POSIX ACL
+const XATTR_ACL_ACCESS: &str = "system.posix_acl_access";
+const XATTR_ACL_DEFAULT: &str = "system.posix_acl_default";
+
+// 所有者映射，from可以是名称或数字ID，to为名称时在本机查找对应的ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdMapping {
+    pub from: String,
+    pub to: String,
+}
+
+impl IdMapping {
+    // 解析 "来源:目标" 形式的映射，如 1000:0、alice:bob
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (from, to) = spec.split_once(':')?;
+        if from.is_empty() || to.is_empty() {
+            return None;
+        }
+        Some(IdMapping {
+            from: from.to_string(),
+            to: to.to_string(),
+        })
+    }
+}
+
+// TAR归档中POSIX元数据的处理方式；反序列化时缺少的字段使用默认值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TarMetadata {
+    // 解压时恢复文件所有者 (通常需要root权限)，压缩时总是记录
+    pub same_owner: bool,
+    // 只使用数字ID: 压缩时不记录用户名和组名，解压时不按名称查找本机的ID
+    pub numeric_owner: bool,
+    // 用户和组的映射，压缩时转换本机的所有者，解压时转换归档中记录的所有者
+    pub owner_map: Vec<IdMapping>,
+    pub group_map: Vec<IdMapping>,
+    // 解压时保留setuid、setgid和粘滞位，默认只恢复读写执行权限
+    pub preserve_permissions: bool,
+    // 解压时恢复文件和目录的修改时间
+    pub preserve_mtime: bool,
+    // 压缩时记录、解压时恢复扩展属性
+    pub xattrs: bool,
+    // 压缩时记录、解压时恢复POSIX ACL (仅Linux)
+    pub acls: bool,
+}
+
+impl TarMetadata {
+    pub const DEFAULT: TarMetadata = TarMetadata {
+        same_owner: false,
+        numeric_owner: false,
+        owner_map: Vec::new(),
+        group_map: Vec::new(),
+        preserve_permissions: false,
+        preserve_mtime: true,
+        xattrs: false,
+        acls: false,
+    };
+}
+
+impl Default for TarMetadata {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdKind {
+    User,
+    Group,
+}
+
+impl IdKind {
+    fn label(self) -> &'static str {
+        match self {
+            IdKind::User => "用户",
+            IdKind::Group => "组",
+        }
+    }
+
+    fn name_of(self, id: u64) -> Option<String> {
+        match self {
+            IdKind::User => user_name(id),
+            IdKind::Group => group_name(id),
+        }
+    }
+
+    fn id_of(self, name: &str) -> Option<u64> {
+        match self {
+            IdKind::User => user_id(name),
+            IdKind::Group => group_id(name),
+        }
+    }
+}
+
+// 按映射表转换所有者，没有匹配的映射时返回None
+fn map_id(
+    maps: &[IdMapping],
+    id: u64,
+    name: Option<&str>,
+    kind: IdKind,
+) -> Result<Option<(u64, Option<String>)>, String> {
+    let Some(mapping) = maps
+        .iter()
+        .find(|mapping| mapping.from == id.to_string() || Some(mapping.from.as_str()) == name)
+    else {
+        return Ok(None);
+    };
+
+    match mapping.to.parse::<u64>() {
+        Ok(id) => Ok(Some((id, kind.name_of(id)))),
+        Err(_) => kind
+            .id_of(&mapping.to)
+            .map(|id| Some((id, Some(mapping.to.clone()))))
+            .ok_or_else(|| format!("未知的{}: {}", kind.label(), mapping.to)),
+    }
+}
+
+// 写入归档的所有者
+pub struct ArchiveOwner {
+    pub uid: u64,
+    pub gid: u64,
+    pub user: Option<String>,
+    pub group: Option<String>,
+}
+
+// 按设置转换本机文件的所有者
+pub fn archive_owner(
+    metadata: &fs::Metadata,
+    settings: &TarMetadata,
+) -> Result<ArchiveOwner, String> {
+    #[cfg(unix)]
+    let (uid, gid) = {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.uid() as u64, metadata.gid() as u64)
+    };
+    #[cfg(not(unix))]
+    let (uid, gid) = {
+        let _ = metadata;
+        (0, 0)
+    };
+
+    let owner = |id: u64, maps: &[IdMapping], kind: IdKind| {
+        let name = kind.name_of(id);
+        let (id, name) = map_id(maps, id, name.as_deref(), kind)?.unwrap_or((id, name));
+        Ok::<_, String>((id, name.filter(|_| !settings.numeric_owner)))
+    };
+    let (uid, user) = owner(uid, &settings.owner_map, IdKind::User)?;
+    let (gid, group) = owner(gid, &settings.group_map, IdKind::Group)?;
+    Ok(ArchiveOwner {
+        uid,
+        gid,
+        user,
+        group,
+    })
+}
+
+// 按设置读取文件的扩展属性和ACL，转换为PAX扩展记录；符号链接按其指向的文件读取
+pub fn pax_records(path: &Path, settings: &TarMetadata) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut records = Vec::new();
+
+    #[cfg(unix)]
+    if settings.xattrs {
+        // 文件系统不支持扩展属性时视为没有
+        let names = match xattr::list_deref(path) {
+            Ok(names) => names.collect(),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => Vec::new(),
+            Err(e) => return Err(format!("无法读取 {} 的扩展属性: {}", path.display(), e)),
+        };
+        for name in names {
+            // ACL由acls选项处理，名称不是UTF-8的属性无法写入PAX键
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if name == XATTR_ACL_ACCESS || name == XATTR_ACL_DEFAULT {
+                continue;
+            }
+            if let Some(value) = xattr::get_deref(path, name)
+                .map_err(|e| format!("无法读取 {} 的扩展属性 {}: {}", path.display(), name, e))?
+            {
+                records.push((format!("{}{}", PAX_XATTR_PREFIX, name), value));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    if settings.acls {
+        for (attribute, key) in [
+            (XATTR_ACL_ACCESS, PAX_ACL_ACCESS),
+            (XATTR_ACL_DEFAULT, PAX_ACL_DEFAULT),
+        ] {
+            let value = match xattr::get_deref(path, attribute) {
+                Ok(value) => value,
+                Err(e) if e.kind() == io::ErrorKind::Unsupported => None,
+                Err(e) => return Err(format!("无法读取 {} 的ACL: {}", path.display(), e)),
+            };
+            if let Some(text) = value.and_then(|value| acl::to_text(&value, settings.numeric_owner))
+            {
+                records.push((key.to_string(), text.into_bytes()));
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = (path, settings);
+    // 扩展属性的列出顺序取决于文件系统，排序后归档内容才稳定
+    records.sort();
+    Ok(records)
+}
+
+// 修改时间含有小数部分时生成PAX记录，头部字段只能保存整秒
+pub fn mtime_record(metadata: &fs::Metadata) -> Option<(String, Vec<u8>)> {
+    let duration = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    if duration.subsec_nanos() == 0 {
+        return None;
+    }
+    let value = format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos());
+    Some((
+        PAX_MTIME.to_string(),
+        value.trim_end_matches('0').as_bytes().to_vec(),
+    ))
+}
+
+// 解析PAX扩展头中的时间，格式为 秒[.小数]，可以为负数
+fn parse_pax_time(value: &str) -> Option<FileTime> {
+    let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let negative = secs.starts_with('-');
+    let secs: i64 = secs.parse().ok()?;
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let nanos: u32 = format!("{:0<9}", &fraction[..fraction.len().min(9)])
+        .parse()
+        .ok()?;
+
+    // -1.5表示纪元前1.5秒，即 -2秒 + 0.5秒
+    if negative && nanos > 0 {
+        Some(FileTime::from_unix_time(secs - 1, 1_000_000_000 - nanos))
+    } else {
+        Some(FileTime::from_unix_time(secs, nanos))
+    }
+}
+
+// 用户名或组名超出头部字段长度时改用PAX记录
+pub fn set_owner(header: &mut Header, owner: &ArchiveOwner, records: &mut Vec<(String, Vec<u8>)>) {
+    header.set_uid(owner.uid);
+    header.set_gid(owner.gid);
+    if let Some(user) = &owner.user {
+        if header.set_username(user).is_err() {
+            records.push((PAX_UNAME.to_string(), user.clone().into_bytes()));
+        }
+    }
+    if let Some(group) = &owner.group {
+        if header.set_groupname(group).is_err() {
+            records.push((PAX_GNAME.to_string(), group.clone().into_bytes()));
+        }
+    }
+}
+
+// 解压时按设置恢复条目的元数据
+//
+// 目录的权限和修改时间在全部条目写出后才设置: 只读目录先保持可写，
+// 否则其中的条目无法写入；写入条目也会改变目录的修改时间。
+pub struct MetadataRestorer {
+    settings: TarMetadata,
+    dirs: Vec<(PathBuf, Option<u32>, Option<FileTime>)>,
+}
+
+impl MetadataRestorer {
+    pub fn new(settings: TarMetadata) -> Self {
+        MetadataRestorer {
+            settings,
+            dirs: Vec::new(),
+        }
+    }
+
+    // 普通文件的权限和时间由tar写出时设置，所有者和扩展属性由restore处理
+    pub fn configure<R: Read>(&self, archive: &mut Archive<R>) {
+        archive.set_preserve_permissions(self.settings.preserve_permissions);
+        archive.set_preserve_mtime(self.settings.preserve_mtime);
+        archive.set_preserve_ownerships(false);
+        archive.set_unpack_xattrs(false);
+    }
+
+    // 条目写出到path之后调用
+    pub fn restore<R: Read>(
+        &mut self,
+        entry: &mut Entry<'_, R>,
+        path: &Path,
+    ) -> Result<(), String> {
+        let header = entry.header();
+        let entry_type = header.entry_type();
+        let mode = header.mode().ok().map(|mode| mode & self.mode_mask());
+        let mut mtime = header
+            .mtime()
+            .ok()
+            .map(|mtime| FileTime::from_unix_time(mtime as i64, 0));
+        // 只有PAX扩展头中的修改时间精确到纳秒
+        let mut precise = false;
+        let (uid, gid) = (header.uid().ok(), header.gid().ok());
+        let mut user = header.username().ok().flatten().map(str::to_string);
+        let mut group = header.groupname().ok().flatten().map(str::to_string);
+
+        let mut records = Vec::new();
+        if let Some(extensions) = entry.pax_extensions().map_err(|e| e.to_string())? {
+            for extension in extensions.flatten() {
+                let Ok(key) = extension.key() else {
+                    continue;
+                };
+                match key {
+                    PAX_UNAME => user = extension.value().ok().map(str::to_string),
+                    PAX_GNAME => group = extension.value().ok().map(str::to_string),
+                    PAX_MTIME => {
+                        if let Some(time) = extension.value().ok().and_then(parse_pax_time) {
+                            mtime = Some(time);
+                            precise = true;
+                        }
+                    }
+                    _ => records.push((key.to_string(), extension.value_bytes().to_vec())),
+                }
+            }
+        }
+
+        if self.settings.same_owner {
+            if let (Some(uid), Some(gid)) = (uid, gid) {
+                let uid = self.local_id(uid, user.as_deref(), IdKind::User)?;
+                let gid = self.local_id(gid, group.as_deref(), IdKind::Group)?;
+                set_owner_of(path, uid, gid)?;
+                // 修改所有者会清除setuid和setgid位，需要重新设置权限
+                if self.settings.preserve_permissions && entry_type.is_file() {
+                    if let Some(mode) = mode {
+                        set_mode(path, mode)?;
+                    }
+                }
+            }
+        }
+
+        // 符号链接的扩展属性作用于其指向的文件，不恢复
+        if !entry_type.is_symlink() {
+            self.restore_attributes(path, &records, entry_type.is_dir())?;
+        }
+
+        if entry_type.is_dir() {
+            // 只读目录先保持可写，结束时恢复
+            if let Some(mode) = mode.filter(|mode| mode & 0o200 == 0) {
+                set_mode(path, mode | 0o700)?;
+            }
+            self.dirs.push((path.to_path_buf(), mode, mtime));
+        } else if precise && self.settings.preserve_mtime && entry_type.is_file() {
+            // tar写出文件时只设置了整秒
+            if let Some(mtime) = mtime {
+                set_mtime(path, mtime)?;
+            }
+        }
+        Ok(())
+    }
+
+    // 设置目录的权限和修改时间
+    pub fn finish(self) -> Result<(), String> {
+        for (path, mode, mtime) in self.dirs.into_iter().rev() {
+            if let Some(mode) = mode {
+                set_mode(&path, mode)?;
+            }
+            if let Some(mtime) = mtime.filter(|_| self.settings.preserve_mtime) {
+                set_mtime(&path, mtime)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn mode_mask(&self) -> u32 {
+        if self.settings.preserve_permissions {
+            0o7777
+        } else {
+            0o777
+        }
+    }
+
+    // 归档中的所有者在本机对应的ID: 先按映射，其次按名称查找，最后使用数字ID
+    fn local_id(&self, id: u64, name: Option<&str>, kind: IdKind) -> Result<u64, String> {
+        let maps = match kind {
+            IdKind::User => &self.settings.owner_map,
+            IdKind::Group => &self.settings.group_map,
+        };
+        if let Some((id, _)) = map_id(maps, id, name, kind)? {
+            return Ok(id);
+        }
+        if !self.settings.numeric_owner {
+            if let Some(id) = name
+                .filter(|name| !name.is_empty())
+                .and_then(|name| kind.id_of(name))
+            {
+                return Ok(id);
+            }
+        }
+        Ok(id)
+    }
+
+    #[cfg(unix)]
+    fn restore_attributes(
+        &self,
+        path: &Path,
+        records: &[(String, Vec<u8>)],
+        is_dir: bool,
+    ) -> Result<(), String> {
+        for (key, value) in records {
+            let (attribute, value) = if let Some(name) = key.strip_prefix(PAX_XATTR_PREFIX) {
+                // 其他工具可能把ACL作为普通扩展属性保存
+                let is_acl = name == XATTR_ACL_ACCESS || name == XATTR_ACL_DEFAULT;
+                if !(if is_acl {
+                    self.settings.acls
+                } else {
+                    self.settings.xattrs
+                }) {
+                    continue;
+                }
+                (name, value.clone())
+            } else if self.settings.acls && (key == PAX_ACL_ACCESS || key == PAX_ACL_DEFAULT) {
+                // 默认ACL只能设置在目录上
+                if key == PAX_ACL_DEFAULT && !is_dir {
+                    continue;
+                }
+                match acl_attribute(path, key, value, self.settings.numeric_owner)? {
+                    Some(attribute) => attribute,
+                    None => continue,
+                }
+            } else {
+                continue;
+            };
+
+            match xattr::set(path, attribute, &value) {
+                Ok(()) => {}
+                // 目标文件系统不支持时跳过，与GNU tar一致
+                Err(e) if e.kind() == io::ErrorKind::Unsupported => {}
+                Err(e) => {
+                    return Err(format!(
+                        "无法设置 {} 的扩展属性 {}: {}",
+                        path.display(),
+                        attribute,
+                        e
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn restore_attributes(
+        &self,
+        _path: &Path,
+        _records: &[(String, Vec<u8>)],
+        _is_dir: bool,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// 把GNU tar格式的ACL文本转换为要设置的扩展属性，其他系统不支持时返回None
+#[cfg(target_os = "linux")]
+fn acl_attribute(
+    path: &Path,
+    key: &str,
+    value: &[u8],
+    numeric: bool,
+) -> Result<Option<(&'static str, Vec<u8>)>, String> {
+    let text = String::from_utf8_lossy(value);
+    let value = acl::from_text(&text, numeric)
+        .ok_or_else(|| format!("无法解析 {} 的ACL: {}", path.display(), text))?;
+    let attribute = if key == PAX_ACL_ACCESS {
+        XATTR_ACL_ACCESS
+    } else {
+        XATTR_ACL_DEFAULT
+    };
+    Ok(Some((attribute, value)))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn acl_attribute(
+    _path: &Path,
+    _key: &str,
+    _value: &[u8],
+    _numeric: bool,
+) -> Result<Option<(&'static str, Vec<u8>)>, String> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+fn set_owner_of(path: &Path, uid: u64, gid: u64) -> Result<(), String> {
+    let (Ok(uid), Ok(gid)) = (u32::try_from(uid), u32::try_from(gid)) else {
+        return Err(format!(
+            "{} 的所有者ID超出范围: {}:{}",
+            path.display(),
+            uid,
+            gid
+        ));
+    };
+    std::os::unix::fs::lchown(path, Some(uid), Some(gid))
+        .map_err(|e| format!("无法设置 {} 的所有者: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn set_owner_of(_path: &Path, _uid: u64, _gid: u64) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| format!("无法设置 {} 的权限: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<(), String> {
+    Ok(())
+}
+
+fn set_mtime(path: &Path, mtime: FileTime) -> Result<(), String> {
+    filetime::set_file_mtime(path, mtime)
+        .map_err(|e| format!("无法设置 {} 的修改时间: {}", path.display(), e))
+}
+
+// 按ID查找用户名和组名，以及反向查找
+#[cfg(unix)]
+fn user_name(uid: u64) -> Option<String> {
+    let uid = libc::uid_t::try_from(uid).ok()?;
+    lookup(
+        |pwd: &mut libc::passwd, buffer, result| unsafe {
+            libc::getpwuid_r(uid, pwd, buffer.as_mut_ptr(), buffer.len(), result)
+        },
+        |pwd| c_string(pwd.pw_name),
+    )
+}
+
+#[cfg(unix)]
+fn user_id(name: &str) -> Option<u64> {
+    let name = std::ffi::CString::new(name).ok()?;
+    lookup(
+        |pwd: &mut libc::passwd, buffer, result| unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                pwd,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                result,
+            )
+        },
+        |pwd| Some(pwd.pw_uid as u64),
+    )
+}
+
+#[cfg(unix)]
+fn group_name(gid: u64) -> Option<String> {
+    let gid = libc::gid_t::try_from(gid).ok()?;
+    lookup(
+        |grp: &mut libc::group, buffer, result| unsafe {
+            libc::getgrgid_r(gid, grp, buffer.as_mut_ptr(), buffer.len(), result)
+        },
+        |grp| c_string(grp.gr_name),
+    )
+}
+
+#[cfg(unix)]
+fn group_id(name: &str) -> Option<u64> {
+    let name = std::ffi::CString::new(name).ok()?;
+    lookup(
+        |grp: &mut libc::group, buffer, result| unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                grp,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                result,
+            )
+        },
+        |grp| Some(grp.gr_gid as u64),
+    )
+}
+
+// 调用getpw*_r/getgr*_r，记录中的字符串指向buffer，只能在extract中读取
+#[cfg(unix)]
+fn lookup<T, R>(
+    call: impl Fn(&mut T, &mut [libc::c_char], *mut *mut T) -> libc::c_int,
+    extract: impl FnOnce(&T) -> Option<R>,
+) -> Option<R> {
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut record: T = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    if call(&mut record, &mut buffer, &mut result) != 0 || result.is_null() {
+        return None;
+    }
+    extract(&record)
+}
+
+#[cfg(unix)]
+fn c_string(pointer: *const libc::c_char) -> Option<String> {
+    if pointer.is_null() {
+        return None;
+    }
+    unsafe { std::ffi::CStr::from_ptr(pointer) }
+        .to_str()
+        .ok()
+        .map(str::to_string)
+}
+
+#[cfg(not(unix))]
+fn user_name(_uid: u64) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn user_id(_name: &str) -> Option<u64> {
+    None
+}
+
+#[cfg(not(unix))]
+fn group_name(_gid: u64) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn group_id(_name: &str) -> Option<u64> {
+    None
+}
+
+// POSIX ACL在Linux扩展属性中的二进制格式与GNU tar使用的文本格式之间的转换
+#[cfg(target_os = "linux")]
+mod acl {
+    use std::io::Read;
+
+    use super::IdKind;
+
+    const VERSION: u32 = 2;
+    const UNDEFINED_ID: u32 = u32::MAX;
+
+    const USER_OBJ: u16 = 0x01;
+    const USER: u16 = 0x02;
+    const GROUP_OBJ: u16 = 0x04;
+    const GROUP: u16 = 0x08;
+    const MASK: u16 = 0x10;
+    const OTHER: u16 = 0x20;
+
+    // 二进制 -> 文本，如 user::rw-,user:alice:r--,group::r--,mask::r--,other::---
+    pub fn to_text(value: &[u8], numeric: bool) -> Option<String> {
+        let mut reader = value;
+        if read_u32(&mut reader)? != VERSION || !reader.len().is_multiple_of(8) {
+            return None;
+        }
+
+        let mut entries = Vec::new();
+        while !reader.is_empty() {
+            let tag = read_u16(&mut reader)?;
+            let perm = read_u16(&mut reader)?;
+            let id = read_u32(&mut reader)?;
+
+            let qualifier = |kind: IdKind| match kind.name_of(id as u64) {
+                Some(name) if !numeric => name,
+                _ => id.to_string(),
+            };
+            let (tag, qualifier) = match tag {
+                USER_OBJ => ("user", String::new()),
+                USER => ("user", qualifier(IdKind::User)),
+                GROUP_OBJ => ("group", String::new()),
+                GROUP => ("group", qualifier(IdKind::Group)),
+                MASK => ("mask", String::new()),
+                OTHER => ("other", String::new()),
+                _ => return None,
+            };
+            entries.push(format!("{}:{}:{}", tag, qualifier, perm_text(perm)));
+        }
+        Some(entries.join(","))
+    }
+
+    // 文本 -> 二进制，接受缩写的标签、注释和star格式末尾的数字ID
+    pub fn from_text(text: &str, numeric: bool) -> Option<Vec<u8>> {
+        let mut entries = Vec::new();
+        for entry in text.split([',', '\n']) {
+            let entry = entry.split('#').next().unwrap_or_default().trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = entry.split(':').collect();
+            if fields.len() < 3 {
+                return None;
+            }
+            let (qualifier, perm) = (fields[1], parse_perm(fields[2])?);
+            let resolve = |kind: IdKind| {
+                let name_id = (!numeric).then(|| kind.id_of(qualifier)).flatten();
+                let star_id = fields.get(3).and_then(|id| id.parse().ok());
+                name_id
+                    .or(star_id)
+                    .or_else(|| qualifier.parse().ok())
+                    .and_then(|id: u64| u32::try_from(id).ok())
+            };
+            let (tag, id) = match (fields[0], qualifier.is_empty()) {
+                ("user" | "u", true) => (USER_OBJ, UNDEFINED_ID),
+                ("user" | "u", false) => (USER, resolve(IdKind::User)?),
+                ("group" | "g", true) => (GROUP_OBJ, UNDEFINED_ID),
+                ("group" | "g", false) => (GROUP, resolve(IdKind::Group)?),
+                ("mask" | "m", _) => (MASK, UNDEFINED_ID),
+                ("other" | "o", _) => (OTHER, UNDEFINED_ID),
+                _ => return None,
+            };
+            entries.push((tag, id, perm));
+        }
+
+        // 内核要求条目按标签和ID排序
+        entries.sort();
+        let mut value = VERSION.to_le_bytes().to_vec();
+        for (tag, id, perm) in entries {
+            value.extend_from_slice(&tag.to_le_bytes());
+            value.extend_from_slice(&perm.to_le_bytes());
+            value.extend_from_slice(&id.to_le_bytes());
+        }
+        Some(value)
+    }
+
+    fn perm_text(perm: u16) -> String {
+        [(4, 'r'), (2, 'w'), (1, 'x')]
+            .iter()
+            .map(|(bit, c)| if perm & bit != 0 { *c } else { '-' })
+            .collect()
+    }
+
+    fn parse_perm(text: &str) -> Option<u16> {
+        text.chars().try_fold(0, |perm, c| match c {
+            'r' => Some(perm | 4),
+            'w' => Some(perm | 2),
+            'x' => Some(perm | 1),
+            '-' => Some(perm),
+            _ => None,
+        })
+    }
+
+    fn read_u16(reader: &mut &[u8]) -> Option<u16> {
+        let mut bytes = [0; 2];
+        reader.read_exact(&mut bytes).ok()?;
+        Some(u16::from_le_bytes(bytes))
+    }
+
+    fn read_u32(reader: &mut &[u8]) -> Option<u32> {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes).ok()?;
+        Some(u32::from_le_bytes(bytes))
+    }
+}
@@ -0,0 +1,214 @@
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use sevenz_rust2 as sevenz;
+use zip::ZipArchive;
+
+use super::bench::format_bytes;
+use super::CompressionFormat;
+
+// ZSTD帧头的最大长度
+const ZSTD_FRAME_HEADER_MAX: u64 = 18;
+
+// 输出大小的估算来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateSource {
+    // 归档元数据中记录的解压后大小 (zip中央目录、7z头部、gzip/zstd记录的原始大小等)
+    Metadata,
+    // 压缩文件本身的大小，解压结果至少有这么大
+    ArchiveSize,
+    // 输入文件的总大小，压缩结果通常更小
+    Inputs,
+}
+
+// 磁盘空间预检的结果
+//
+// 估算值来自元数据或压缩文件大小时，空间不足就一定放不下，应拒绝执行；
+// 压缩时的估算是上限，空间不足只说明可能放不下，给出警告即可。
+#[derive(Debug, Clone, Serialize)]
+pub struct SpaceCheck {
+    pub required: u64,
+    pub available: u64,
+    pub source: EstimateSource,
+}
+
+impl SpaceCheck {
+    pub fn is_sufficient(&self) -> bool {
+        self.required <= self.available
+    }
+
+    pub fn must_refuse(&self) -> bool {
+        !self.is_sufficient() && self.source != EstimateSource::Inputs
+    }
+
+    pub fn message(&self) -> String {
+        let needed = match self.source {
+            EstimateSource::Metadata => "预计需要",
+            EstimateSource::ArchiveSize => "至少需要",
+            EstimateSource::Inputs => "最多需要",
+        };
+        format!(
+            "磁盘空间不足: {} {}，可用 {}",
+            needed,
+            format_bytes(self.required),
+            format_bytes(self.available)
+        )
+    }
+}
+
+// 压缩前的预检，以输入文件总大小作为输出大小的上限
+pub fn check_compress(input_paths: &[&str], output_path: &Path) -> Result<SpaceCheck, String> {
+    let mut required = 0u64;
+    for input_path in input_paths {
+        required = required.saturating_add(input_size(Path::new(input_path))?);
+    }
+
+    Ok(SpaceCheck {
+        required,
+        available: available_space(output_path.parent().unwrap_or(Path::new(".")))?,
+        source: EstimateSource::Inputs,
+    })
+}
+
+// 解压前的预检，按归档元数据估算解压后的大小，无法读取时以压缩文件大小作为下限
+pub fn check_decompress(
+    format: CompressionFormat,
+    input_paths: &[&str],
+    output_dir: &Path,
+) -> Result<SpaceCheck, String> {
+    let mut required = 0u64;
+    let mut source = EstimateSource::Metadata;
+    for input_path in input_paths {
+        let (size, estimate) = extracted_size(format, Path::new(input_path))?;
+        required = required.saturating_add(size);
+        if estimate == EstimateSource::ArchiveSize {
+            source = estimate;
+        }
+    }
+
+    Ok(SpaceCheck {
+        required,
+        available: available_space(output_dir)?,
+        source,
+    })
+}
+
+fn extracted_size(format: CompressionFormat, path: &Path) -> Result<(u64, EstimateSource), String> {
+    let archive_size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    let recorded = match format {
+        CompressionFormat::Zip => zip_size(path),
+        CompressionFormat::SevenZip => sevenz::Archive::open(path)
+            .ok()
+            .map(|archive| archive.files.iter().map(|entry| entry.size()).sum()),
+        // 未压缩的归档，内容不会超过归档本身
+        CompressionFormat::Tar
+        | CompressionFormat::Cpio
+        | CompressionFormat::Ar
+        | CompressionFormat::Iso => Some(archive_size),
+        // 只记录了最后一个成员或第一帧的大小，且gzip的大小对4GB取模，只能作为下限
+        CompressionFormat::Gzip | CompressionFormat::TarGz => gzip_size(path),
+        CompressionFormat::Zstd | CompressionFormat::TarZst => zstd_size(path),
+        _ => None,
+    };
+
+    Ok(match recorded {
+        Some(size) => (size.max(archive_size), EstimateSource::Metadata),
+        None => (archive_size, EstimateSource::ArchiveSize),
+    })
+}
+
+// zip中央目录中记录的原始大小之和
+fn zip_size(path: &Path) -> Option<u64> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    let mut total = 0u64;
+    for index in 0..archive.len() {
+        total = total.saturating_add(archive.by_index_raw(index).ok()?.size());
+    }
+    Some(total)
+}
+
+// gzip尾部记录的原始大小 (ISIZE)
+fn gzip_size(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::End(-4)).ok()?;
+    let mut trailer = [0u8; 4];
+    file.read_exact(&mut trailer).ok()?;
+    Some(u64::from(u32::from_le_bytes(trailer)))
+}
+
+// zstd帧头中记录的原始大小，压缩时未记录则无法得知
+fn zstd_size(path: &Path) -> Option<u64> {
+    let mut header = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(ZSTD_FRAME_HEADER_MAX)
+        .read_to_end(&mut header)
+        .ok()?;
+    zstd::zstd_safe::get_frame_content_size(&header).ok()?
+}
+
+// 输入的总大小，不跟随目录中的符号链接
+fn input_size(path: &Path) -> Result<u64, String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+
+    let mut total = 0u64;
+    for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        total = total.saturating_add(input_size(&entry.path())?);
+    }
+    Ok(total)
+}
+
+// 路径所在文件系统的可用空间，路径不存在时使用最近的已存在的上级目录
+pub fn available_space(path: &Path) -> Result<u64, String> {
+    let mut existing = path;
+    while !existing.exists() {
+        existing = match existing.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+    }
+    free_bytes(existing).map_err(|e| format!("无法获取 {} 的可用空间: {}", path.display(), e))
+}
+
+// statvfs字段的整数类型因平台而异，统一转换为u64
+#[cfg(unix)]
+#[allow(clippy::useless_conversion)]
+fn free_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // 普通用户可用的块数，不含为root保留的部分
+    Ok(u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize)))
+}
+
+#[cfg(windows)]
+fn free_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(wide.as_ptr()),
+            Some(&mut available as *mut u64),
+            None,
+            None,
+        )
+    }
+    .map_err(std::io::Error::other)?;
+    Ok(available)
+}
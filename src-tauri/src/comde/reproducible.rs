@@ -0,0 +1,179 @@
+use std::env;
+use std::fs::Metadata;
+use std::time::UNIX_EPOCH;
+
+// 构建系统约定的环境变量，指定归档中允许出现的最晚时间 (Unix秒)
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+// 未设置SOURCE_DATE_EPOCH时所有条目使用的时间: 1980-01-01 00:00:00 UTC，ZIP能表示的最早时间
+pub const DEFAULT_EPOCH: u64 = 315_532_800;
+
+// 本次压缩的可复现规则，enabled为压缩设置中的开关，未开启时返回None；
+// SOURCE_DATE_EPOCH在每次压缩时重新读取
+pub fn load(enabled: bool) -> Result<Option<Reproducible>, String> {
+    if !enabled {
+        return Ok(None);
+    }
+
+    let epoch = match env::var(SOURCE_DATE_EPOCH) {
+        Ok(value) => Some(
+            value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| format!("{} 无效: {}", SOURCE_DATE_EPOCH, value))?,
+        ),
+        Err(_) => None,
+    };
+    Ok(Some(Reproducible { epoch }))
+}
+
+// 可复现归档的规则
+//
+// 相同的输入总是得到逐字节相同的输出: 条目按名称排序，所有者清零，权限只保留
+// 可执行与否 (0755/0644)，修改时间不晚于SOURCE_DATE_EPOCH；未设置该变量时统一使用DEFAULT_EPOCH。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reproducible {
+    epoch: Option<u64>,
+}
+
+impl Reproducible {
+    // 写入归档的修改时间 (Unix秒)
+    pub fn mtime(&self, metadata: &Metadata) -> u64 {
+        match self.epoch {
+            Some(epoch) => metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(epoch, |duration| duration.as_secs().min(epoch)),
+            None => DEFAULT_EPOCH,
+        }
+    }
+
+    // 写入归档的权限，目录和所有者可执行的文件为0755，其他为0644
+    pub fn mode(&self, metadata: &Metadata) -> u32 {
+        if metadata.is_dir() || is_executable(metadata) {
+            0o755
+        } else {
+            0o644
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &Metadata) -> bool {
+    false
+}
+
+// 按名称排序输入路径，使输出不依赖于选择文件的顺序
+pub fn sort_inputs<'a>(input_paths: &[&'a str]) -> Vec<&'a str> {
+    let mut sorted = input_paths.to_vec();
+    sorted.sort();
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::testing::TempDir;
+    use crate::comde::z_tar::{TarCodec, TarCompressor};
+    use crate::comde::z_zip::ZipCompressor;
+    use crate::comde::{ComdeAble, CompressSettings, CompressionOptions};
+    use filetime::FileTime;
+    use std::fs;
+    use std::path::Path;
+
+    // 内容相同的目录树，按给定顺序创建 (read_dir的顺序随之不同)，修改时间和权限各不相同
+    fn build_tree(root: &Path, names: &[&str], mtime: i64, mode: u32) {
+        fs::create_dir_all(root.join("tree/empty")).unwrap();
+        for name in names {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("content of {}\n", name)).unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            }
+            #[cfg(not(unix))]
+            let _ = mode;
+            filetime::set_file_mtime(&path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        for dir in ["tree/empty", "tree/sub", "tree"] {
+            filetime::set_file_mtime(root.join(dir), FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+    }
+
+    async fn compress<C: ComdeAble>(
+        compressor: &C,
+        root: &Path,
+        output: &Path,
+        options: CompressionOptions,
+    ) -> Vec<u8> {
+        let tree = root.join("tree");
+        let extra = root.join("extra.txt");
+        // 第二棵树以相反的顺序选择输入
+        let mut inputs = vec![tree.to_str().unwrap(), extra.to_str().unwrap()];
+        if root.ends_with("second") {
+            inputs.reverse();
+        }
+        compressor
+            .compress(inputs, output.to_str().unwrap(), Some(options))
+            .await
+            .unwrap();
+        fs::read(output).unwrap()
+    }
+
+    // 在SOURCE_DATE_EPOCH下，修改时间和目录遍历顺序不同的相同内容得到逐字节相同的zip和tar.gz
+    #[tokio::test]
+    async fn identical_archives_from_different_trees() {
+        let dir = TempDir::new("reproducible");
+        let names = [
+            "tree/a.txt",
+            "tree/b.txt",
+            "tree/sub/y.bin",
+            "tree/sub/z.bin",
+            "extra.txt",
+        ];
+        let first = dir.join("first");
+        let second = dir.join("second");
+        build_tree(&first, &names, 1_900_000_000, 0o600);
+        let mut reversed = names;
+        reversed.reverse();
+        build_tree(&second, &reversed, 2_000_000_000, 0o640);
+
+        env::set_var(SOURCE_DATE_EPOCH, "1700000000");
+        let settings = CompressSettings {
+            reproducible: true,
+            ..Default::default()
+        };
+        let mut zips = Vec::new();
+        let mut tar_gzs = Vec::new();
+        for root in [&first, &second] {
+            let options = CompressionOptions::Zip {
+                level: 6,
+                password: None,
+                settings: settings.clone(),
+            };
+            zips.push(compress(&ZipCompressor::new(), root, &root.join("out.zip"), options).await);
+
+            let options = CompressionOptions::Gzip {
+                level: 6,
+                comment: None,
+                settings: settings.clone(),
+            };
+            let tar_gz = TarCompressor::with_codec(TarCodec::Gzip);
+            tar_gzs.push(compress(&tar_gz, root, &root.join("out.tar.gz"), options).await);
+        }
+        env::remove_var(SOURCE_DATE_EPOCH);
+
+        assert!(zips[0] == zips[1], "zip的输出不一致");
+        assert!(tar_gzs[0] == tar_gzs[1], "tar.gz的输出不一致");
+    }
+}
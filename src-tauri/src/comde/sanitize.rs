@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use unicode_normalization::UnicodeNormalization;
+
+use super::{normalize_entry_path, safe_join, stays_inside};
+
+// 重命名报告文件名的后缀，报告与输出目录并列，如 out -> out.ziphere-renames.txt
+pub const RENAME_REPORT_SUFFIX: &str = ".ziphere-renames.txt";
+
+// 重命名时尝试的最大序号
+const MAX_RENAME_ATTEMPTS: u32 = 10000;
+
+// Windows文件名中不允许的字符，控制字符另行判断
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+// Windows设备名，带任意扩展名时同样无法创建
+const DEVICE_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// 解压时文件名需要遵守的规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameRules {
+    // 替换Windows保留字符和设备名，去掉结尾的点和空格
+    pub windows: bool,
+    // 只有大小写不同的名称视为同一文件
+    pub case_insensitive: bool,
+}
+
+impl NameRules {
+    // 同时满足Windows和macOS，用于解压到U盘、网络共享等会被其他系统读取的位置
+    pub const PORTABLE: NameRules = NameRules {
+        windows: true,
+        case_insensitive: true,
+    };
+
+    // 本系统的规则，macOS默认的文件系统不区分大小写
+    pub fn native() -> Self {
+        NameRules {
+            windows: cfg!(windows),
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+        }
+    }
+
+    // 按解压设置选择规则，portable表示按所有系统的规则处理文件名
+    pub fn select(portable: bool) -> Self {
+        if portable {
+            NameRules::PORTABLE
+        } else {
+            NameRules::native()
+        }
+    }
+}
+
+// 条目被重命名的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameReason {
+    // 转换为NFC形式 (macOS打包的归档常用NFD)
+    Unicode,
+    // 含有保留字符、设备名或结尾的点和空格
+    Invalid,
+    // 与本次解压的其他条目同名 (忽略大小写或规范化后)
+    Collision,
+}
+
+impl fmt::Display for RenameReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameReason::Unicode => write!(f, "Unicode规范化"),
+            RenameReason::Invalid => write!(f, "名称在Windows上无效"),
+            RenameReason::Collision => write!(f, "与其他条目重名"),
+        }
+    }
+}
+
+// 一个被重命名的条目，renamed相对于解压时的输出目录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub original: String,
+    pub renamed: String,
+    pub reasons: Vec<RenameReason>,
+}
+
+// 解压过程中被重命名的条目
+//
+// 克隆后共享同一份记录: 解压设置中带一份，调用方保留一份，解压结束后取出并写报告。
+// 报告不写进输出目录，以免混入解压结果或覆盖同名条目。
+#[derive(Debug, Clone, Default)]
+pub struct RenameLog(Arc<Mutex<Vec<Rename>>>);
+
+impl RenameLog {
+    fn push(&self, rename: Rename) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(rename);
+    }
+
+    // 取出目前记录的所有重命名并清空，同一记录可用于多次解压
+    pub fn take(&self) -> Vec<Rename> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+// 输出目录的重命名报告路径，与输出目录并列
+pub fn report_path(output_dir: &Path) -> PathBuf {
+    let dir = output_dir
+        .canonicalize()
+        .unwrap_or_else(|_| output_dir.to_path_buf());
+    match (dir.parent(), dir.file_name()) {
+        (Some(parent), Some(name)) => parent.join(format!(
+            "{}{}",
+            name.to_string_lossy(),
+            RENAME_REPORT_SUFFIX
+        )),
+        // 根目录没有上级，只能写在目录中
+        _ => dir.join(RENAME_REPORT_SUFFIX.trim_start_matches('.')),
+    }
+}
+
+// 为输出目录写重命名报告，没有重命名时不创建文件；返回报告路径
+pub fn write_report(output_dir: &Path, renames: &[Rename]) -> Result<Option<PathBuf>, String> {
+    if renames.is_empty() {
+        return Ok(None);
+    }
+
+    let path = report_path(output_dir);
+    let mut file =
+        File::create(&path).map_err(|e| format!("无法创建重命名报告 {}: {}", path.display(), e))?;
+    writeln!(
+        file,
+        "以下条目的名称在此系统上无效或与其他条目冲突，已重命名 (相对于 {}):",
+        output_dir.display()
+    )
+    .map_err(|e| e.to_string())?;
+    for rename in renames {
+        let reasons = rename
+            .reasons
+            .iter()
+            .map(|reason| reason.to_string())
+            .collect::<Vec<_>>()
+            .join("，");
+        writeln!(
+            file,
+            "{} -> {} ({})",
+            rename.original, rename.renamed, reasons
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(Some(path))
+}
+
+// 一次解压过程中的文件名处理状态
+//
+// 每个条目名按规则逐级清理，再与本次已写出的文件比较，冲突时加序号重命名。
+// 名称有变化的条目记录到RenameLog，由调用方在解压结束后 (包括中途失败时) 写报告。
+pub struct Sanitizer {
+    rules: NameRules,
+    // 已写出的文件: 比较用的路径 -> 原条目名
+    files: HashMap<String, String>,
+    renames: RenameLog,
+}
+
+impl Sanitizer {
+    pub fn new(rules: NameRules, renames: RenameLog) -> Self {
+        Sanitizer {
+            rules,
+            files: HashMap::new(),
+            renames,
+        }
+    }
+
+    // 文件条目在输出目录中的路径，None表示路径不安全
+    pub fn file_path(&mut self, output_dir: &Path, name: &str) -> Result<Option<PathBuf>, String> {
+        let original = normalize_entry_path(name);
+        let Some((mut parts, mut reasons)) = self.clean(&original) else {
+            return Ok(None);
+        };
+
+        // 同一条目重复出现 (如追加更新的tar) 时按原样覆盖，只有不同的条目才算冲突
+        let joined = parts.join("/");
+        let key = self.key(output_dir, &joined);
+        if self
+            .files
+            .get(&key)
+            .is_some_and(|existing| *existing != original)
+        {
+            parts = self.unique(output_dir, parts)?;
+            reasons.push(RenameReason::Collision);
+        }
+        let relative = parts.join("/");
+        self.files
+            .insert(self.key(output_dir, &relative), original.clone());
+
+        self.finish(output_dir, &original, &relative, &reasons)
+    }
+
+    // 目录条目在输出目录中的路径；同名目录直接合并，不算冲突
+    pub fn dir_path(&mut self, output_dir: &Path, name: &str) -> Result<Option<PathBuf>, String> {
+        let original = normalize_entry_path(name);
+        let Some((parts, reasons)) = self.clean(&original) else {
+            return Ok(None);
+        };
+        self.finish(output_dir, &original, &parts.join("/"), &reasons)
+    }
+
+    // 逐级清理路径，含有..等不安全的部分时返回None
+    fn clean(&self, path: &str) -> Option<(Vec<String>, Vec<RenameReason>)> {
+        if path.is_empty() || path.split('/').any(|part| part == "..") {
+            return None;
+        }
+
+        let mut reasons = Vec::new();
+        let parts = path
+            .split('/')
+            .map(|part| {
+                let normalized: String = part.nfc().collect();
+                if normalized != part && !reasons.contains(&RenameReason::Unicode) {
+                    reasons.push(RenameReason::Unicode);
+                }
+                let cleaned = if self.rules.windows {
+                    windows_name(&normalized)
+                } else {
+                    normalized.clone()
+                };
+                if cleaned != normalized && !reasons.contains(&RenameReason::Invalid) {
+                    reasons.push(RenameReason::Invalid);
+                }
+                cleaned
+            })
+            .collect();
+        Some((parts, reasons))
+    }
+
+    // 比较用的路径，包含输出目录以区分同一次解压中的不同目标目录
+    fn key(&self, output_dir: &Path, relative: &str) -> String {
+        let key = format!("{}/{}", output_dir.to_string_lossy(), relative);
+        if self.rules.case_insensitive {
+            key.to_lowercase()
+        } else {
+            key
+        }
+    }
+
+    // 在文件名后加序号，如 a.txt -> a (2).txt，直到不与已写出的文件冲突
+    fn unique(&self, output_dir: &Path, mut parts: Vec<String>) -> Result<Vec<String>, String> {
+        let name = parts.pop().unwrap_or_default();
+        let (stem, extension) = match name.rfind('.') {
+            Some(index) if index > 0 => name.split_at(index),
+            _ => (name.as_str(), ""),
+        };
+
+        for index in 2..=MAX_RENAME_ATTEMPTS {
+            parts.push(format!("{} ({}){}", stem, index, extension));
+            if !self
+                .files
+                .contains_key(&self.key(output_dir, &parts.join("/")))
+            {
+                return Ok(parts);
+            }
+            parts.pop();
+        }
+
+        Err(format!("无法为 {} 生成不冲突的文件名", name))
+    }
+
+    // 检查清理后的路径并记录重命名
+    fn finish(
+        &mut self,
+        output_dir: &Path,
+        original: &str,
+        relative: &str,
+        reasons: &[RenameReason],
+    ) -> Result<Option<PathBuf>, String> {
+        // 上级目录可能是之前的条目创建的符号链接，解析后必须仍在输出目录中
+        let Some(target) =
+            safe_join(output_dir, relative).filter(|target| stays_inside(output_dir, target))
+        else {
+            return Ok(None);
+        };
+        if !reasons.is_empty() {
+            self.renames.push(Rename {
+                original: original.to_string(),
+                renamed: relative.to_string(),
+                reasons: reasons.to_vec(),
+            });
+        }
+        Ok(Some(target))
+    }
+}
+
+// 按Windows的规则清理单级名称
+fn windows_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if RESERVED_CHARS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    // Windows会去掉结尾的点和空格，导致 "a." 与 "a" 成为同一文件
+    let mut cleaned = replaced.trim_end_matches(['.', ' ']).to_string();
+    if cleaned.is_empty() {
+        cleaned.push('_');
+    }
+
+    // 设备名按第一个点之前的部分判断，如 aux.c、con.tar.gz
+    let base_len = cleaned.find('.').unwrap_or(cleaned.len());
+    let base = cleaned[..base_len].trim_end_matches(' ');
+    if DEVICE_NAMES.contains(&base.to_uppercase().as_str()) {
+        cleaned.insert(base_len, '_');
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::testing::TempDir;
+    use std::fs;
+    use RenameReason::*;
+
+    // 按所有系统的规则清理条目名: (条目名, 清理后的相对路径, 重命名原因)，None表示被拒绝
+    #[test]
+    fn cleans_entry_names() {
+        let cases: &[(&str, Option<&str>, &[RenameReason])] = &[
+            ("ok.txt", Some("ok.txt"), &[]),
+            ("CON", Some("CON_"), &[Invalid]),
+            ("dir/con.tar.gz", Some("dir/con_.tar.gz"), &[Invalid]),
+            ("lpt1 .txt", Some("lpt1 _.txt"), &[Invalid]),
+            ("a:b", Some("a_b"), &[Invalid]),
+            ("name.", Some("name"), &[Invalid]),
+            ("dir. /name. ", Some("dir/name"), &[Invalid]),
+            ("...", Some("_"), &[Invalid]),
+            ("e\u{301}.txt", Some("\u{e9}.txt"), &[Unicode]),
+            ("../x", None, &[]),
+            ("a/../../x", None, &[]),
+            ("..\\x", None, &[]),
+            ("/etc/passwd", Some("etc/passwd"), &[]),
+            ("C:\\Windows\\x", Some("C_/Windows/x"), &[Invalid]),
+        ];
+
+        let dir = TempDir::new("sanitize-names");
+        for (name, expected, reasons) in cases {
+            let renames = RenameLog::default();
+            let mut sanitizer = Sanitizer::new(NameRules::PORTABLE, renames.clone());
+            let target = sanitizer.file_path(dir.path(), name).unwrap();
+            assert_eq!(target, expected.map(|path| dir.join(path)), "{}", name);
+
+            let recorded = renames.take();
+            if reasons.is_empty() {
+                assert!(recorded.is_empty(), "{}", name);
+            } else {
+                assert_eq!(recorded.len(), 1, "{}", name);
+                assert_eq!(recorded[0].renamed, expected.unwrap(), "{}", name);
+                assert_eq!(recorded[0].reasons, *reasons, "{}", name);
+            }
+        }
+    }
+
+    // 只有大小写不同的条目加序号区分，同一条目重复出现时不算冲突
+    #[test]
+    fn numbers_colliding_names() {
+        let dir = TempDir::new("sanitize-collision");
+        let renames = RenameLog::default();
+        let mut sanitizer = Sanitizer::new(NameRules::PORTABLE, renames.clone());
+        for (name, expected) in [
+            ("Read.me", "Read.me"),
+            ("read.me", "read (2).me"),
+            ("READ.ME", "READ (3).ME"),
+            ("Read.me", "Read.me"),
+        ] {
+            let target = sanitizer.file_path(dir.path(), name).unwrap();
+            assert_eq!(target, Some(dir.join(expected)));
+        }
+        let recorded = renames.take();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded.iter().all(|rename| rename.reasons == [Collision]));
+    }
+
+    // 报告与输出目录并列写在其外，没有重命名时不创建
+    #[test]
+    fn writes_report_beside_output_dir() {
+        let dir = TempDir::new("sanitize-report");
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+        assert_eq!(write_report(&output, &[]).unwrap(), None);
+
+        let renames = [Rename {
+            original: "CON".to_string(),
+            renamed: "CON_".to_string(),
+            reasons: vec![Invalid],
+        }];
+        let path = write_report(&output, &renames).unwrap().unwrap();
+        assert!(!path.starts_with(output.canonicalize().unwrap()));
+        assert_eq!(
+            path.file_name().unwrap().to_string_lossy(),
+            format!("out{}", RENAME_REPORT_SUFFIX)
+        );
+        assert_eq!(path.parent(), output.canonicalize().unwrap().parent());
+        assert!(fs::read_to_string(&path).unwrap().contains("CON -> CON_"));
+        assert_eq!(fs::read_dir(&output).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::atomic::StagingDir;
+use super::conflict::{merge_dir, ConflictPolicy, ConflictResolver};
+use super::{CompressionFormat, Compressor, DecompressionOptions};
+
+// 智能解压 ("解压到此处")
+//
+// 先把归档解压到目标目录下的暂存目录，再检查顶层条目:
+// 只有一个条目 (如 project.zip 中只有 project/) 时直接移到目标目录，避免 project/project/；
+// 有多个条目时包一层以归档名命名的文件夹，避免散落在目标目录中。
+// 移动时按解压选项中的冲突策略处理已存在的文件。
+pub async fn smart_extract(
+    compressor: &Compressor,
+    format: CompressionFormat,
+    input_path: &str,
+    base_dir: &Path,
+    mut options: Option<DecompressionOptions>,
+) -> Result<PathBuf, String> {
+    let mut conflict = ConflictResolver::from_options(options.as_ref());
+    // 暂存目录是新建的，解压到其中不会有冲突
+    if let Some(options) = options.as_mut() {
+        options.set_conflict_policy(ConflictPolicy::Overwrite);
+    }
+
+    let staging = StagingDir::create(base_dir)?;
+    compressor
+        .decompress(vec![input_path], &staging.path().to_string_lossy(), options)
+        .await?;
+    place(
+        staging.path(),
+        base_dir,
+        &archive_stem(input_path, format),
+        &mut conflict,
+    )
+}
+
+// 将暂存目录中的内容移到最终位置，返回解压结果所在的路径
+fn place(
+    staging: &Path,
+    base_dir: &Path,
+    stem: &str,
+    conflict: &mut ConflictResolver,
+) -> Result<PathBuf, String> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(staging).map_err(|e| e.to_string())? {
+        names.push(entry.map_err(|e| e.to_string())?.file_name());
+    }
+
+    match names.as_slice() {
+        [] => Ok(base_dir.to_path_buf()),
+        [name] => {
+            merge_dir(staging, base_dir, conflict)?;
+            Ok(base_dir.join(name))
+        }
+        _ => {
+            let folder = base_dir.join(stem);
+            fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+            merge_dir(staging, &folder, conflict)?;
+            Ok(folder)
+        }
+    }
+}
+
+// 去掉格式扩展名后的归档名，如 project.tar.gz -> project
+pub fn archive_stem(input_path: &str, format: CompressionFormat) -> String {
+    let path = Path::new(input_path);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let suffix = format!(".{}", format.extension());
+    let split = file_name.len().saturating_sub(suffix.len());
+    let stem = if file_name
+        .get(split..)
+        .is_some_and(|tail| tail.eq_ignore_ascii_case(&suffix))
+    {
+        &file_name[..split]
+    } else {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(&file_name)
+    };
+
+    if stem.is_empty() {
+        "extracted".to_string()
+    } else {
+        stem.to_string()
+    }
+}
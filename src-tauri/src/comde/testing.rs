@@ -0,0 +1,105 @@
+// 各模块测试共用的工具
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::{ComdeAble, DecompressionOptions};
+
+// 同一进程中各临时目录的序号
+static NEXT_DIR: AtomicU32 = AtomicU32::new(0);
+
+// 测试用的临时目录，离开作用域时删除，测试失败时同样会清理
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "ziphere-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// 两段数据分别压缩后首尾相接 (如 cat a.gz b.gz)，解压应得到全部数据。
+// 先解压到指定的文件，再解压到已有同名文件的目录，此时按options中的重命名策略写到新文件
+pub async fn check_concatenated<C: ComdeAble>(
+    compressor: &C,
+    extension: &str,
+    encode: impl Fn(&[u8]) -> Vec<u8>,
+    options: DecompressionOptions,
+) {
+    let dir = TempDir::new(extension);
+    let expected = b"first part\nsecond part\n";
+
+    let mut data = encode(b"first part\n");
+    data.extend(encode(b"second part\n"));
+    let input = dir.join(format!("joined.txt.{}", extension));
+    fs::write(&input, &data).unwrap();
+    let input = input.to_str().unwrap();
+
+    let output = dir.join("joined.txt");
+    compressor
+        .decompress(vec![input], output.to_str().unwrap(), None)
+        .await
+        .unwrap();
+    assert_eq!(fs::read(&output).unwrap(), expected);
+
+    let output_dir = dir.join("out");
+    fs::create_dir_all(&output_dir).unwrap();
+    fs::write(output_dir.join("joined.txt"), "existing").unwrap();
+    compressor
+        .decompress(vec![input], output_dir.to_str().unwrap(), Some(options))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(output_dir.join("joined.txt")).unwrap(),
+        "existing"
+    );
+    assert_eq!(
+        fs::read(output_dir.join("joined (1).txt")).unwrap(),
+        expected
+    );
+}
+
+// 构建ar归档: 每个成员为 (头部中的名称字段, 数据)，名称字段原样写入，奇数长度的数据后补换行
+pub fn ar_archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = b"!<arch>\n".to_vec();
+    for (name, data) in members {
+        let header = format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8o}{:<10}`\n",
+            name,
+            1_700_000_000,
+            0,
+            0,
+            0o644,
+            data.len()
+        );
+        assert_eq!(header.len(), 60);
+        archive.extend_from_slice(header.as_bytes());
+        archive.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            archive.push(b'\n');
+        }
+    }
+    archive
+}
@@ -1,7 +1,12 @@
 use sevenz_rust2 as sevenz;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::{Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions};
 
 #[derive(Clone)]
 pub struct SevenZipCompressor;
@@ -13,6 +18,29 @@ impl SevenZipCompressor {
 }
 
 impl ComdeAble for SevenZipCompressor {
+    fn capabilities(&self) -> Capabilities {
+        // sevenz-rust2 不支持设置压缩级别和密码，两者都会被忽略
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: true,
+            multi_input: true,
+            supports_password: false,
+            level: None,
+            methods: vec!["lzma2"],
+            compress_options: Vec::new(),
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::SevenZip {
+            level: preset.pick([0, 1, 3, 6, 8, 9]),
+            password: None,
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -21,7 +49,9 @@ impl ComdeAble for SevenZipCompressor {
     ) -> Result<(), String> {
         // 处理压缩选项
         let (_level, _password) = match options {
-            Some(CompressionOptions::SevenZip { level, password }) => (level, password),
+            Some(CompressionOptions::SevenZip {
+                level, password, ..
+            }) => (level, password),
             _ => (6, None), // 默认压缩级别为6，无密码
         };
 
@@ -31,39 +61,25 @@ impl ComdeAble for SevenZipCompressor {
             .map(|p| Path::new(*p).to_path_buf())
             .collect();
 
-        // 使用7z压缩文件或目录
+        // 使用7z压缩文件或目录，写入临时文件，成功后才移动到最终位置
         // 注：sevenz-rust2库目前不支持密码和压缩级别设置，需要等待库的更新
-        // 对单个文件或目录特殊处理
-        if paths.len() == 1 {
-            // 如果只有一个路径，直接使用它
-            let path = &paths[0];
-            return match sevenz::compress_to_path(path, output_path) {
-                Ok(_) => Ok(()),
-                Err(e) => Err(format!("压缩失败: {}", e)),
-            };
+        let output_file = AtomicFile::create(output_path)?;
+        let output_file = if paths.len() == 1 {
+            // 只有一个路径时与7-Zip的行为一致，目录的内容直接位于归档根部
+            sevenz::compress(&paths[0], output_file).map_err(|e| format!("压缩失败: {}", e))?
         } else {
-            // 如果有多个文件，需要单独处理每个文件
+            // 多个路径时每个路径以自身名称作为归档中的顶层条目
+            let mut writer =
+                sevenz::SevenZWriter::new(output_file).map_err(|e| format!("压缩失败: {}", e))?;
             for path in &paths {
-                // 获取文件名作为输出文件的一部分
-                let file_name = path.file_name().ok_or("无效的文件名")?.to_string_lossy();
-
-                // 创建临时输出路径
-                let temp_output = format!("{}.tmp", output_path);
-
-                // 压缩单个文件
-                match sevenz::compress_to_path(path, &temp_output) {
-                    Ok(_) => {
-                        // 成功压缩，将临时文件移动到最终位置
-                        std::fs::rename(temp_output, output_path)
-                            .map_err(|e| format!("重命名文件失败: {}", e))?;
-                    }
-                    Err(e) => return Err(format!("压缩文件 {} 失败: {}", file_name, e)),
-                }
+                let root = path.parent().unwrap_or(path);
+                push_path(&mut writer, path, root)
+                    .map_err(|e| format!("压缩文件 {} 失败: {}", path.display(), e))?;
             }
+            writer.finish().map_err(|e| format!("压缩失败: {}", e))?
+        };
 
-            // 如果代码能运行到这里，表示所有文件都已成功压缩
-            Ok(())
-        }
+        output_file.commit()
     }
 
     async fn decompress(
@@ -73,8 +89,9 @@ impl ComdeAble for SevenZipCompressor {
         options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
         // 处理解压选项
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
         let _password = match options {
-            Some(DecompressionOptions::SevenZip { password }) => password,
+            Some(DecompressionOptions::SevenZip { password, .. }) => password,
             _ => None,
         };
 
@@ -84,14 +101,94 @@ impl ComdeAble for SevenZipCompressor {
             std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
         }
 
-        // 解压所有输入文件
+        // 解压所有输入文件，逐个条目处理已存在的文件
         for input_path in input_paths {
-            match sevenz::decompress_file(input_path, output_path) {
-                Ok(_) => (),
-                Err(e) => return Err(format!("解压失败: {}", e)),
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+            let result = sevenz::decompress_file_with_extract_fn(
+                input_path,
+                output_path,
+                |entry, reader, _dest| {
+                    limiter
+                        .begin_entry(entry.name(), Some(entry.size()))
+                        .map_err(|e| sevenz::Error::other(e.to_string()))?;
+
+                    // 不使用库给出的路径，名称按本系统的规则清理，不安全的路径跳过
+                    let dest = if entry.is_directory() {
+                        conflict.dir_path(output_dir, entry.name())
+                    } else {
+                        conflict.entry_path(output_dir, entry.name())
+                    }
+                    .map_err(sevenz::Error::other)?;
+                    let Some(dest) = dest else {
+                        io::copy(&mut limiter.reader(reader), &mut io::sink())
+                            .map_err(sevenz::Error::io)?;
+                        return Ok(true);
+                    };
+                    if entry.is_directory() {
+                        return sevenz::default_entry_extract_fn(entry, reader, &dest);
+                    }
+
+                    let mtime = entry
+                        .has_last_modified_date
+                        .then(|| SystemTime::from(entry.last_modified_date()))
+                        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                        .map(|duration| duration.as_secs());
+                    match conflict
+                        .resolve(&dest, Some(entry.size()), mtime)
+                        .map_err(sevenz::Error::other)?
+                    {
+                        Some(target) => {
+                            limiter.track(&target);
+                            sevenz::default_entry_extract_fn(
+                                entry,
+                                &mut limiter.reader(reader),
+                                &target,
+                            )
+                        }
+                        // 跳过的条目也要读完，固实压缩的后续条目依赖此前的数据
+                        None => {
+                            io::copy(&mut limiter.reader(reader), &mut io::sink())
+                                .map_err(sevenz::Error::io)?;
+                            Ok(true)
+                        }
+                    }
+                },
+            );
+            if let Err(e) = result {
+                return Err(limiter.error(format!("解压失败: {}", e)));
             }
         }
 
         Ok(())
     }
 }
+
+// 将文件或目录递归写入归档，条目名为相对root的路径；只处理普通文件和目录
+fn push_path<W: io::Write + io::Seek>(
+    writer: &mut sevenz::SevenZWriter<W>,
+    path: &Path,
+    root: &Path,
+) -> Result<(), sevenz::Error> {
+    let name = path
+        .strip_prefix(root)
+        .map_err(|e| sevenz::Error::other(e.to_string()))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let entry = sevenz::SevenZArchiveEntry::from_path(path, name);
+
+    if path.is_dir() {
+        writer.push_archive_entry::<&[u8]>(entry, None)?;
+        for child in path.read_dir().map_err(sevenz::Error::io)? {
+            let child = child.map_err(sevenz::Error::io)?;
+            let file_type = child.file_type().map_err(sevenz::Error::io)?;
+            if file_type.is_dir() || file_type.is_file() {
+                push_path(writer, &child.path(), root)?;
+            }
+        }
+    } else {
+        let file = std::fs::File::open(path).map_err(sevenz::Error::io)?;
+        writer.push_archive_entry(entry, Some(file))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::{
+    is_selected, ArchiveEntry, ArchiveReader, Capabilities, ComdeAble, CompressionOptions,
+    DecompressionOptions,
+};
+
+// ar归档的全局头部
+const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+const THIN_MAGIC: &[u8; 8] = b"!<thin>\n";
+
+// 每个成员头部的大小及结束标记
+const HEADER_SIZE: usize = 60;
+const HEADER_END: &[u8; 2] = b"`\n";
+
+// 长文件名表和BSD格式文件名的最大长度，头部中的大小字段不可信，按此上限分配
+const MAX_NAME_TABLE_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_NAME_SIZE: u64 = 64 * 1024;
+
+// ar成员头部
+pub struct ArMember {
+    pub name: String,
+    pub mtime: u64,
+    pub mode: u32,
+    pub size: u64,
+}
+
+// ar归档只读支持 (GNU/System V与BSD长文件名)
+#[derive(Clone)]
+pub struct ArReader;
+
+impl ArReader {
+    pub fn new() -> Self {
+        ArReader
+    }
+
+    // 依次访问归档中的每个成员，跳过符号表和长文件名表
+    pub fn walk<R, F>(&self, reader: R, mut visit: F) -> Result<(), String>
+    where
+        R: Read,
+        F: FnMut(&ArMember, &mut dyn Read) -> Result<(), String>,
+    {
+        let mut reader = BufReader::new(reader);
+
+        let mut magic = [0u8; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| "不是有效的AR文件".to_string())?;
+        if &magic == THIN_MAGIC {
+            return Err("不支持精简(thin)AR归档".to_string());
+        }
+        if &magic != AR_MAGIC {
+            return Err("不是有效的AR文件".to_string());
+        }
+
+        // GNU格式的长文件名表
+        let mut long_names = Vec::new();
+
+        loop {
+            let mut header = [0u8; HEADER_SIZE];
+            match reader.read(&mut header[..1]).map_err(|e| e.to_string())? {
+                0 => break,
+                _ => reader
+                    .read_exact(&mut header[1..])
+                    .map_err(|_| "AR文件被截断".to_string())?,
+            }
+            if &header[58..60] != HEADER_END {
+                return Err("AR成员头部损坏".to_string());
+            }
+
+            let raw_name = String::from_utf8_lossy(&header[0..16])
+                .trim_end()
+                .to_string();
+            let mtime = Self::parse_number(&header[16..28], 10)?;
+            let mode = Self::parse_number(&header[40..48], 8)? as u32;
+            let mut size = Self::parse_number(&header[48..58], 10)?;
+            let padding = size % 2;
+
+            let name =
+                if raw_name == "/" || raw_name == "/SYM64/" || raw_name.starts_with("__.SYMDEF") {
+                    // 符号表
+                    None
+                } else if raw_name == "//" {
+                    // 长文件名表
+                    if size > MAX_NAME_TABLE_SIZE {
+                        return Err("AR长文件名表过大".to_string());
+                    }
+                    long_names = vec![0u8; size as usize];
+                    reader
+                        .read_exact(&mut long_names)
+                        .map_err(|_| "AR文件被截断".to_string())?;
+                    size = 0;
+                    None
+                } else if let Some(length) = raw_name.strip_prefix("#1/") {
+                    // BSD格式: 文件名紧跟在头部之后，计入成员大小
+                    let length: u64 = length.parse().map_err(|_| "AR成员头部损坏".to_string())?;
+                    if length > MAX_NAME_SIZE {
+                        return Err("AR成员名称过长".to_string());
+                    }
+                    let mut name = vec![0u8; length as usize];
+                    reader
+                        .read_exact(&mut name)
+                        .map_err(|_| "AR文件被截断".to_string())?;
+                    size = size.checked_sub(length).ok_or("AR成员头部损坏")?;
+                    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                    Some(String::from_utf8_lossy(&name[..end]).to_string())
+                } else if let Some(offset) = raw_name.strip_prefix('/') {
+                    // GNU格式: 名称在长文件名表中，以 "/\n" 结尾
+                    let offset: usize = offset.parse().map_err(|_| "AR成员头部损坏".to_string())?;
+                    let rest = long_names.get(offset..).ok_or("AR长文件名表损坏")?;
+                    let end = rest
+                        .windows(2)
+                        .position(|window| window == b"/\n")
+                        .or_else(|| rest.iter().position(|&b| b == b'\n'))
+                        .unwrap_or(rest.len());
+                    Some(String::from_utf8_lossy(&rest[..end]).to_string())
+                } else {
+                    Some(raw_name.strip_suffix('/').unwrap_or(&raw_name).to_string())
+                };
+
+            let mut data = (&mut reader).take(size);
+            if let Some(name) = name {
+                let member = ArMember {
+                    name,
+                    mtime,
+                    mode,
+                    size,
+                };
+                visit(&member, &mut data)?;
+            }
+
+            // 丢弃未读取的数据，成员按2字节对齐
+            io::copy(&mut data, &mut io::sink()).map_err(|e| e.to_string())?;
+            io::copy(&mut (&mut reader).take(padding), &mut io::sink())
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    fn parse_number(field: &[u8], radix: u32) -> Result<u64, String> {
+        let text = String::from_utf8_lossy(field);
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(0);
+        }
+        u64::from_str_radix(text, radix).map_err(|_| "AR成员头部包含无效的数字字段".to_string())
+    }
+}
+
+impl ArchiveReader for ArReader {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        let mut entries = Vec::new();
+
+        self.walk(file, |member, _| {
+            entries.push(ArchiveEntry {
+                path: member.name.clone(),
+                size: member.size,
+                is_dir: false,
+                mtime: Some(member.mtime),
+            });
+            Ok(())
+        })?;
+
+        Ok(entries)
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        let output_dir = Path::new(output_path);
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        let limiter = Limiter::new(archive_path, conflict.settings().limits);
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        self.walk(file, |member, data| {
+            limiter.begin_entry(&member.name, Some(member.size))?;
+            if !is_selected(&member.name, selection) {
+                return Ok(());
+            }
+
+            let target = conflict
+                .entry_path(output_dir, &member.name)?
+                .ok_or_else(|| format!("不安全的成员名称: {}", member.name))?;
+            let Some(target) = conflict.resolve(&target, Some(member.size), Some(member.mtime))?
+            else {
+                return Ok(());
+            };
+            limiter.track(&target);
+            let mut output_file = File::create(&target).map_err(|e| e.to_string())?;
+            io::copy(&mut limiter.reader(data), &mut output_file).map_err(|e| e.to_string())?;
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                if member.mode != 0 {
+                    fs::set_permissions(&target, fs::Permissions::from_mode(member.mode & 0o7777))
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+impl ComdeAble for ArReader {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::read_only_archive()
+    }
+
+    async fn compress(
+        &self,
+        _input_paths: Vec<&str>,
+        _output_path: &str,
+        _options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        Err("AR为只读格式，不支持压缩".to_string())
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        for input_path in input_paths {
+            self.extract(input_path, output_path, &[], &mut conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::testing::ar_archive;
+
+    // 依次读取所有成员的名称和数据
+    fn members(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let mut members = Vec::new();
+        ArReader::new().walk(archive, |member, data| {
+            let mut content = Vec::new();
+            data.read_to_end(&mut content).map_err(|e| e.to_string())?;
+            assert_eq!(content.len() as u64, member.size);
+            members.push((member.name.clone(), content));
+            Ok(())
+        })?;
+        Ok(members)
+    }
+
+    // 奇数长度的成员后有一字节填充，不应算入数据或影响下一个成员
+    #[test]
+    fn skips_odd_length_padding() {
+        let archive = ar_archive(&[("a.txt/", b"abc"), ("b.txt/", b"de"), ("c", b"f")]);
+        assert_eq!(
+            members(&archive).unwrap(),
+            [
+                ("a.txt".to_string(), b"abc".to_vec()),
+                ("b.txt".to_string(), b"de".to_vec()),
+                ("c".to_string(), b"f".to_vec()),
+            ]
+        );
+    }
+
+    // GNU格式的 // 长文件名表和BSD格式的 #1/ 名称，符号表被跳过
+    #[test]
+    fn reads_long_names() {
+        let table = b"a-very-long-member-name.txt/\nsecond-long-member.o/\n";
+        let mut bsd = b"bsd-long-member-name".to_vec();
+        bsd.extend_from_slice(b"data");
+        let archive = ar_archive(&[
+            ("/", b"\0\0\0\0"),
+            ("//", table),
+            ("/0", b"one"),
+            ("/29", b"two"),
+            ("#1/20", &bsd),
+        ]);
+        assert_eq!(
+            members(&archive).unwrap(),
+            [
+                ("a-very-long-member-name.txt".to_string(), b"one".to_vec()),
+                ("second-long-member.o".to_string(), b"two".to_vec()),
+                ("bsd-long-member-name".to_string(), b"data".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_long_name_offset() {
+        let archive = ar_archive(&[("//", b"name/\n"), ("/100", b"x")]);
+        assert_eq!(members(&archive).unwrap_err(), "AR长文件名表损坏");
+    }
+
+    // 头部不完整或结束标记错误时报错，而不是当作归档结束
+    #[test]
+    fn rejects_truncated_header() {
+        let archive = ar_archive(&[("a.txt/", b"abc")]);
+        assert_eq!(members(&archive[..30]).unwrap_err(), "AR文件被截断");
+
+        let mut archive = ar_archive(&[("a.txt/", b"abc")]);
+        archive[66] = b'x';
+        assert_eq!(members(&archive).unwrap_err(), "AR成员头部损坏");
+
+        assert_eq!(members(b"!<thin>\n").unwrap_err(), "不支持精简(thin)AR归档");
+        assert_eq!(members(b"!<arc").unwrap_err(), "不是有效的AR文件");
+    }
+}
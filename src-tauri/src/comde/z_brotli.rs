@@ -0,0 +1,309 @@
+use brotli::enc::BrotliEncoderParams;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// 默认质量和窗口大小 (窗口大小为以2为底的对数，范围10-24)
+const DEFAULT_QUALITY: u32 = 11;
+const DEFAULT_WINDOW_SIZE: u32 = 22;
+
+// 流式解码时的缓冲区大小
+const BROTLI_BUFFER_SIZE: usize = 64 * 1024;
+
+// 批量模式的结果，单个文件失败不影响其余文件
+#[derive(Debug, Default)]
+pub struct TreeReport {
+    // 生成的.br文件数
+    pub compressed: usize,
+    // 按冲突策略跳过的文件数，如已有不旧于源文件的.br文件
+    pub skipped: usize,
+    // 无法处理的文件或目录及原因
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+#[derive(Clone)]
+pub struct BrotliCompressor;
+
+impl BrotliCompressor {
+    pub fn new() -> Self {
+        BrotliCompressor
+    }
+
+    // 为目录树中的每个文件生成同级的.br文件；.br文件已存在时按冲突策略处理，
+    // 单个文件或子目录出错时记录在结果中并继续处理其余文件
+    pub fn compress_tree(
+        &self,
+        dir_path: &Path,
+        quality: u32,
+        window_size: u32,
+        conflict: &mut ConflictResolver,
+    ) -> Result<TreeReport, String> {
+        let mut report = TreeReport::default();
+        self.compress_dir(dir_path, quality, window_size, conflict, &mut report)?;
+        Ok(report)
+    }
+
+    fn compress_dir(
+        &self,
+        dir_path: &Path,
+        quality: u32,
+        window_size: u32,
+        conflict: &mut ConflictResolver,
+        report: &mut TreeReport,
+    ) -> Result<(), String> {
+        // 先列出全部条目，处理过程中新建的.br和临时文件不会出现在遍历中
+        let entries = fs::read_dir(dir_path)
+            .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| format!("{}: {}", dir_path.display(), e))?;
+
+        for entry in entries {
+            let path = entry.path();
+            // 不跟随符号链接，避免链接成环时无限递归
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(e) => {
+                    report.failed.push((path, e.to_string()));
+                    continue;
+                }
+            };
+
+            if file_type.is_dir() {
+                // 递归处理子目录，无法读取的子目录记为失败
+                if let Err(e) = self.compress_dir(&path, quality, window_size, conflict, report) {
+                    report.failed.push((path, e));
+                }
+            } else if file_type.is_file() && path.extension().is_none_or(|ext| ext != "br") {
+                // 跳过已有的.br文件，其余文件写入 文件名.br
+                let mut output_path = path.clone().into_os_string();
+                output_path.push(".br");
+                let size = entry.metadata().ok().map(|metadata| metadata.len());
+                let result = conflict
+                    .resolve(Path::new(&output_path), size, modified_secs(&path))
+                    .and_then(|target| match target {
+                        Some(target) => self
+                            .compress_file(&path, &target, quality, window_size)
+                            .map(|_| true),
+                        None => Ok(false),
+                    });
+                match result {
+                    Ok(true) => report.compressed += 1,
+                    Ok(false) => report.skipped += 1,
+                    Err(e) => report.failed.push((path, e)),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // 压缩单个文件
+    fn compress_file(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        quality: u32,
+        window_size: u32,
+    ) -> Result<(), String> {
+        let params = BrotliEncoderParams {
+            quality: quality.min(11) as i32,
+            lgwin: window_size.clamp(10, 24) as i32,
+            ..Default::default()
+        };
+
+        let mut input_file = File::open(input_path).map_err(|e| e.to_string())?;
+        let mut output_file = AtomicFile::create(output_path)?;
+
+        brotli::BrotliCompress(&mut input_file, &mut output_file, &params)
+            .map_err(|e| format!("压缩 {} 失败: {}", input_path.display(), e))?;
+
+        output_file.commit()
+    }
+}
+
+impl ComdeAble for BrotliCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 11,
+                default: DEFAULT_QUALITY as i32,
+            }),
+            methods: vec!["brotli"],
+            compress_options: vec!["quality", "window_size"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        // 极限预设同时使用最大窗口
+        Some(CompressionOptions::Brotli {
+            quality: preset.pick([0, 1, 5, DEFAULT_QUALITY, DEFAULT_QUALITY, 11]),
+            window_size: match preset {
+                CompressionPreset::Ultra => 24,
+                _ => DEFAULT_WINDOW_SIZE,
+            },
+            settings: Default::default(),
+        })
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        // 处理压缩选项
+        let (quality, window_size) = match options {
+            Some(CompressionOptions::Brotli {
+                quality,
+                window_size,
+                ..
+            }) => (quality, window_size),
+            _ => (DEFAULT_QUALITY, DEFAULT_WINDOW_SIZE),
+        };
+
+        // BROTLI只能压缩单个文件，不能打包多个文件
+        if input_paths.len() > 1 {
+            return Err("BROTLI只能压缩单个文件，无法打包多个文件".to_string());
+        }
+
+        let input_path = input_paths[0];
+        let path = Path::new(input_path);
+
+        if path.is_dir() {
+            return Err("BROTLI不能直接压缩目录，请使用批量模式为每个文件生成.br文件".to_string());
+        }
+
+        self.compress_file(path, Path::new(output_path), quality, window_size)
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // BROTLI只能处理单个文件
+        for input_path in input_paths {
+            let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，此时还未创建输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+
+            // 获取输出文件名
+            let output_file_path = if Path::new(output_path).is_dir() {
+                let input_filename = Path::new(input_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or("无法获取输入文件名")?;
+
+                // 移除.br扩展名
+                let original_name = if input_filename.ends_with(".br") {
+                    &input_filename[0..input_filename.len() - 3]
+                } else {
+                    input_filename
+                };
+
+                Path::new(output_path).join(original_name)
+            } else {
+                Path::new(output_path).to_path_buf()
+            };
+
+            // 解压数据
+            let mut buffer = Vec::new();
+            limiter
+                .reader(brotli::Decompressor::new(input_file, BROTLI_BUFFER_SIZE))
+                .read_to_end(&mut buffer)
+                .map_err(|e| e.to_string())?;
+
+            // 目标文件已存在时按冲突策略处理
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                Some(buffer.len() as u64),
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
+            output_file.write_all(&buffer).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::TempDir;
+    use filetime::FileTime;
+
+    fn set_mtime(path: &Path, secs: i64) {
+        filetime::set_file_mtime(path, FileTime::from_unix_time(secs, 0)).unwrap();
+    }
+
+    fn compress_tree(dir: &Path, policy: ConflictPolicy) -> TreeReport {
+        BrotliCompressor::new()
+            .compress_tree(dir, 1, 16, &mut ConflictResolver::new(policy))
+            .unwrap()
+    }
+
+    // 按保留较新策略时，已是最新的.br文件被跳过，源文件更新后重新压缩
+    #[test]
+    fn skips_up_to_date_siblings() {
+        let dir = TempDir::new("brotli-newer");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "alpha").unwrap();
+        fs::write(dir.join("sub/b.txt"), "beta").unwrap();
+
+        let report = compress_tree(dir.path(), ConflictPolicy::KeepNewer);
+        assert_eq!((report.compressed, report.skipped), (2, 0));
+        assert!(report.failed.is_empty());
+
+        set_mtime(&dir.join("a.txt"), 1_000);
+        set_mtime(&dir.join("a.txt.br"), 2_000);
+        set_mtime(&dir.join("sub/b.txt"), 3_000);
+        fs::write(dir.join("sub/b.txt.br"), "stale").unwrap();
+        set_mtime(&dir.join("sub/b.txt.br"), 2_000);
+
+        let report = compress_tree(dir.path(), ConflictPolicy::KeepNewer);
+        assert_eq!((report.compressed, report.skipped), (1, 1));
+        assert_ne!(fs::read(dir.join("sub/b.txt.br")).unwrap(), b"stale");
+
+        let report = compress_tree(dir.path(), ConflictPolicy::Rename);
+        assert_eq!((report.compressed, report.skipped), (2, 0));
+        assert!(dir.join("a.txt (1).br").exists());
+        assert!(dir.join("sub/b.txt (1).br").exists());
+    }
+
+    // 单个文件失败时记录原因并继续处理其余文件
+    #[test]
+    fn reports_per_file_errors() {
+        let dir = TempDir::new("brotli-errors");
+        fs::write(dir.join("a.txt"), "alpha").unwrap();
+        fs::write(dir.join("b.txt"), "beta").unwrap();
+        fs::create_dir(dir.join("a.txt.br")).unwrap();
+
+        let report = compress_tree(dir.path(), ConflictPolicy::Overwrite);
+        assert_eq!(report.compressed, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, dir.join("a.txt"));
+        assert!(dir.join("b.txt.br").is_file());
+    }
+}
@@ -1,11 +1,17 @@
-use bzip2::read::BzDecoder;
+use bzip2::read::MultiBzDecoder;
 use bzip2::write::BzEncoder;
 use bzip2::Compression;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
 
 #[derive(Clone)]
 pub struct Bzip2Compressor;
@@ -17,6 +23,32 @@ impl Bzip2Compressor {
 }
 
 impl ComdeAble for Bzip2Compressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 1,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["bzip2"],
+            compress_options: vec!["level"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        // BZIP2没有不压缩的模式，存储预设使用最低级别
+        Some(CompressionOptions::Bzip2 {
+            level: preset.pick([1, 1, 3, 6, 8, 9]),
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -49,7 +81,7 @@ impl ComdeAble for Bzip2Compressor {
             .map_err(|e| e.to_string())?;
 
         // 创建输出文件
-        let output_file = File::create(output_path).map_err(|e| e.to_string())?;
+        let output_file = AtomicFile::create(output_path)?;
 
         // 选择压缩级别 (1-9，9为最高压缩率)
         // 修复: Compression::Fastest和Compression::Best不存在，使用函数替代
@@ -62,20 +94,22 @@ impl ComdeAble for Bzip2Compressor {
         // 压缩数据
         let mut encoder = BzEncoder::new(output_file, compression_level);
         encoder.write_all(&buffer).map_err(|e| e.to_string())?;
-        encoder.finish().map_err(|e| e.to_string())?;
-
-        Ok(())
+        encoder.finish().map_err(|e| e.to_string())?.commit()
     }
 
     async fn decompress(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
-        _options: Option<DecompressionOptions>,
+        options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
         // BZIP2只能处理单个文件
         for input_path in input_paths {
             let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，并删除已创建的输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
 
             // 获取输出文件名
             let output_file_path = if Path::new(output_path).is_dir() {
@@ -96,12 +130,23 @@ impl ComdeAble for Bzip2Compressor {
                 Path::new(output_path).to_path_buf()
             };
 
-            // 解压数据
-            let mut decoder = BzDecoder::new(input_file);
+            // 解压数据，pbzip2等工具生成的多个流会被依次解压
+            let mut decoder = MultiBzDecoder::new(input_file);
+            // 目标文件已存在时按冲突策略处理，流格式以压缩文件的修改时间作为条目时间
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                None,
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            limiter.track(&output_file_path);
             let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
             let mut buffer = Vec::new();
 
-            decoder
+            limiter
+                .reader(&mut decoder)
                 .read_to_end(&mut buffer)
                 .map_err(|e| e.to_string())?;
             output_file.write_all(&buffer).map_err(|e| e.to_string())?;
@@ -110,3 +155,25 @@ impl ComdeAble for Bzip2Compressor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::check_concatenated;
+
+    // pbzip2等工具生成的多个BZIP2流应全部解压
+    #[tokio::test]
+    async fn decompresses_concatenated_streams() {
+        let encode = |part: &[u8]| {
+            let mut encoder = BzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(part).unwrap();
+            encoder.finish().unwrap()
+        };
+        let options = DecompressionOptions::Bzip2 {
+            conflict: ConflictPolicy::Rename,
+            settings: Default::default(),
+        };
+        check_concatenated(&Bzip2Compressor::new(), "bz2", encode, options).await;
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::z_tar::TarCodec;
+use super::{
+    is_selected, normalize_entry_path, ArchiveEntry, ArchiveReader, Capabilities, ComdeAble,
+    CompressionOptions, DecompressionOptions,
+};
+
+// newc (070701)、带校验和的newc (070702) 与 odc (070707) 格式的魔数
+const NEWC_MAGIC: &[u8] = b"070701";
+const NEWC_CRC_MAGIC: &[u8] = b"070702";
+const ODC_MAGIC: &[u8] = b"070707";
+
+// 归档结束标记
+const TRAILER: &str = "TRAILER!!!";
+
+// 条目名称和符号链接目标的最大长度，头部中的长度字段不可信，按此上限分配
+const MAX_NAME_SIZE: usize = 64 * 1024;
+
+// 文件类型位
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+// cpio条目头部
+struct CpioHeader {
+    name: String,
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u32,
+    mtime: u64,
+    size: u64,
+    // newc格式的头部、文件名和数据都按4字节对齐
+    aligned: bool,
+}
+
+impl CpioHeader {
+    fn file_type(&self) -> u32 {
+        self.mode & S_IFMT
+    }
+}
+
+// cpio归档只读支持 (newc/odc)，可处理initramfs常见的多段拼接与压缩
+#[derive(Clone)]
+pub struct CpioReader;
+
+impl CpioReader {
+    pub fn new() -> Self {
+        CpioReader
+    }
+
+    // 依次访问归档中的每个条目，数据读取器只包含该条目的数据
+    fn walk<F>(&self, archive_path: &str, mut visit: F) -> Result<(), String>
+    where
+        F: FnMut(&CpioHeader, &mut dyn Read) -> Result<(), String>,
+    {
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        let mut reader: Box<dyn BufRead> = Box::new(BufReader::new(file));
+        let mut found = false;
+
+        loop {
+            // 跳过拼接的归档之间的零填充
+            Self::skip_zeros(&mut reader)?;
+            let magic = reader.fill_buf().map_err(|e| e.to_string())?;
+            if magic.is_empty() {
+                break;
+            }
+
+            if magic.starts_with(b"0707") {
+                found = true;
+                while let Some(header) = Self::read_header(&mut reader)? {
+                    let mut data = (&mut reader).take(header.size);
+                    visit(&header, &mut data)?;
+
+                    // 丢弃未读取的数据和对齐填充
+                    io::copy(&mut data, &mut io::sink()).map_err(|e| e.to_string())?;
+                    if header.aligned {
+                        Self::skip(&mut reader, Self::padding(header.size))?;
+                    }
+                }
+            } else {
+                // 其余部分被压缩 (如 early microcode + 压缩的主initramfs)
+                let codec = TarCodec::from_magic(magic);
+                if codec == TarCodec::None {
+                    if found {
+                        break;
+                    }
+                    return Err("不是有效的CPIO文件".to_string());
+                }
+                reader = Box::new(BufReader::new(codec.decoder(reader)?));
+            }
+        }
+
+        Ok(())
+    }
+
+    // 读取下一个条目头部，遇到结束标记时返回None
+    fn read_header(reader: &mut dyn BufRead) -> Result<Option<CpioHeader>, String> {
+        let mut magic = [0u8; 6];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| "CPIO文件被截断".to_string())?;
+
+        let (mut header, name_size) = if magic == NEWC_MAGIC || magic == NEWC_CRC_MAGIC {
+            let mut fields = [0u8; 104];
+            reader
+                .read_exact(&mut fields)
+                .map_err(|_| "CPIO文件被截断".to_string())?;
+            let field = |index: usize| Self::parse_number(&fields[index * 8..index * 8 + 8], 16);
+
+            // ino mode uid gid nlink mtime filesize devmajor devminor rdevmajor rdevminor namesize check
+            let header = CpioHeader {
+                name: String::new(),
+                dev: (field(7)? << 32) | field(8)?,
+                ino: field(0)?,
+                mode: field(1)? as u32,
+                nlink: field(4)? as u32,
+                mtime: field(5)?,
+                size: field(6)?,
+                aligned: true,
+            };
+            (header, field(11)? as usize)
+        } else if magic == ODC_MAGIC {
+            let mut fields = [0u8; 70];
+            reader
+                .read_exact(&mut fields)
+                .map_err(|_| "CPIO文件被截断".to_string())?;
+            let field =
+                |start: usize, len: usize| Self::parse_number(&fields[start..start + len], 8);
+
+            // dev ino mode uid gid nlink rdev mtime namesize filesize
+            let header = CpioHeader {
+                name: String::new(),
+                dev: field(0, 6)?,
+                ino: field(6, 6)?,
+                mode: field(12, 6)? as u32,
+                nlink: field(30, 6)? as u32,
+                mtime: field(42, 11)?,
+                size: field(59, 11)?,
+                aligned: false,
+            };
+            (header, field(53, 6)? as usize)
+        } else {
+            return Err("不支持的CPIO格式，仅支持newc和odc格式".to_string());
+        };
+
+        // 文件名以NUL结尾
+        if name_size > MAX_NAME_SIZE {
+            return Err("CPIO条目名称过长".to_string());
+        }
+        let mut name = vec![0u8; name_size];
+        reader
+            .read_exact(&mut name)
+            .map_err(|_| "CPIO文件被截断".to_string())?;
+        if let Some(end) = name.iter().position(|&b| b == 0) {
+            name.truncate(end);
+        }
+        header.name = String::from_utf8_lossy(&name).to_string();
+
+        if header.aligned {
+            Self::skip(reader, Self::padding(110 + name_size as u64))?;
+        }
+
+        if header.name == TRAILER {
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
+    fn parse_number(field: &[u8], radix: u32) -> Result<u64, String> {
+        std::str::from_utf8(field)
+            .ok()
+            .and_then(|text| u64::from_str_radix(text.trim_end_matches('\0').trim(), radix).ok())
+            .ok_or_else(|| "CPIO头部包含无效的数字字段".to_string())
+    }
+
+    // newc格式按4字节对齐所需的填充长度
+    fn padding(len: u64) -> u64 {
+        (4 - len % 4) % 4
+    }
+
+    fn skip(reader: &mut dyn BufRead, len: u64) -> Result<(), String> {
+        let skipped =
+            io::copy(&mut reader.take(len), &mut io::sink()).map_err(|e| e.to_string())?;
+        if skipped != len {
+            return Err("CPIO文件被截断".to_string());
+        }
+        Ok(())
+    }
+
+    fn skip_zeros(reader: &mut dyn BufRead) -> Result<(), String> {
+        loop {
+            let buffer = reader.fill_buf().map_err(|e| e.to_string())?;
+            let zeros = buffer.iter().take_while(|&&b| b == 0).count();
+            let available = buffer.len();
+            reader.consume(zeros);
+
+            if zeros < available || available == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // 写出单个条目
+    fn extract_entry(
+        &self,
+        header: &CpioHeader,
+        data: &mut dyn Read,
+        target: &Path,
+        hard_links: &mut HashMap<(u64, u64), Vec<PathBuf>>,
+        conflict: &mut ConflictResolver,
+        limiter: &Limiter,
+    ) -> Result<(), String> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        match header.file_type() {
+            S_IFDIR => {
+                fs::create_dir_all(target).map_err(|e| e.to_string())?;
+            }
+            S_IFREG => {
+                // 目标已存在时按冲突策略处理，之后的写入和硬链接都使用处理后的路径
+                let Some(target) =
+                    conflict.resolve(target, Some(header.size), Some(header.mtime))?
+                else {
+                    return Ok(());
+                };
+                let target = target.as_path();
+                limiter.track(target);
+                let mut file = File::create(target).map_err(|e| e.to_string())?;
+                io::copy(&mut limiter.reader(data), &mut file).map_err(|e| e.to_string())?;
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs::set_permissions(target, fs::Permissions::from_mode(header.mode & 0o7777))
+                        .map_err(|e| e.to_string())?;
+                }
+
+                // newc格式中硬链接只有最后一个条目携带数据，收到数据后再补建之前的链接
+                if header.nlink > 1 {
+                    let links = hard_links.entry((header.dev, header.ino)).or_default();
+                    if header.size > 0 {
+                        for link in links.drain(..) {
+                            let _ = fs::remove_file(&link);
+                            if fs::hard_link(target, &link).is_err() {
+                                fs::copy(target, &link).map_err(|e| e.to_string())?;
+                            }
+                        }
+                    } else {
+                        links.push(target.to_path_buf());
+                    }
+                }
+            }
+            S_IFLNK => {
+                if header.size > MAX_NAME_SIZE as u64 {
+                    return Err("CPIO符号链接目标过长".to_string());
+                }
+                let mut link_target = String::new();
+                data.read_to_string(&mut link_target)
+                    .map_err(|e| e.to_string())?;
+
+                #[cfg(unix)]
+                {
+                    // 与普通文件一样按冲突策略处理，覆盖时resolve会删除已有的文件或链接
+                    let Some(target) =
+                        conflict.resolve(target, Some(header.size), Some(header.mtime))?
+                    else {
+                        return Ok(());
+                    };
+                    std::os::unix::fs::symlink(&link_target, target).map_err(|e| e.to_string())?;
+                }
+            }
+            // 设备文件和命名管道需要root权限，跳过
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl ArchiveReader for CpioReader {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        let mut entries = Vec::new();
+
+        self.walk(archive_path, |header, _| {
+            let path = normalize_entry_path(&header.name);
+            if !path.is_empty() {
+                entries.push(ArchiveEntry {
+                    path,
+                    size: header.size,
+                    is_dir: header.file_type() == S_IFDIR,
+                    mtime: Some(header.mtime),
+                });
+            }
+            Ok(())
+        })?;
+
+        Ok(entries)
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        let output_dir = Path::new(output_path);
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        let mut hard_links = HashMap::new();
+        let limiter = Limiter::new(archive_path, conflict.settings().limits);
+        self.walk(archive_path, |header, data| {
+            limiter.begin_entry(&header.name, Some(header.size))?;
+            if normalize_entry_path(&header.name).is_empty()
+                || !is_selected(&header.name, selection)
+            {
+                return Ok(());
+            }
+
+            // 目录同名时合并，其他条目与本次解压的条目重名时重命名
+            let target = if header.file_type() == S_IFDIR {
+                conflict.dir_path(output_dir, &header.name)?
+            } else {
+                conflict.entry_path(output_dir, &header.name)?
+            }
+            .ok_or_else(|| format!("不安全的条目路径: {}", header.name))?;
+            self.extract_entry(header, data, &target, &mut hard_links, conflict, &limiter)
+        })
+    }
+}
+
+impl ComdeAble for CpioReader {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::read_only_archive()
+    }
+
+    async fn compress(
+        &self,
+        _input_paths: Vec<&str>,
+        _output_path: &str,
+        _options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        Err("CPIO为只读格式，不支持压缩".to_string())
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        for input_path in input_paths {
+            self.extract(input_path, output_path, &[], &mut conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::TempDir;
+
+    // newc格式的条目: 头部和名称、数据各自按4字节对齐
+    fn newc_entry(name: &str, mode: u32, data: &[u8]) -> Vec<u8> {
+        let name_size = name.len() + 1;
+        // ino mode uid gid nlink mtime filesize devmajor devminor rdevmajor rdevminor namesize check
+        let fields = [
+            1,
+            mode,
+            0,
+            0,
+            1,
+            1_700_000_000,
+            data.len() as u32,
+            0,
+            0,
+            0,
+            0,
+        ];
+        let mut entry = b"070701".to_vec();
+        for field in fields.into_iter().chain([name_size as u32, 0]) {
+            entry.extend_from_slice(format!("{:08X}", field).as_bytes());
+        }
+        entry.extend_from_slice(name.as_bytes());
+        entry.push(0);
+        entry.resize(
+            entry.len() + CpioReader::padding(110 + name_size as u64) as usize,
+            0,
+        );
+        entry.extend_from_slice(data);
+        entry.resize(
+            entry.len() + CpioReader::padding(data.len() as u64) as usize,
+            0,
+        );
+        entry
+    }
+
+    // odc格式的条目，没有对齐填充
+    fn odc_entry(name: &str, mode: u32, data: &[u8]) -> Vec<u8> {
+        let mut entry = format!(
+            "070707{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:06o}{:011o}{:06o}{:011o}",
+            0,
+            1,
+            mode,
+            0,
+            0,
+            1,
+            0,
+            1_700_000_000u64,
+            name.len() + 1,
+            data.len()
+        )
+        .into_bytes();
+        entry.extend_from_slice(name.as_bytes());
+        entry.push(0);
+        entry.extend_from_slice(data);
+        entry
+    }
+
+    fn write_archive(dir: &TempDir, data: &[u8]) -> String {
+        let path = dir.join("archive.cpio");
+        fs::write(&path, data).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn names(archive: &str) -> Result<Vec<(String, u64)>, String> {
+        Ok(CpioReader::new()
+            .list(archive)?
+            .into_iter()
+            .map(|entry| (entry.path, entry.size))
+            .collect())
+    }
+
+    // 奇数长度的名称和数据后的填充不影响后续条目
+    #[test]
+    fn reads_padded_newc_entries() {
+        let dir = TempDir::new("cpio-newc");
+        let mut data = newc_entry("dir", S_IFDIR | 0o755, b"");
+        data.extend(newc_entry("dir/a.txt", S_IFREG | 0o644, b"hello"));
+        data.extend(newc_entry("dir/bc.txt", S_IFREG | 0o644, b"abc"));
+        data.extend(newc_entry(TRAILER, 0, b""));
+        let archive = write_archive(&dir, &data);
+
+        assert_eq!(
+            names(&archive).unwrap(),
+            [
+                ("dir".to_string(), 0),
+                ("dir/a.txt".to_string(), 5),
+                ("dir/bc.txt".to_string(), 3)
+            ]
+        );
+
+        let output = dir.join("out");
+        CpioReader::new()
+            .extract(
+                &archive,
+                output.to_str().unwrap(),
+                &[],
+                &mut ConflictResolver::new(ConflictPolicy::Overwrite),
+            )
+            .unwrap();
+        assert_eq!(fs::read(output.join("dir/a.txt")).unwrap(), b"hello");
+        assert_eq!(fs::read(output.join("dir/bc.txt")).unwrap(), b"abc");
+    }
+
+    // 零填充后拼接的第二段归档 (odc格式) 同样被读取
+    #[test]
+    fn reads_concatenated_odc_archive() {
+        let dir = TempDir::new("cpio-odc");
+        let mut data = newc_entry("early.bin", S_IFREG | 0o644, b"x");
+        data.extend(newc_entry(TRAILER, 0, b""));
+        data.resize(data.len() + 512, 0);
+        data.extend(odc_entry("main.txt", S_IFREG | 0o644, b"odd"));
+        data.extend(odc_entry(TRAILER, 0, b""));
+        let archive = write_archive(&dir, &data);
+
+        assert_eq!(
+            names(&archive).unwrap(),
+            [("early.bin".to_string(), 1), ("main.txt".to_string(), 3)]
+        );
+    }
+
+    // 头部、名称或填充不完整时报告截断
+    #[test]
+    fn rejects_truncated_archive() {
+        let dir = TempDir::new("cpio-truncated");
+        let entry = newc_entry("a.txt", S_IFREG | 0o644, b"hello");
+        for len in [40, 112, 118] {
+            let archive = write_archive(&dir, &entry[..len]);
+            assert_eq!(names(&archive).unwrap_err(), "CPIO文件被截断", "{}", len);
+        }
+
+        let mut bad = entry.clone();
+        bad[6] = b'g';
+        let archive = write_archive(&dir, &bad);
+        assert_eq!(names(&archive).unwrap_err(), "CPIO头部包含无效的数字字段");
+
+        let archive = write_archive(&dir, b"070799");
+        assert!(names(&archive).unwrap_err().contains("不支持的CPIO格式"));
+        let archive = write_archive(&dir, b"not a cpio archive");
+        assert_eq!(names(&archive).unwrap_err(), "不是有效的CPIO文件");
+    }
+}
@@ -0,0 +1,279 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::z_ar::ArReader;
+use super::z_tar::{TarCodec, TarCompressor};
+use super::{
+    normalize_entry_path, ArchiveEntry, ArchiveReader, Capabilities, ComdeAble, CompressionOptions,
+    DecompressionOptions,
+};
+
+// 控制文件在列表和解压结果中的目录，与 dpkg-deb -R 的布局一致
+const CONTROL_DIR: &str = "DEBIAN";
+
+// deb包中的tar成员
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DebMember {
+    Control,
+    Data,
+}
+
+// Debian软件包只读支持: ar归档中的 control.tar.* 与 data.tar.*
+#[derive(Clone)]
+pub struct DebReader;
+
+impl DebReader {
+    pub fn new() -> Self {
+        DebReader
+    }
+
+    // 依次访问control和data成员，以对应的复合tar读取器处理
+    fn walk<F>(&self, archive_path: &str, mut visit: F) -> Result<(), String>
+    where
+        F: FnMut(DebMember, &TarCompressor, &mut dyn Read) -> Result<(), String>,
+    {
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        let mut is_deb = false;
+
+        ArReader::new().walk(file, |member, data| {
+            if member.name == "debian-binary" {
+                let mut version = String::new();
+                data.read_to_string(&mut version)
+                    .map_err(|e| e.to_string())?;
+                if !version.trim().starts_with("2.") {
+                    return Err(format!("不支持的DEB格式版本: {}", version.trim()));
+                }
+                is_deb = true;
+                return Ok(());
+            }
+
+            let kind = if member.name.starts_with("control.tar") {
+                DebMember::Control
+            } else if member.name.starts_with("data.tar") {
+                DebMember::Data
+            } else {
+                return Ok(());
+            };
+            // debian-binary必须位于control和data之前，否则会在解压出内容后才发现不是deb包
+            if !is_deb {
+                return Err("不是有效的DEB文件: 缺少debian-binary".to_string());
+            }
+
+            let codec = TarCodec::from_file_name(&member.name)
+                .ok_or_else(|| format!("不支持的DEB成员压缩格式: {}", member.name))?;
+            visit(kind, &TarCompressor::with_codec(codec), data)
+        })?;
+
+        if !is_deb {
+            return Err("不是有效的DEB文件: 缺少debian-binary".to_string());
+        }
+
+        Ok(())
+    }
+
+    // 将列表中的路径映射为control成员内的路径
+    fn control_selection(selection: &[String]) -> Option<Vec<String>> {
+        if selection.is_empty() {
+            return Some(Vec::new());
+        }
+
+        let mut control = Vec::new();
+        for selected in selection {
+            let selected = normalize_entry_path(selected);
+            if selected == CONTROL_DIR {
+                return Some(Vec::new());
+            }
+            if let Some(path) = selected.strip_prefix(&format!("{}/", CONTROL_DIR)) {
+                control.push(path.to_string());
+            }
+        }
+
+        if control.is_empty() {
+            None
+        } else {
+            Some(control)
+        }
+    }
+}
+
+impl ArchiveReader for DebReader {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        let mut entries = Vec::new();
+
+        self.walk(archive_path, |kind, tar, data| {
+            for mut entry in tar.list_stream(data)? {
+                if kind == DebMember::Control {
+                    entry.path = format!("{}/{}", CONTROL_DIR, entry.path);
+                }
+                entries.push(entry);
+            }
+            Ok(())
+        })?;
+
+        Ok(entries)
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        let output_dir = Path::new(output_path);
+        let control_dir = output_dir.join(CONTROL_DIR);
+        let control_selection = Self::control_selection(selection);
+        // 控制和数据两部分合计检查解压限制
+        let limiter = Limiter::new(archive_path, conflict.settings().limits);
+
+        self.walk(archive_path, |kind, tar, data| match kind {
+            DebMember::Control => match &control_selection {
+                Some(control_selection) => {
+                    fs::create_dir_all(&control_dir).map_err(|e| e.to_string())?;
+                    tar.extract_stream(
+                        data,
+                        &control_dir,
+                        control_selection,
+                        conflict,
+                        &limiter,
+                        None,
+                    )
+                }
+                None => Ok(()),
+            },
+            DebMember::Data => {
+                fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+                tar.extract_stream(data, output_dir, selection, conflict, &limiter, None)
+            }
+        })
+    }
+}
+
+impl ComdeAble for DebReader {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::read_only_archive()
+    }
+
+    async fn compress(
+        &self,
+        _input_paths: Vec<&str>,
+        _output_path: &str,
+        _options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        Err("DEB为只读格式，不支持压缩".to_string())
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        for input_path in input_paths {
+            self.extract(input_path, output_path, &[], &mut conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::{ar_archive, TempDir};
+
+    // 只含一个文件的未压缩tar
+    fn tar_with(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, name, data).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    fn write_deb(dir: &TempDir, members: &[(&str, &[u8])]) -> String {
+        let path = dir.join("package.deb");
+        fs::write(&path, ar_archive(members)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn lists_control_and_data() {
+        let dir = TempDir::new("deb-list");
+        let control = tar_with("./control", b"Package: test\n");
+        let data = tar_with("./usr/bin/tool", b"#!/bin/sh\n");
+        let deb = write_deb(
+            &dir,
+            &[
+                ("debian-binary", b"2.0\n"),
+                ("control.tar/", &control),
+                ("data.tar/", &data),
+            ],
+        );
+
+        let paths: Vec<String> = DebReader::new()
+            .list(&deb)
+            .unwrap()
+            .into_iter()
+            .map(|entry| normalize_entry_path(&entry.path))
+            .collect();
+        assert_eq!(paths, ["DEBIAN/control", "usr/bin/tool"]);
+
+        let output = dir.join("out");
+        DebReader::new()
+            .extract(
+                &deb,
+                output.to_str().unwrap(),
+                &[],
+                &mut ConflictResolver::new(ConflictPolicy::Overwrite),
+            )
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(output.join("DEBIAN/control")).unwrap(),
+            "Package: test\n"
+        );
+        assert!(output.join("usr/bin/tool").is_file());
+    }
+
+    // 缺少debian-binary时在写出任何内容之前报错
+    #[test]
+    fn rejects_missing_debian_binary() {
+        let dir = TempDir::new("deb-missing");
+        let data = tar_with("./usr/bin/tool", b"#!/bin/sh\n");
+        let deb = write_deb(&dir, &[("data.tar/", &data)]);
+        let output = dir.join("out");
+
+        let e = DebReader::new()
+            .extract(
+                &deb,
+                output.to_str().unwrap(),
+                &[],
+                &mut ConflictResolver::new(ConflictPolicy::Overwrite),
+            )
+            .unwrap_err();
+        assert_eq!(e, "不是有效的DEB文件: 缺少debian-binary");
+        assert!(!output.join("usr/bin/tool").exists());
+
+        let deb = write_deb(&dir, &[("README/", b"not a package")]);
+        assert_eq!(
+            DebReader::new().list(&deb).unwrap_err(),
+            "不是有效的DEB文件: 缺少debian-binary"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let dir = TempDir::new("deb-version");
+        let deb = write_deb(&dir, &[("debian-binary", b"3.0\n")]);
+        assert_eq!(
+            DebReader::new().list(&deb).unwrap_err(),
+            "不支持的DEB格式版本: 3.0"
+        );
+    }
+}
@@ -1,11 +1,26 @@
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use flate2::{Compression, GzBuilder};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::reproducible;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// GZIP头部中记录的元数据
+#[derive(Debug, Clone)]
+pub struct GzipHeaderInfo {
+    pub filename: Option<String>,
+    pub mtime: u32,
+    pub comment: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct GzipCompressor;
@@ -14,9 +29,60 @@ impl GzipCompressor {
     pub fn new() -> Self {
         GzipCompressor
     }
+
+    // 读取GZIP头部中的原始文件名、修改时间和注释
+    pub fn read_header(&self, input_path: &str) -> Result<GzipHeaderInfo, String> {
+        let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+        let mut decoder = GzDecoder::new(input_file);
+
+        // 头部在首次读取时才会被完整解析
+        if decoder.header().is_none() {
+            decoder.read(&mut [0u8; 1]).map_err(|e| e.to_string())?;
+        }
+
+        let header = decoder
+            .header()
+            .ok_or_else(|| format!("{} 不是有效的GZIP文件", input_path))?;
+
+        Ok(GzipHeaderInfo {
+            filename: header
+                .filename()
+                .map(|name| String::from_utf8_lossy(name).to_string()),
+            mtime: header.mtime(),
+            comment: header
+                .comment()
+                .map(|comment| String::from_utf8_lossy(comment).to_string()),
+        })
+    }
 }
 
 impl ComdeAble for GzipCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["deflate"],
+            compress_options: vec!["level", "comment", "reproducible"],
+            decompress_options: vec!["restore_name"],
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::Gzip {
+            level: preset.pick([0, 1, 3, 6, 8, 9]),
+            comment: None,
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -24,9 +90,14 @@ impl ComdeAble for GzipCompressor {
         options: Option<CompressionOptions>,
     ) -> Result<(), String> {
         // 处理压缩选项
-        let compression_level = match options {
-            Some(CompressionOptions::Gzip { level, .. }) => level,
-            _ => 6, // 默认压缩级别为6
+        let reproducible = reproducible::load(
+            options
+                .as_ref()
+                .is_some_and(|options| options.settings().reproducible),
+        )?;
+        let (compression_level, comment) = match options {
+            Some(CompressionOptions::Gzip { level, comment, .. }) => (level, comment),
+            _ => (6, None), // 默认压缩级别为6，无注释
         };
 
         // GZIP只能压缩单个文件，不能打包多个文件
@@ -49,7 +120,7 @@ impl ComdeAble for GzipCompressor {
             .map_err(|e| e.to_string())?;
 
         // 创建输出文件
-        let output_file = File::create(output_path).map_err(|e| e.to_string())?;
+        let output_file = AtomicFile::create(output_path)?;
 
         // 选择压缩级别 (0-9，9为最高压缩率)
         let compression_level = match compression_level {
@@ -59,23 +130,52 @@ impl ComdeAble for GzipCompressor {
             _ => Compression::best(),
         };
 
+        // 在头部记录原始文件名和修改时间
+        let file_name = path.file_name().ok_or("无效的文件名")?;
+        let metadata = input_file.metadata().map_err(|e| e.to_string())?;
+        let mtime = match reproducible {
+            // 可复现模式下修改时间不晚于SOURCE_DATE_EPOCH
+            Some(reproducible) => reproducible.mtime(&metadata) as u32,
+            None => metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as u32)
+                .unwrap_or(0),
+        };
+
+        let mut builder = GzBuilder::new()
+            .filename(file_name.to_string_lossy().as_bytes())
+            .mtime(mtime);
+        if let Some(comment) = comment {
+            builder = builder.comment(comment.as_bytes());
+        }
+
         // 压缩数据
-        let mut encoder = GzEncoder::new(output_file, compression_level);
+        let mut encoder = builder.write(output_file, compression_level);
         encoder.write_all(&buffer).map_err(|e| e.to_string())?;
-        encoder.finish().map_err(|e| e.to_string())?;
-
-        Ok(())
+        encoder.finish().map_err(|e| e.to_string())?.commit()
     }
 
     async fn decompress(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
-        _options: Option<DecompressionOptions>,
+        options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // 处理解压选项
+        let restore_name = match options {
+            Some(DecompressionOptions::Gzip { restore_name, .. }) => restore_name,
+            _ => false,
+        };
+
         // GZIP只能处理单个文件
         for input_path in input_paths {
             let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，此时还未创建输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
 
             // 获取输出文件名
             let output_file_path = if Path::new(output_path).is_dir() {
@@ -96,17 +196,78 @@ impl ComdeAble for GzipCompressor {
                 Path::new(output_path).to_path_buf()
             };
 
-            // 解压数据
-            let mut decoder = GzDecoder::new(input_file);
-            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
+            // 解压数据，拼接的多个GZIP成员会被依次解压
+            let mut decoder = MultiGzDecoder::new(input_file);
             let mut buffer = Vec::new();
 
-            decoder
+            limiter
+                .reader(&mut decoder)
                 .read_to_end(&mut buffer)
                 .map_err(|e| e.to_string())?;
+
+            // 使用头部中记录的文件名，只保留最后一级以防写出目标目录
+            let header = decoder.header();
+            let stored_name = header
+                .and_then(|header| header.filename())
+                .map(|name| String::from_utf8_lossy(name).to_string())
+                .and_then(|name| {
+                    Path::new(&name)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                });
+            // 头部中的名称可能在此系统上无效，按规则清理后写入重命名报告
+            let output_file_path = match (restore_name, stored_name) {
+                (true, Some(name)) if Path::new(output_path).is_dir() => conflict
+                    .entry_path(Path::new(output_path), &name)?
+                    .unwrap_or(output_file_path),
+                _ => output_file_path,
+            };
+
+            // 目标文件已存在时按冲突策略处理，头部未记录时间时使用压缩文件的修改时间
+            let mtime = header.map(|header| header.mtime()).unwrap_or(0);
+            let entry_mtime = match mtime {
+                0 => modified_secs(Path::new(input_path)),
+                mtime => Some(mtime as u64),
+            };
+            let Some(output_file_path) =
+                conflict.resolve(&output_file_path, Some(buffer.len() as u64), entry_mtime)?
+            else {
+                continue;
+            };
+            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
             output_file.write_all(&buffer).map_err(|e| e.to_string())?;
+
+            // 恢复头部中记录的修改时间 (0表示未记录)
+            if restore_name && mtime != 0 {
+                output_file
+                    .set_modified(UNIX_EPOCH + Duration::from_secs(mtime as u64))
+                    .map_err(|e| e.to_string())?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::check_concatenated;
+
+    // cat a.gz b.gz 得到的多个GZIP成员应全部解压
+    #[tokio::test]
+    async fn decompresses_concatenated_members() {
+        let encode = |part: &[u8]| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(part).unwrap();
+            encoder.finish().unwrap()
+        };
+        let options = DecompressionOptions::Gzip {
+            restore_name: false,
+            conflict: ConflictPolicy::Rename,
+            settings: Default::default(),
+        };
+        check_concatenated(&GzipCompressor::new(), "gz", encode, options).await;
+    }
+}
@@ -0,0 +1,763 @@
+use chrono::{FixedOffset, NaiveDate, TimeZone};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::conflict::ConflictResolver;
+use super::limits::Limiter;
+use super::{
+    is_selected, ArchiveEntry, ArchiveReader, Capabilities, ComdeAble, CompressionOptions,
+    DecompressionOptions,
+};
+
+// 逻辑扇区大小，卷描述符从第16个扇区开始
+const SECTOR_SIZE: u64 = 2048;
+const DESCRIPTOR_START: u64 = 16;
+
+// 卷描述符中的标准标识符
+const STANDARD_ID: &[u8; 5] = b"CD001";
+
+// 卷描述符类型
+const PRIMARY_DESCRIPTOR: u8 = 1;
+const SUPPLEMENTARY_DESCRIPTOR: u8 = 2;
+const TERMINATOR_DESCRIPTOR: u8 = 255;
+
+// 目录记录标志位
+const FLAG_DIRECTORY: u8 = 0x02;
+const FLAG_ASSOCIATED: u8 = 0x04;
+const FLAG_MULTI_EXTENT: u8 = 0x80;
+
+// 目录嵌套的安全上限，防止损坏的镜像造成无限递归
+const MAX_DEPTH: usize = 64;
+
+// 文件名的来源，按Rock Ridge、Joliet、ISO 9660的顺序优先
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Naming {
+    Iso9660,
+    Joliet,
+    RockRidge { skip: usize },
+}
+
+// 镜像中的一个条目
+struct IsoFile {
+    path: String,
+    extents: Vec<(u64, u64)>,
+    is_dir: bool,
+    mtime: Option<u64>,
+    mode: Option<u32>,
+    symlink: Option<String>,
+}
+
+impl IsoFile {
+    fn size(&self) -> u64 {
+        self.extents.iter().map(|(_, length)| length).sum()
+    }
+}
+
+// 解析后的目录记录
+struct DirectoryRecord {
+    extent: u64,
+    length: u64,
+    flags: u8,
+    name: String,
+    mtime: Option<u64>,
+    mode: Option<u32>,
+    symlink: Option<String>,
+    // Rock Ridge重定位: CL指向深层目录被移走后的真实位置
+    child_link: Option<u64>,
+}
+
+// Rock Ridge系统使用区中的信息
+#[derive(Default)]
+struct RockRidgeInfo {
+    name: Option<String>,
+    mode: Option<u32>,
+    symlink: Option<String>,
+    child_link: Option<u64>,
+    // RE标记被移走的目录，它会通过CL记录出现在原位置
+    relocated: bool,
+    has_sp: bool,
+}
+
+// ISO 9660光盘镜像只读支持，支持Joliet与Rock Ridge文件名
+#[derive(Clone)]
+pub struct IsoReader;
+
+impl IsoReader {
+    pub fn new() -> Self {
+        IsoReader
+    }
+
+    // 读取镜像的完整目录树
+    fn read_tree(&self, image: &mut File) -> Result<Vec<IsoFile>, String> {
+        let mut primary_root = None;
+        let mut joliet_root = None;
+
+        // 遍历卷描述符，找到主卷描述符与Joliet补充卷描述符
+        for index in DESCRIPTOR_START.. {
+            let sector = Self::read_at(image, index * SECTOR_SIZE, SECTOR_SIZE as usize)
+                .map_err(|_| "不是有效的ISO 9660镜像".to_string())?;
+            if &sector[1..6] != STANDARD_ID {
+                return Err("不是有效的ISO 9660镜像".to_string());
+            }
+
+            match sector[0] {
+                PRIMARY_DESCRIPTOR if primary_root.is_none() => {
+                    primary_root = Some(sector[156..190].to_vec());
+                }
+                SUPPLEMENTARY_DESCRIPTOR if joliet_root.is_none() => {
+                    // UCS-2转义序列 %/@ %/C %/E 标识Joliet
+                    let escape = &sector[88..91];
+                    if escape[0] == b'%' && escape[1] == b'/' && b"@CE".contains(&escape[2]) {
+                        joliet_root = Some(sector[156..190].to_vec());
+                    }
+                }
+                TERMINATOR_DESCRIPTOR => break,
+                _ => {}
+            }
+        }
+
+        let primary_root = primary_root.ok_or("ISO镜像缺少主卷描述符")?;
+        let root = Self::parse_record(&primary_root, Naming::Iso9660)?;
+
+        // 根目录第一条记录中的SP条目表示使用了Rock Ridge扩展
+        let root_data = Self::read_at(image, root.extent * SECTOR_SIZE, root.length as usize)
+            .map_err(|e| e.to_string())?;
+        let record_length = root_data.first().copied().unwrap_or(0) as usize;
+        let rock_ridge = root_data
+            .get(..record_length)
+            .and_then(|record| Self::system_use(record))
+            .map(|area| Self::parse_system_use(image, area, 0))
+            .transpose()?
+            .filter(|info| info.has_sp);
+
+        let (root, naming) = match (rock_ridge, joliet_root) {
+            (Some(_), _) => {
+                // SP条目的第7字节为每条记录系统使用区前需跳过的字节数
+                let skip = Self::system_use(&root_data[..record_length])
+                    .and_then(|area| area.get(6).copied())
+                    .unwrap_or(0) as usize;
+                (root, Naming::RockRidge { skip })
+            }
+            (None, Some(joliet_root)) => (
+                Self::parse_record(&joliet_root, Naming::Joliet)?,
+                Naming::Joliet,
+            ),
+            (None, None) => (root, Naming::Iso9660),
+        };
+
+        let mut files = Vec::new();
+        let mut visited = HashSet::new();
+        self.read_directory(image, &root, "", naming, 0, &mut visited, &mut files)?;
+        Ok(files)
+    }
+
+    // 递归读取目录中的条目
+    #[allow(clippy::too_many_arguments)]
+    fn read_directory(
+        &self,
+        image: &mut File,
+        directory: &DirectoryRecord,
+        prefix: &str,
+        naming: Naming,
+        depth: usize,
+        visited: &mut HashSet<u64>,
+        files: &mut Vec<IsoFile>,
+    ) -> Result<(), String> {
+        if depth > MAX_DEPTH || !visited.insert(directory.extent) {
+            return Err("ISO目录结构损坏: 目录层级过深或存在循环".to_string());
+        }
+
+        let data = Self::read_at(
+            image,
+            directory.extent * SECTOR_SIZE,
+            directory.length as usize,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut offset = 0;
+        // 上一条记录是否为多区段文件的非最后区段
+        let mut continues_previous = false;
+        while offset < data.len() {
+            let length = data[offset] as usize;
+            if length == 0 {
+                // 目录记录不会跨越扇区，长度为0表示跳到下一个扇区
+                offset = (offset / SECTOR_SIZE as usize + 1) * SECTOR_SIZE as usize;
+                continue;
+            }
+            let raw = data
+                .get(offset..offset + length)
+                .ok_or("ISO目录记录被截断")?;
+            offset += length;
+
+            // 跳过 "." 和 ".." 记录
+            let name_length = raw.get(32).copied().unwrap_or(0) as usize;
+            if name_length == 1 && raw.get(33).is_some_and(|&b| b <= 1) {
+                continue;
+            }
+
+            let mut record = Self::parse_record(raw, naming)?;
+            if record.flags & FLAG_ASSOCIATED != 0 {
+                continue;
+            }
+            if let Naming::RockRidge { skip } = naming {
+                if let Some(area) = Self::system_use(raw) {
+                    let info = Self::parse_system_use(image, area.get(skip..).unwrap_or(&[]), 0)?;
+                    // 被移走的目录通过CL记录在原位置读取，移动目标目录本身不列出
+                    if info.relocated
+                        || (prefix.is_empty() && info.name.as_deref() == Some("rr_moved"))
+                    {
+                        continue;
+                    }
+                    if let Some(name) = info.name {
+                        record.name = name;
+                    }
+                    record.mode = info.mode;
+                    record.symlink = info.symlink;
+                    record.child_link = info.child_link;
+                }
+            }
+
+            let path = if prefix.is_empty() {
+                record.name.clone()
+            } else {
+                format!("{}/{}", prefix, record.name)
+            };
+
+            // 多区段文件 (大于4GB) 的后续区段合并到同一个文件
+            if continues_previous {
+                if let Some(last) = files.last_mut() {
+                    last.extents.push((record.extent, record.length));
+                }
+                continues_previous = record.flags & FLAG_MULTI_EXTENT != 0;
+                continue;
+            }
+            continues_previous = record.flags & FLAG_MULTI_EXTENT != 0;
+
+            if let Some(child) = record.child_link {
+                // Rock Ridge重定位的深层目录，按CL记录的位置读取
+                let child_data = Self::read_at(image, child * SECTOR_SIZE, SECTOR_SIZE as usize)
+                    .map_err(|e| e.to_string())?;
+                let child_record = Self::parse_record(
+                    child_data
+                        .get(..child_data[0] as usize)
+                        .ok_or("ISO目录记录被截断")?,
+                    naming,
+                )?;
+                files.push(IsoFile {
+                    path: path.clone(),
+                    extents: Vec::new(),
+                    is_dir: true,
+                    mtime: record.mtime,
+                    mode: record.mode,
+                    symlink: None,
+                });
+                self.read_directory(
+                    image,
+                    &child_record,
+                    &path,
+                    naming,
+                    depth + 1,
+                    visited,
+                    files,
+                )?;
+            } else if record.flags & FLAG_DIRECTORY != 0 {
+                files.push(IsoFile {
+                    path: path.clone(),
+                    extents: Vec::new(),
+                    is_dir: true,
+                    mtime: record.mtime,
+                    mode: record.mode,
+                    symlink: None,
+                });
+                self.read_directory(image, &record, &path, naming, depth + 1, visited, files)?;
+            } else {
+                files.push(IsoFile {
+                    path,
+                    extents: vec![(record.extent, record.length)],
+                    is_dir: false,
+                    mtime: record.mtime,
+                    mode: record.mode,
+                    symlink: record.symlink,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // 解析目录记录的固定部分与文件名
+    fn parse_record(raw: &[u8], naming: Naming) -> Result<DirectoryRecord, String> {
+        if raw.len() < 34 {
+            return Err("ISO目录记录被截断".to_string());
+        }
+
+        let name_length = raw[32] as usize;
+        let name = raw.get(33..33 + name_length).ok_or("ISO目录记录被截断")?;
+        let name = match naming {
+            Naming::Joliet => {
+                let units: Vec<u16> = name
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }
+            _ => String::from_utf8_lossy(name).to_string(),
+        };
+
+        // 去掉版本号 (";1") 以及没有扩展名时留下的结尾 "."
+        let name = name.split(';').next().unwrap_or_default();
+        let name = if raw[25] & FLAG_DIRECTORY == 0 {
+            name.strip_suffix('.').unwrap_or(name)
+        } else {
+            name
+        };
+
+        Ok(DirectoryRecord {
+            extent: u32::from_le_bytes(raw[2..6].try_into().unwrap()) as u64,
+            length: u32::from_le_bytes(raw[10..14].try_into().unwrap()) as u64,
+            flags: raw[25],
+            name: name.to_string(),
+            mtime: Self::parse_date(&raw[18..25]),
+            mode: None,
+            symlink: None,
+            child_link: None,
+        })
+    }
+
+    // 目录记录中文件名之后的系统使用区
+    fn system_use(raw: &[u8]) -> Option<&[u8]> {
+        let name_length = *raw.get(32)? as usize;
+        // 文件名长度为偶数时有一个填充字节
+        let start = 33 + name_length + (1 - name_length % 2);
+        raw.get(start..).filter(|area| !area.is_empty())
+    }
+
+    // 解析SUSP条目，CE条目指向的延续区会被继续解析
+    fn parse_system_use(
+        image: &mut File,
+        area: &[u8],
+        depth: usize,
+    ) -> Result<RockRidgeInfo, String> {
+        let mut info = RockRidgeInfo::default();
+        let mut name = String::new();
+        let mut symlink: Vec<String> = Vec::new();
+        let mut continuation = None;
+
+        let mut offset = 0;
+        while offset + 4 <= area.len() {
+            let signature = &area[offset..offset + 2];
+            let length = area[offset + 2] as usize;
+            if length < 4 || offset + length > area.len() {
+                break;
+            }
+            let data = &area[offset + 4..offset + length];
+            offset += length;
+
+            match signature {
+                b"SP" => info.has_sp = true,
+                // 标志位1和2分别表示 "." 与 ".."，这类名称不使用
+                b"NM" if !data.is_empty() && data[0] & 0x06 == 0 => {
+                    name.push_str(&String::from_utf8_lossy(&data[1..]));
+                }
+                b"PX" if data.len() >= 4 => {
+                    info.mode = Some(u32::from_le_bytes(data[0..4].try_into().unwrap()));
+                }
+                b"SL" if !data.is_empty() => {
+                    let mut components = &data[1..];
+                    while components.len() >= 2 {
+                        let flags = components[0];
+                        let length = components[1] as usize;
+                        let content = components.get(2..2 + length).unwrap_or(&[]);
+                        let component = if flags & 0x02 != 0 {
+                            ".".to_string()
+                        } else if flags & 0x04 != 0 {
+                            "..".to_string()
+                        } else if flags & 0x08 != 0 {
+                            String::new()
+                        } else {
+                            String::from_utf8_lossy(content).to_string()
+                        };
+                        symlink.push(component);
+                        components = components.get(2 + length..).unwrap_or(&[]);
+                    }
+                }
+                b"CL" if data.len() >= 4 => {
+                    info.child_link =
+                        Some(u32::from_le_bytes(data[0..4].try_into().unwrap()) as u64);
+                }
+                b"RE" => info.relocated = true,
+                b"CE" if data.len() >= 24 => {
+                    let block = u32::from_le_bytes(data[0..4].try_into().unwrap()) as u64;
+                    let offset = u32::from_le_bytes(data[8..12].try_into().unwrap()) as u64;
+                    let length = u32::from_le_bytes(data[16..20].try_into().unwrap()) as usize;
+                    continuation = Some((block * SECTOR_SIZE + offset, length));
+                }
+                b"ST" => break,
+                _ => {}
+            }
+        }
+
+        if let Some((position, length)) = continuation {
+            if depth < MAX_DEPTH {
+                let area = Self::read_at(image, position, length).map_err(|e| e.to_string())?;
+                let more = Self::parse_system_use(image, &area, depth + 1)?;
+                name.push_str(more.name.as_deref().unwrap_or_default());
+                if let Some(target) = more.symlink {
+                    symlink.push(target);
+                }
+                info.mode = info.mode.or(more.mode);
+                info.child_link = info.child_link.or(more.child_link);
+                info.relocated |= more.relocated;
+            }
+        }
+
+        if !name.is_empty() {
+            info.name = Some(name);
+        }
+        if !symlink.is_empty() {
+            let target = symlink.join("/");
+            info.symlink = Some(if target.is_empty() {
+                "/".to_string()
+            } else {
+                target
+            });
+        }
+        Ok(info)
+    }
+
+    // 解析7字节的目录记录时间
+    fn parse_date(raw: &[u8]) -> Option<u64> {
+        let date = NaiveDate::from_ymd_opt(1900 + raw[0] as i32, raw[1] as u32, raw[2] as u32)?
+            .and_hms_opt(raw[3] as u32, raw[4] as u32, raw[5] as u32)?;
+        // 时区以15分钟为单位
+        let offset = FixedOffset::east_opt(raw[6] as i8 as i32 * 15 * 60)?;
+        let timestamp = offset.from_local_datetime(&date).single()?.timestamp();
+        u64::try_from(timestamp).ok()
+    }
+
+    // 读取镜像中的一段数据；位置和长度来自镜像本身，超出文件末尾时不分配缓冲区
+    fn read_at(image: &mut File, position: u64, length: usize) -> io::Result<Vec<u8>> {
+        if position.saturating_add(length as u64) > image.metadata()?.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "ISO镜像被截断",
+            ));
+        }
+        image.seek(SeekFrom::Start(position))?;
+        let mut buffer = vec![0u8; length];
+        image.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    // 写出单个条目
+    fn extract_file(
+        &self,
+        image: &mut File,
+        file: &IsoFile,
+        target: &Path,
+        conflict: &mut ConflictResolver,
+        limiter: &Limiter,
+    ) -> Result<(), String> {
+        if file.is_dir {
+            return fs::create_dir_all(target).map_err(|e| e.to_string());
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        if let Some(link_target) = &file.symlink {
+            #[cfg(unix)]
+            {
+                // 与普通文件一样按冲突策略处理，覆盖时resolve会删除已有的文件或链接
+                let size = Some(link_target.len() as u64);
+                let Some(target) = conflict.resolve(target, size, file.mtime)? else {
+                    return Ok(());
+                };
+                std::os::unix::fs::symlink(link_target, target).map_err(|e| e.to_string())?;
+            }
+            #[cfg(not(unix))]
+            let _ = link_target;
+            return Ok(());
+        }
+
+        let Some(target) = conflict.resolve(target, Some(file.size()), file.mtime)? else {
+            return Ok(());
+        };
+        let target = target.as_path();
+        limiter.track(target);
+        let mut output_file = File::create(target).map_err(|e| e.to_string())?;
+        // 多个区段属于同一条目，共用一个读取器累计条目大小
+        let mut reader = limiter.reader(&mut *image);
+        for &(extent, length) in &file.extents {
+            reader
+                .get_mut()
+                .seek(SeekFrom::Start(extent * SECTOR_SIZE))
+                .map_err(|e| e.to_string())?;
+            let copied = io::copy(&mut (&mut reader).take(length), &mut output_file)
+                .map_err(|e| e.to_string())?;
+            if copied != length {
+                return Err(format!("ISO镜像被截断: {}", file.path));
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(mode) = file.mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o7777))
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ArchiveReader for IsoReader {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        let mut image = File::open(archive_path).map_err(|e| e.to_string())?;
+
+        Ok(self
+            .read_tree(&mut image)?
+            .into_iter()
+            .map(|file| ArchiveEntry {
+                size: file.size(),
+                path: file.path,
+                is_dir: file.is_dir,
+                mtime: file.mtime,
+            })
+            .collect())
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+    ) -> Result<(), String> {
+        let output_dir = Path::new(output_path);
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        let limiter = Limiter::new(archive_path, conflict.settings().limits);
+        let mut image = File::open(archive_path).map_err(|e| e.to_string())?;
+        for file in self.read_tree(&mut image)? {
+            limiter.begin_entry(&file.path, Some(file.size()))?;
+            if !is_selected(&file.path, selection) {
+                continue;
+            }
+
+            let target = if file.is_dir {
+                conflict.dir_path(output_dir, &file.path)?
+            } else {
+                conflict.entry_path(output_dir, &file.path)?
+            }
+            .ok_or_else(|| format!("不安全的条目路径: {}", file.path))?;
+            self.extract_file(&mut image, &file, &target, conflict, &limiter)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ComdeAble for IsoReader {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::read_only_archive()
+    }
+
+    async fn compress(
+        &self,
+        _input_paths: Vec<&str>,
+        _output_path: &str,
+        _options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        Err("ISO为只读格式，不支持压缩".to_string())
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        for input_path in input_paths {
+            self.extract(input_path, output_path, &[], &mut conflict)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::TempDir;
+
+    const ROOT: u32 = 18;
+    const SUB: u32 = 19;
+    const DATA: u32 = 20;
+
+    // 目录记录: 区段位置和长度按双字节序写入，时间为2024-01-02 03:04:05 UTC
+    fn record(extent: u32, length: u32, flags: u8, name: &[u8]) -> Vec<u8> {
+        let padding = 1 - name.len() % 2;
+        let mut raw = vec![(33 + name.len() + padding) as u8, 0];
+        raw.extend_from_slice(&extent.to_le_bytes());
+        raw.extend_from_slice(&extent.to_be_bytes());
+        raw.extend_from_slice(&length.to_le_bytes());
+        raw.extend_from_slice(&length.to_be_bytes());
+        raw.extend_from_slice(&[124, 1, 2, 3, 4, 5, 0, flags, 0, 0, 1, 0, 0, 1]);
+        raw.push(name.len() as u8);
+        raw.extend_from_slice(name);
+        raw.resize(raw.len() + padding, 0);
+        raw
+    }
+
+    // 目录内容: "." 和 ".." 之后是给定的记录
+    fn directory(extent: u32, parent: u32, records: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = record(extent, SECTOR_SIZE as u32, FLAG_DIRECTORY, &[0]);
+        data.extend(record(parent, SECTOR_SIZE as u32, FLAG_DIRECTORY, &[1]));
+        for raw in records {
+            data.extend_from_slice(raw);
+        }
+        data
+    }
+
+    // 最小的ISO 9660镜像: 主卷描述符、结束描述符，根目录和子目录，以及若干数据扇区
+    fn image(root_length: u32, root: &[Vec<u8>], sub: &[Vec<u8>], data: &[&[u8]]) -> Vec<u8> {
+        let sector = SECTOR_SIZE as usize;
+        let mut image = vec![0u8; (DATA as usize + data.len()) * sector];
+        let mut descriptor = |index: usize, kind: u8| {
+            let start = index * sector;
+            image[start] = kind;
+            image[start + 1..start + 6].copy_from_slice(STANDARD_ID);
+            image[start + 6] = 1;
+            start
+        };
+        let primary = descriptor(16, PRIMARY_DESCRIPTOR);
+        descriptor(17, TERMINATOR_DESCRIPTOR);
+        let root_record = record(ROOT, root_length, FLAG_DIRECTORY, &[0]);
+        image[primary + 156..primary + 190].copy_from_slice(&root_record);
+
+        for (extent, content) in [
+            (ROOT, directory(ROOT, ROOT, root)),
+            (SUB, directory(SUB, ROOT, sub)),
+        ] {
+            let start = extent as usize * sector;
+            image[start..start + content.len()].copy_from_slice(&content);
+        }
+        for (index, content) in data.iter().enumerate() {
+            let start = (DATA as usize + index) * sector;
+            image[start..start + content.len()].copy_from_slice(content);
+        }
+        image
+    }
+
+    fn write_image(dir: &TempDir, image: &[u8]) -> String {
+        let path = dir.join("image.iso");
+        fs::write(&path, image).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn reads_minimal_image() {
+        let dir = TempDir::new("iso-minimal");
+        let sector = SECTOR_SIZE as u32;
+        // BIG.BIN由两个区段组成，第一个区段带多区段标志
+        let data: [&[u8]; 4] = [b"hello", b"inner", &[b'a'; 2048], b"bc"];
+        let root = [
+            record(DATA, 5, 0, b"HELLO.TXT;1"),
+            record(DATA + 2, sector, FLAG_MULTI_EXTENT, b"BIG.BIN;1"),
+            record(DATA + 3, 2, 0, b"BIG.BIN;1"),
+            record(SUB, sector, FLAG_DIRECTORY, b"SUB"),
+            record(DATA, 5, FLAG_ASSOCIATED, b"HELLO.TXT;1"),
+        ];
+        let sub = [record(DATA + 1, 5, 0, b"INNER.;1")];
+        let archive = write_image(&dir, &image(sector, &root, &sub, &data));
+
+        let entries: Vec<(String, u64, bool)> = IsoReader::new()
+            .list(&archive)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.path, entry.size, entry.is_dir))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("HELLO.TXT".to_string(), 5, false),
+                ("BIG.BIN".to_string(), 2050, false),
+                ("SUB".to_string(), 0, true),
+                ("SUB/INNER".to_string(), 5, false),
+            ]
+        );
+        let mtime = IsoReader::new().list(&archive).unwrap()[0].mtime;
+        assert_eq!(mtime, Some(1_704_164_645));
+
+        let output = dir.join("out");
+        IsoReader::new()
+            .extract(
+                &archive,
+                output.to_str().unwrap(),
+                &[],
+                &mut ConflictResolver::new(ConflictPolicy::Overwrite),
+            )
+            .unwrap();
+        assert_eq!(fs::read(output.join("HELLO.TXT")).unwrap(), b"hello");
+        assert_eq!(fs::read(output.join("SUB/INNER")).unwrap(), b"inner");
+        let big = fs::read(output.join("BIG.BIN")).unwrap();
+        assert_eq!(big.len(), 2050);
+        assert!(big.ends_with(b"abc"));
+    }
+
+    // 记录长度超出目录数据时报告截断
+    #[test]
+    fn rejects_truncated_record() {
+        let dir = TempDir::new("iso-truncated");
+        let mut broken = record(DATA, 5, 0, b"HELLO.TXT;1");
+        broken[0] = 80;
+        let root_length = (34 * 2 + broken.len()) as u32;
+        let archive = write_image(&dir, &image(root_length, &[broken], &[], &[b"hello"]));
+        assert_eq!(
+            IsoReader::new().list(&archive).unwrap_err(),
+            "ISO目录记录被截断"
+        );
+    }
+
+    // 子目录指回根目录时报告循环，而不是无限递归
+    #[test]
+    fn rejects_directory_loop() {
+        let dir = TempDir::new("iso-loop");
+        let sector = SECTOR_SIZE as u32;
+        let root = [record(SUB, sector, FLAG_DIRECTORY, b"SUB")];
+        let sub = [record(ROOT, sector, FLAG_DIRECTORY, b"BACK")];
+        let archive = write_image(&dir, &image(sector, &root, &sub, &[]));
+        assert!(IsoReader::new()
+            .list(&archive)
+            .unwrap_err()
+            .contains("存在循环"));
+    }
+
+    // 区段超出镜像末尾时报错，不按头部中的长度分配内存
+    #[test]
+    fn rejects_extent_past_end() {
+        let dir = TempDir::new("iso-extent");
+        let sector = SECTOR_SIZE as u32;
+        let root = [record(SUB, u32::MAX, FLAG_DIRECTORY, b"SUB")];
+        let archive = write_image(&dir, &image(sector, &root, &[], &[]));
+        assert!(IsoReader::new().list(&archive).is_err());
+
+        let root = [record(DATA + 5, 5, 0, b"GONE.TXT;1")];
+        let archive = write_image(&dir, &image(sector, &root, &[], &[]));
+        let e = IsoReader::new()
+            .extract(
+                &archive,
+                dir.join("out").to_str().unwrap(),
+                &[],
+                &mut ConflictResolver::new(ConflictPolicy::Overwrite),
+            )
+            .unwrap_err();
+        assert!(e.contains("ISO镜像被截断"), "{}", e);
+    }
+}
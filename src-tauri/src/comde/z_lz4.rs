@@ -0,0 +1,185 @@
+use lz4::{ContentChecksum, Decoder, Encoder, EncoderBuilder};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// LZ4高压缩(HC)模式的最高级别
+const MAX_LEVEL: u32 = 12;
+
+#[derive(Clone)]
+pub struct Lz4Compressor;
+
+impl Lz4Compressor {
+    pub fn new() -> Self {
+        Lz4Compressor
+    }
+
+    // 创建LZ4帧编码器 (级别1-2为快速模式，3-12为高压缩模式)
+    pub fn encoder<W: Write>(
+        &self,
+        writer: W,
+        level: u32,
+        checksum: bool,
+    ) -> Result<Encoder<W>, String> {
+        let checksum = if checksum {
+            ContentChecksum::ChecksumEnabled
+        } else {
+            ContentChecksum::NoChecksum
+        };
+
+        EncoderBuilder::new()
+            .level(level.min(MAX_LEVEL))
+            .checksum(checksum)
+            .build(writer)
+            .map_err(|e| e.to_string())
+    }
+
+    // 写入帧结束标记并取回底层写入器
+    pub fn finish<W: Write>(&self, encoder: Encoder<W>) -> Result<W, String> {
+        let (writer, result) = encoder.finish();
+        result.map_err(|e| e.to_string())?;
+        Ok(writer)
+    }
+
+    // 创建LZ4帧解码器
+    pub fn decoder<R: Read>(&self, reader: R) -> Result<Decoder<R>, String> {
+        Decoder::new(reader).map_err(|e| e.to_string())
+    }
+}
+
+impl ComdeAble for Lz4Compressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 1,
+                max: MAX_LEVEL as i32,
+                default: 1,
+            }),
+            methods: vec!["lz4"],
+            compress_options: vec!["level", "checksum"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        // 级别1-2为快速模式，高压缩预设切换到HC模式
+        Some(CompressionOptions::Lz4 {
+            level: preset.pick([1, 1, 1, 1, 9, MAX_LEVEL]),
+            checksum: false,
+            settings: Default::default(),
+        })
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        // 处理压缩选项
+        let (compression_level, checksum) = match options {
+            Some(CompressionOptions::Lz4 {
+                level, checksum, ..
+            }) => (level, checksum),
+            _ => (1, false), // 默认使用快速模式，不写入内容校验和
+        };
+
+        // LZ4只能压缩单个文件，不能打包多个文件
+        if input_paths.len() > 1 {
+            return Err("LZ4只能压缩单个文件，无法打包多个文件。请使用TAR+LZ4组合".to_string());
+        }
+
+        let input_path = input_paths[0];
+        let path = Path::new(input_path);
+
+        if path.is_dir() {
+            return Err("LZ4不能直接压缩目录，请先使用TAR打包后再用LZ4压缩".to_string());
+        }
+
+        // 打开输入文件
+        let mut input_file = File::open(path).map_err(|e| e.to_string())?;
+        let mut buffer = Vec::new();
+        input_file
+            .read_to_end(&mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        // 创建输出文件
+        let output_file = AtomicFile::create(output_path)?;
+
+        // 压缩数据
+        let mut encoder = self.encoder(output_file, compression_level, checksum)?;
+        encoder.write_all(&buffer).map_err(|e| e.to_string())?;
+        self.finish(encoder)?.commit()
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // LZ4只能处理单个文件
+        for input_path in input_paths {
+            let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，并删除已创建的输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+
+            // 获取输出文件名
+            let output_file_path = if Path::new(output_path).is_dir() {
+                let input_filename = Path::new(input_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or("无法获取输入文件名")?;
+
+                // 移除.lz4扩展名
+                let original_name = if input_filename.ends_with(".lz4") {
+                    &input_filename[0..input_filename.len() - 4]
+                } else {
+                    input_filename
+                };
+
+                Path::new(output_path).join(original_name)
+            } else {
+                Path::new(output_path).to_path_buf()
+            };
+
+            // 解压数据
+            let mut decoder = self.decoder(input_file)?;
+            // 目标文件已存在时按冲突策略处理，流格式以压缩文件的修改时间作为条目时间
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                None,
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            limiter.track(&output_file_path);
+            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
+            let mut buffer = Vec::new();
+
+            limiter
+                .reader(&mut decoder)
+                .read_to_end(&mut buffer)
+                .map_err(|e| e.to_string())?;
+            output_file.write_all(&buffer).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
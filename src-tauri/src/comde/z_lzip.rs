@@ -0,0 +1,590 @@
+use flate2::Crc;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use xz2::stream::{Action, LzmaOptions, Status, Stream};
+use xz2::write::XzEncoder;
+
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// lzip成员头部: "LZIP" + 版本号 + 编码后的字典大小
+const LZIP_MAGIC: [u8; 4] = *b"LZIP";
+const LZIP_VERSION: u8 = 1;
+const HEADER_SIZE: usize = 6;
+
+// lzip成员尾部: CRC32 + 原始数据大小 + 成员大小
+const TRAILER_SIZE: usize = 20;
+
+// .lzma头部大小: 属性字节 + 字典大小 + 未压缩大小
+const LZMA_ALONE_HEADER_SIZE: usize = 13;
+
+// lzip固定使用 lc=3, lp=0, pb=2
+const LZMA_PROPERTIES: u8 = 0x5D;
+
+// lzip允许的字典大小范围 (4KiB - 512MiB)
+const MIN_DICT_LOG: u32 = 12;
+const MAX_DICT_LOG: u32 = 29;
+
+// lzip容器格式，LZMA数据流由liblzma的.lzma编解码器处理
+#[derive(Clone)]
+pub struct LzipCompressor;
+
+impl LzipCompressor {
+    pub fn new() -> Self {
+        LzipCompressor
+    }
+
+    // 创建写出单个lzip成员的编码器 (级别0-9)；已知数据大小时据此缩小字典
+    pub fn encoder<W: Write>(
+        &self,
+        mut writer: W,
+        level: u32,
+        size_hint: Option<u64>,
+    ) -> Result<LzipEncoder<W>, String> {
+        // 字典大小取2的幂，才能在头部中精确表示
+        let mut options = LzmaOptions::new_preset(level.min(9)).map_err(|e| e.to_string())?;
+        let dict_log = match size_hint {
+            Some(size) => size
+                .max(1)
+                .next_power_of_two()
+                .trailing_zeros()
+                .clamp(MIN_DICT_LOG, Self::preset_dict_log(level)),
+            None => Self::preset_dict_log(level),
+        };
+        options
+            .dict_size(1 << dict_log)
+            .literal_context_bits(3)
+            .literal_position_bits(0)
+            .position_bits(2);
+
+        let mut header = [0u8; HEADER_SIZE];
+        header[..4].copy_from_slice(&LZIP_MAGIC);
+        header[4] = LZIP_VERSION;
+        header[5] = dict_log as u8;
+        writer.write_all(&header).map_err(|e| e.to_string())?;
+
+        // .lzma编码器会写入结束标记，去掉其头部后即为lzip需要的LZMA数据流
+        let stream = Stream::new_lzma_encoder(&options).map_err(|e| e.to_string())?;
+        Ok(LzipEncoder {
+            inner: XzEncoder::new_stream(
+                MemberWriter {
+                    writer,
+                    skip: LZMA_ALONE_HEADER_SIZE,
+                    written: 0,
+                },
+                stream,
+            ),
+            crc: Crc::new(),
+            size: 0,
+        })
+    }
+
+    // 创建lzip解码器，依次解码所有成员并校验尾部
+    pub fn decoder<R: Read>(&self, reader: R) -> LzipDecoder<R> {
+        LzipDecoder {
+            reader: BufReader::new(reader),
+            member: None,
+            members: 0,
+            done: false,
+        }
+    }
+
+    // 解析头部中编码的字典大小
+    fn decode_dict_size(coded: u8) -> Result<u32, String> {
+        let dict_log = (coded & 0x1F) as u32;
+        if !(MIN_DICT_LOG..=MAX_DICT_LOG).contains(&dict_log) {
+            return Err(format!("无效的LZIP字典大小: {:#x}", coded));
+        }
+
+        let base = 1u32 << dict_log;
+        Ok(base - (base / 16) * ((coded >> 5) & 0x07) as u32)
+    }
+
+    // 各预设级别对应的字典大小 (与xz预设一致)
+    fn preset_dict_log(level: u32) -> u32 {
+        match level.min(9) {
+            0 => 18,
+            1 => 20,
+            2 => 21,
+            3..=5 => 22,
+            6 => 23,
+            7 => 24,
+            8 => 25,
+            _ => 26,
+        }
+    }
+}
+
+// 去掉.lzma编码器输出的头部并统计LZMA数据流大小
+struct MemberWriter<W: Write> {
+    writer: W,
+    // 尚未跳过的.lzma头部字节数
+    skip: usize,
+    written: u64,
+}
+
+impl<W: Write> Write for MemberWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let skipped = self.skip.min(buf.len());
+        self.skip -= skipped;
+        if skipped == buf.len() {
+            return Ok(skipped);
+        }
+
+        let written = self.writer.write(&buf[skipped..])?;
+        self.written += written as u64;
+        Ok(skipped + written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// 流式写出单个lzip成员，边压缩边累计CRC和原始大小，finish时写入尾部
+pub struct LzipEncoder<W: Write> {
+    inner: XzEncoder<MemberWriter<W>>,
+    crc: Crc,
+    size: u64,
+}
+
+impl<W: Write> LzipEncoder<W> {
+    // 写入结束标记和成员尾部并取回底层写入器
+    pub fn finish(self) -> Result<W, String> {
+        let MemberWriter {
+            mut writer,
+            written,
+            ..
+        } = self.inner.finish().map_err(|e| e.to_string())?;
+
+        let member_size = (HEADER_SIZE + TRAILER_SIZE) as u64 + written;
+        let mut trailer = [0u8; TRAILER_SIZE];
+        trailer[0..4].copy_from_slice(&self.crc.sum().to_le_bytes());
+        trailer[4..12].copy_from_slice(&self.size.to_le_bytes());
+        trailer[12..20].copy_from_slice(&member_size.to_le_bytes());
+        writer.write_all(&trailer).map_err(|e| e.to_string())?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for LzipEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// 正在解码的成员
+struct Member {
+    stream: Stream,
+    crc: Crc,
+    size: u64,
+}
+
+// 流式解码lzip数据，每个成员结束时校验尾部记录的CRC和大小
+pub struct LzipDecoder<R: Read> {
+    reader: BufReader<R>,
+    member: Option<Member>,
+    // 已完成的成员数
+    members: u64,
+    done: bool,
+}
+
+impl<R: Read> LzipDecoder<R> {
+    // 读取下一个成员的头部，没有更多成员时返回None
+    fn next_member(&mut self) -> io::Result<Option<Member>> {
+        let mut header = [0u8; HEADER_SIZE];
+        let read = read_full(&mut self.reader, &mut header)?;
+        if read < HEADER_SIZE || header[..4] != LZIP_MAGIC {
+            // 第一个成员之后的尾随数据会被忽略
+            if self.members > 0 {
+                return Ok(None);
+            }
+            return Err(invalid_data("不是有效的LZIP文件".to_string()));
+        }
+        if header[4] != LZIP_VERSION {
+            return Err(invalid_data(format!("不支持的LZIP版本: {}", header[4])));
+        }
+        let dict_size = LzipCompressor::decode_dict_size(header[5]).map_err(invalid_data)?;
+
+        // 构造未知大小的.lzma头部，使解码器在结束标记处停止
+        let mut lzma_header = [0xFFu8; LZMA_ALONE_HEADER_SIZE];
+        lzma_header[0] = LZMA_PROPERTIES;
+        lzma_header[1..5].copy_from_slice(&dict_size.to_le_bytes());
+
+        // 输出缓冲区没有剩余空间时解码器不会读取任何输入，包括头部
+        let mut stream = Stream::new_lzma_decoder(u64::MAX).map_err(io::Error::other)?;
+        stream
+            .process(&lzma_header, &mut [0u8; 1], Action::Run)
+            .map_err(io::Error::other)?;
+        if stream.total_in() != LZMA_ALONE_HEADER_SIZE as u64 {
+            return Err(io::Error::other("无法初始化LZMA解码器"));
+        }
+
+        Ok(Some(Member {
+            stream,
+            crc: Crc::new(),
+            size: 0,
+        }))
+    }
+
+    // 读取并校验成员尾部
+    fn finish_member(&mut self, member: Member) -> io::Result<()> {
+        let mut trailer = [0u8; TRAILER_SIZE];
+        if read_full(&mut self.reader, &mut trailer)? < TRAILER_SIZE {
+            return Err(invalid_data("LZIP文件被截断".to_string()));
+        }
+        let stored_crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+        let stored_size = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
+        let member_size = u64::from_le_bytes(trailer[12..20].try_into().unwrap());
+
+        if member.crc.sum() != stored_crc {
+            return Err(invalid_data("LZIP数据CRC校验失败".to_string()));
+        }
+        let stream_size = member.stream.total_in() - LZMA_ALONE_HEADER_SIZE as u64;
+        if member.size != stored_size
+            || (HEADER_SIZE + TRAILER_SIZE) as u64 + stream_size != member_size
+        {
+            return Err(invalid_data("LZIP成员大小与尾部记录不一致".to_string()));
+        }
+
+        self.members += 1;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for LzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.done {
+                return Ok(0);
+            }
+            let mut member = match self.member.take() {
+                Some(member) => member,
+                None => match self.next_member()? {
+                    Some(member) => member,
+                    None => {
+                        self.done = true;
+                        continue;
+                    }
+                },
+            };
+
+            let input = self.reader.fill_buf()?;
+            let input_empty = input.is_empty();
+            let (total_in, total_out) = (member.stream.total_in(), member.stream.total_out());
+            let status = member
+                .stream
+                .process(input, buf, Action::Run)
+                .map_err(|e| invalid_data(format!("LZIP数据损坏: {}", e)))?;
+            let consumed = (member.stream.total_in() - total_in) as usize;
+            let produced = (member.stream.total_out() - total_out) as usize;
+            self.reader.consume(consumed);
+            member.crc.update(&buf[..produced]);
+            member.size += produced as u64;
+
+            if let Status::StreamEnd = status {
+                self.finish_member(member)?;
+            } else if consumed == 0 && produced == 0 && input_empty {
+                // 输入已耗尽却仍未遇到结束标记
+                return Err(invalid_data("LZIP文件被截断".to_string()));
+            } else {
+                self.member = Some(member);
+            }
+
+            if produced > 0 {
+                return Ok(produced);
+            }
+        }
+    }
+}
+
+// 尽量读满缓冲区，返回实际读取的字节数，不足说明已到文件末尾
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl ComdeAble for LzipCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["lzma"],
+            compress_options: vec!["level"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::Lzip {
+            level: preset.pick([0, 0, 2, 6, 8, 9]),
+            settings: Default::default(),
+        })
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        // 处理压缩选项
+        let compression_level = match options {
+            Some(CompressionOptions::Lzip { level, .. }) => level,
+            _ => 6, // 默认压缩级别为6
+        };
+
+        // LZIP只能压缩单个文件，不能打包多个文件
+        if input_paths.len() > 1 {
+            return Err("LZIP只能压缩单个文件，无法打包多个文件。请使用TAR+LZIP组合".to_string());
+        }
+
+        let input_path = input_paths[0];
+        let path = Path::new(input_path);
+
+        if path.is_dir() {
+            return Err("LZIP不能直接压缩目录，请先使用TAR打包后再用LZIP压缩".to_string());
+        }
+
+        // 打开输入文件
+        let mut input_file = File::open(path).map_err(|e| e.to_string())?;
+        let size = input_file.metadata().map_err(|e| e.to_string())?.len();
+
+        // 创建输出文件
+        let output_file = AtomicFile::create(output_path)?;
+
+        // 压缩数据
+        let mut encoder = self.encoder(output_file, compression_level, Some(size))?;
+        std::io::copy(&mut input_file, &mut encoder).map_err(|e| e.to_string())?;
+        encoder.finish()?.commit()
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // LZIP只能处理单个文件
+        for input_path in input_paths {
+            let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，并删除已创建的输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+
+            // 获取输出文件名
+            let output_file_path = if Path::new(output_path).is_dir() {
+                let input_filename = Path::new(input_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or("无法获取输入文件名")?;
+
+                // 移除.lz扩展名
+                let original_name = if input_filename.ends_with(".lz") {
+                    &input_filename[0..input_filename.len() - 3]
+                } else {
+                    input_filename
+                };
+
+                Path::new(output_path).join(original_name)
+            } else {
+                Path::new(output_path).to_path_buf()
+            };
+
+            // 解压数据
+            let mut decoder = self.decoder(input_file);
+            // 目标文件已存在时按冲突策略处理，流格式以压缩文件的修改时间作为条目时间
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                None,
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            limiter.track(&output_file_path);
+            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
+            std::io::copy(&mut limiter.reader(&mut decoder), &mut output_file)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每次最多接受3个字节的写入器，使.lzma头部跨越多次写入
+    struct Trickle(Vec<u8>);
+
+    impl Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect()
+    }
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let mut encoder = LzipCompressor::new()
+            .encoder(Vec::new(), 6, Some(data.len() as u64))
+            .unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn encode_level(data: &[u8], level: u32) -> Vec<u8> {
+        let mut encoder = LzipCompressor::new()
+            .encoder(Vec::new(), level, None)
+            .unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut decoded = Vec::new();
+        LzipCompressor::new()
+            .decoder(data)
+            .read_to_end(&mut decoded)
+            .map_err(|e| e.to_string())?;
+        Ok(decoded)
+    }
+
+    // 编码结果为单个完整成员: 头部之后直接是LZMA数据 (首字节为0)，尾部记录的大小与实际一致
+    #[test]
+    fn round_trips_members() {
+        for len in [0, 1, 100_000] {
+            let data = sample(len);
+            let encoded = encode(&data);
+            assert_eq!(encoded[..5], *b"LZIP\x01");
+            assert_eq!(
+                encoded[5],
+                MIN_DICT_LOG.max(len.next_power_of_two().trailing_zeros()) as u8
+            );
+            assert_eq!(encoded[HEADER_SIZE], 0);
+            let trailer = &encoded[encoded.len() - TRAILER_SIZE..];
+            assert_eq!(trailer[4..12], (len as u64).to_le_bytes());
+            assert_eq!(trailer[12..20], (encoded.len() as u64).to_le_bytes());
+            assert_eq!(decode(&encoded).unwrap(), data);
+        }
+    }
+
+    // 底层写入器只接受部分数据时同样正确跳过.lzma头部
+    #[test]
+    fn strips_header_across_short_writes() {
+        let data = sample(10_000);
+        let mut encoder = LzipCompressor::new()
+            .encoder(Trickle(Vec::new()), 0, None)
+            .unwrap();
+        encoder.write_all(&data).unwrap();
+        let Trickle(encoded) = encoder.finish().unwrap();
+        assert_eq!(encoded, encode_level(&data, 0));
+        assert_eq!(decode(&encoded).unwrap(), data);
+    }
+
+    // 多个成员依次解码，最后一个成员之后的尾随数据被忽略
+    #[test]
+    fn decodes_multiple_members() {
+        let mut encoded = encode(b"first member\n");
+        encoded.extend(encode(b""));
+        encoded.extend(encode(b"second member\n"));
+        encoded.extend_from_slice(&[0u8; 16]);
+        assert_eq!(decode(&encoded).unwrap(), b"first member\nsecond member\n");
+    }
+
+    // 尾部的CRC、原始大小或成员大小与数据不符时报错
+    #[test]
+    fn rejects_trailer_mismatch() {
+        let encoded = encode(&sample(1000));
+        let trailer = encoded.len() - TRAILER_SIZE;
+        for (offset, message) in [
+            (0, "LZIP数据CRC校验失败"),
+            (4, "LZIP成员大小与尾部记录不一致"),
+            (12, "LZIP成员大小与尾部记录不一致"),
+        ] {
+            let mut broken = encoded.clone();
+            broken[trailer + offset] ^= 0x01;
+            assert_eq!(decode(&broken).unwrap_err(), message, "{}", offset);
+        }
+
+        // 第二个成员损坏时同样报错
+        let mut joined = encoded.clone();
+        joined.extend(encoded.iter().enumerate().map(
+            |(index, &b)| {
+                if index == trailer {
+                    b ^ 0x01
+                } else {
+                    b
+                }
+            },
+        ));
+        assert_eq!(decode(&joined).unwrap_err(), "LZIP数据CRC校验失败");
+    }
+
+    #[test]
+    fn rejects_truncated_and_invalid_input() {
+        let encoded = encode(&sample(1000));
+        for len in [encoded.len() - 1, encoded.len() / 2] {
+            assert_eq!(decode(&encoded[..len]).unwrap_err(), "LZIP文件被截断");
+        }
+        assert_eq!(decode(b"LZI").unwrap_err(), "不是有效的LZIP文件");
+        assert_eq!(decode(b"").unwrap_err(), "不是有效的LZIP文件");
+
+        let mut version = encoded.clone();
+        version[4] = 2;
+        assert_eq!(decode(&version).unwrap_err(), "不支持的LZIP版本: 2");
+        let mut dict = encoded;
+        dict[5] = 0x0B;
+        assert!(decode(&dict).unwrap_err().contains("无效的LZIP字典大小"));
+    }
+}
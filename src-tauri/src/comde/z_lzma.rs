@@ -0,0 +1,162 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use xz2::read::XzDecoder;
+use xz2::stream::{LzmaOptions, Stream};
+use xz2::write::XzEncoder;
+
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// LZMA-alone (.lzma) 格式，通过xz2链接的liblzma读写
+#[derive(Clone)]
+pub struct LzmaCompressor;
+
+impl LzmaCompressor {
+    pub fn new() -> Self {
+        LzmaCompressor
+    }
+
+    // 创建.lzma编码器 (级别0-9)
+    pub fn encoder<W: Write>(&self, writer: W, level: u32) -> Result<XzEncoder<W>, String> {
+        let options = LzmaOptions::new_preset(level.min(9)).map_err(|e| e.to_string())?;
+        let stream = Stream::new_lzma_encoder(&options).map_err(|e| e.to_string())?;
+        Ok(XzEncoder::new_stream(writer, stream))
+    }
+
+    // 创建.lzma解码器
+    pub fn decoder<R: Read>(&self, reader: R) -> Result<XzDecoder<R>, String> {
+        let stream = Stream::new_lzma_decoder(u64::MAX).map_err(|e| e.to_string())?;
+        Ok(XzDecoder::new_stream(reader, stream))
+    }
+}
+
+impl ComdeAble for LzmaCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["lzma"],
+            compress_options: vec!["level"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::Lzma {
+            level: preset.pick([0, 0, 2, 6, 8, 9]),
+            settings: Default::default(),
+        })
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        // 处理压缩选项
+        let compression_level = match options {
+            Some(CompressionOptions::Lzma { level, .. }) => level,
+            _ => 6, // 默认压缩级别为6
+        };
+
+        // LZMA只能压缩单个文件，不能打包多个文件
+        if input_paths.len() > 1 {
+            return Err("LZMA只能压缩单个文件，无法打包多个文件。请使用TAR+LZMA组合".to_string());
+        }
+
+        let input_path = input_paths[0];
+        let path = Path::new(input_path);
+
+        if path.is_dir() {
+            return Err("LZMA不能直接压缩目录，请先使用TAR打包后再用LZMA压缩".to_string());
+        }
+
+        // 打开输入文件
+        let mut input_file = File::open(path).map_err(|e| e.to_string())?;
+        let mut buffer = Vec::new();
+        input_file
+            .read_to_end(&mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        // 创建输出文件
+        let output_file = AtomicFile::create(output_path)?;
+
+        // 压缩数据
+        let mut encoder = self.encoder(output_file, compression_level)?;
+        encoder.write_all(&buffer).map_err(|e| e.to_string())?;
+        encoder.finish().map_err(|e| e.to_string())?.commit()
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // LZMA只能处理单个文件
+        for input_path in input_paths {
+            let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，并删除已创建的输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+
+            // 获取输出文件名
+            let output_file_path = if Path::new(output_path).is_dir() {
+                let input_filename = Path::new(input_path)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .ok_or("无法获取输入文件名")?;
+
+                // 移除.lzma扩展名
+                let original_name = if input_filename.ends_with(".lzma") {
+                    &input_filename[0..input_filename.len() - 5]
+                } else {
+                    input_filename
+                };
+
+                Path::new(output_path).join(original_name)
+            } else {
+                Path::new(output_path).to_path_buf()
+            };
+
+            // 解压数据
+            let mut decoder = self.decoder(input_file)?;
+            // 目标文件已存在时按冲突策略处理，流格式以压缩文件的修改时间作为条目时间
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                None,
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            limiter.track(&output_file_path);
+            let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
+            let mut buffer = Vec::new();
+
+            limiter
+                .reader(&mut decoder)
+                .read_to_end(&mut buffer)
+                .map_err(|e| e.to_string())?;
+            output_file.write_all(&buffer).map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+}
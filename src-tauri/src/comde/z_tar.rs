@@ -1,64 +1,609 @@
+use bzip2::read::MultiBzDecoder;
+use bzip2::write::BzEncoder;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Write};
 use std::path::Path;
-use tar::{Archive, Builder};
+use tar::{Archive, Builder, Entry, EntryType, Header, HeaderMode};
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::ConflictResolver;
+use super::journal::Journal;
+use super::limits::Limiter;
+use super::posix::{self, MetadataRestorer, TarMetadata};
+use super::reproducible::{self, Reproducible};
+use super::z_bzip2::Bzip2Compressor;
+use super::z_gzip::GzipCompressor;
+use super::z_lz4::Lz4Compressor;
+use super::z_lzip::LzipCompressor;
+use super::z_lzma::LzmaCompressor;
+use super::z_xz::XzCompressor;
+use super::z_zstd::ZstdCompressor;
+use super::{
+    is_selected, normalize_entry_path, safe_join, stays_inside, ArchiveEntry, ArchiveReader,
+    Capabilities, ComdeAble, CompressSettings, CompressionOptions, CompressionPreset,
+    DecompressionOptions, XzCheck,
+};
+
+// TAR归档外层的流压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TarCodec {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+    Lz4,
+    Lzma,
+    Lzip,
+}
+
+impl TarCodec {
+    // 根据数据开头的魔数识别流压缩格式，无法识别时返回None
+    pub fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1F, 0x8B]) {
+            TarCodec::Gzip
+        } else if header.starts_with(b"BZh") {
+            TarCodec::Bzip2
+        } else if header.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+            TarCodec::Xz
+        } else if header.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            TarCodec::Zstd
+        } else if header.starts_with(&[0x04, 0x22, 0x4D, 0x18]) {
+            TarCodec::Lz4
+        } else if header.starts_with(b"LZIP") {
+            TarCodec::Lzip
+        } else if header.starts_with(&[0x5D, 0x00, 0x00]) {
+            TarCodec::Lzma
+        } else {
+            TarCodec::None
+        }
+    }
+
+    // 根据文件名识别tar归档的外层压缩格式 (如 data.tar.xz)
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        if name.ends_with(".tar") {
+            return Some(TarCodec::None);
+        }
+
+        let (stem, ext) = name.rsplit_once('.')?;
+        if !stem.ends_with(".tar") {
+            return None;
+        }
+
+        match ext {
+            "gz" => Some(TarCodec::Gzip),
+            "bz2" => Some(TarCodec::Bzip2),
+            "xz" => Some(TarCodec::Xz),
+            "zst" => Some(TarCodec::Zstd),
+            "lz4" => Some(TarCodec::Lz4),
+            "lzma" => Some(TarCodec::Lzma),
+            "lz" => Some(TarCodec::Lzip),
+            _ => None,
+        }
+    }
+
+    // 为读取器套上对应的解码器
+    pub fn decoder<'a, R: Read + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>, String> {
+        let decoder: Box<dyn Read + 'a> = match self {
+            TarCodec::None => Box::new(reader),
+            TarCodec::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            TarCodec::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            TarCodec::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+            TarCodec::Zstd => {
+                let mut decoder = zstd::stream::Decoder::new(reader).map_err(|e| e.to_string())?;
+                // 允许读取使用长距离模式压缩的归档
+                decoder.window_log_max(31).map_err(|e| e.to_string())?;
+                Box::new(decoder)
+            }
+            TarCodec::Lz4 => Box::new(Lz4Compressor::new().decoder(reader)?),
+            TarCodec::Lzma => Box::new(LzmaCompressor::new().decoder(reader)?),
+            TarCodec::Lzip => Box::new(LzipCompressor::new().decoder(reader)),
+        };
+
+        Ok(decoder)
+    }
+
+    // 为读取器套上解码器，解码出的数据流按解压限制累计
+    pub fn limited_decoder<'a, R: Read + 'a>(
+        self,
+        reader: R,
+        limiter: &'a Limiter,
+    ) -> Result<Box<dyn Read + 'a>, String> {
+        Ok(Box::new(limiter.stream_reader(self.decoder(reader)?)))
+    }
+}
+
+// ustar头部数字字段能表示的最大值: 大小和时间为11位八进制，ID为7位八进制
+const USTAR_MAX_SIZE: u64 = 0o77777777777;
+const USTAR_MAX_ID: u64 = 0o7777777;
+
+// 新建归档使用的头部格式，读取时三种格式都能识别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TarFormat {
+    // POSIX.1-1988，路径最长256字节，文件小于8GB，不能保存扩展属性
+    Ustar,
+    // GNU tar的格式，长路径和大文件使用GNU扩展，部分工具无法识别
+    Gnu,
+    // POSIX.1-2001，长路径、大文件、纳秒级时间和扩展属性都保存在PAX扩展头中
+    #[default]
+    Pax,
+}
+
+impl TarFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ustar" => Some(TarFormat::Ustar),
+            "gnu" => Some(TarFormat::Gnu),
+            "pax" | "posix" => Some(TarFormat::Pax),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TarFormat::Ustar => "ustar",
+            TarFormat::Gnu => "gnu",
+            TarFormat::Pax => "pax",
+        }
+    }
+}
 
 #[derive(Clone)]
-pub struct TarCompressor;
+pub struct TarCompressor {
+    codec: TarCodec,
+}
 
 impl TarCompressor {
     pub fn new() -> Self {
-        TarCompressor
+        TarCompressor {
+            codec: TarCodec::None,
+        }
     }
-}
 
-impl ComdeAble for TarCompressor {
-    async fn compress(
+    // 创建带外层流压缩的TAR压缩器 (如 .tar.lz4)
+    pub fn with_codec(codec: TarCodec) -> Self {
+        TarCompressor { codec }
+    }
+
+    // 将所有输入写入tar归档，返回底层写入器以便结束外层压缩流
+    fn build<W: Write>(
         &self,
-        input_paths: Vec<&str>,
-        output_path: &str,
-        _options: Option<CompressionOptions>,
-    ) -> Result<(), String> {
-        // 创建输出文件
-        let file = File::create(output_path).map_err(|e| e.to_string())?;
-        let mut builder = Builder::new(file);
+        writer: W,
+        input_paths: &[&str],
+        settings: &CompressSettings,
+    ) -> Result<W, String> {
+        let metadata = &settings.tar_metadata;
+        let format = settings.tar_format;
+        let reproducible = reproducible::load(settings.reproducible)?;
+        let input_paths = match reproducible {
+            Some(_) => reproducible::sort_inputs(input_paths),
+            None => input_paths.to_vec(),
+        };
+        let mut builder = Builder::new(writer);
 
-        // 将每个输入路径添加到tar归档中
         for input_path in input_paths {
             let path = Path::new(input_path);
 
-            if path.is_dir() {
-                // 添加目录及其所有内容
-                builder
-                    .append_dir_all(
-                        path.file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string(),
-                        path,
-                    )
-                    .map_err(|e| e.to_string())?;
-            } else if path.is_file() {
-                // 添加单个文件
-                let mut file = File::open(path).map_err(|e| e.to_string())?;
-                let file_name = path.file_name().unwrap_or_default();
-                builder
-                    .append_file(file_name, &mut file)
-                    .map_err(|e| e.to_string())?;
+            // 目录及其所有内容以目录名为根，单个文件直接放在归档顶层
+            if path.is_dir() || path.is_file() {
+                let name = path.file_name().unwrap_or_default();
+                append_tree(
+                    &mut builder,
+                    path,
+                    Path::new(name),
+                    metadata,
+                    format,
+                    reproducible.as_ref(),
+                )?;
             }
         }
 
         // 完成归档
-        builder.finish().map_err(|e| e.to_string())?;
-        Ok(())
+        builder.into_inner().map_err(|e| e.to_string())
     }
 
-    async fn decompress(
+    // 列出tar数据流中的条目，数据流按本压缩器的外层格式解码
+    pub fn list_stream<R: Read>(&self, reader: R) -> Result<Vec<ArchiveEntry>, String> {
+        let mut archive = Archive::new(self.codec.decoder(reader)?);
+        let mut entries = Vec::new();
+
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let path =
+                normalize_entry_path(&entry.path().map_err(|e| e.to_string())?.to_string_lossy());
+            if path.is_empty() {
+                continue;
+            }
+
+            // 大小按PAX扩展头中的记录，超过8GB的文件头部字段放不下
+            let header = entry.header();
+            entries.push(ArchiveEntry {
+                path,
+                size: entry.size(),
+                is_dir: header.entry_type() == EntryType::Directory,
+                mtime: header.mtime().ok(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    // 解压tar数据流中选中的条目，未选择任何条目时解压全部
+    pub fn extract_stream<R: Read>(
+        &self,
+        reader: R,
+        output_dir: &Path,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
+        limiter: &Limiter,
+        mut journal: Option<&mut Journal>,
+    ) -> Result<(), String> {
+        let mut archive = Archive::new(self.codec.limited_decoder(reader, limiter)?);
+        let mut restorer = MetadataRestorer::new(conflict.settings().tar_metadata.clone());
+        restorer.configure(&mut archive);
+        // 已存在的文件由冲突策略决定是否删除，tar不得自行替换
+        archive.set_overwrite(false);
+
+        // 超出解压限制时，tar会把读取错误包装成自己的消息，统一换回限制错误
+        for entry in archive.entries().map_err(|e| limiter.error(e))? {
+            let mut entry = entry.map_err(|e| limiter.error(e))?;
+            let path = entry
+                .path()
+                .map_err(|e| e.to_string())?
+                .to_string_lossy()
+                .to_string();
+
+            // 未选中的条目同样计入条目数，其数据也要被解码
+            let header = entry.header();
+            limiter.begin_entry(&path, Some(entry.size()))?;
+            if !is_selected(&path, selection) {
+                continue;
+            }
+
+            // 扩展头和长名称由tar自身处理，不写出任何文件
+            let entry_type = header.entry_type();
+            if entry_type.is_pax_global_extensions()
+                || entry_type.is_pax_local_extensions()
+                || entry_type.is_gnu_longname()
+                || entry_type.is_gnu_longlink()
+            {
+                continue;
+            }
+
+            // 名称按本系统的规则清理；旧格式中以/结尾的条目同样是目录
+            let is_dir =
+                entry_type.is_dir() || (header.as_ustar().is_none() && path.ends_with('/'));
+            let target = if is_dir {
+                conflict.dir_path(output_dir, &path)?
+            } else {
+                conflict.entry_path(output_dir, &path)?
+            };
+            // 路径含有..或上级目录是指向输出目录之外的符号链接时跳过
+            let Some(target) = target else {
+                continue;
+            };
+            // 名称未变时由unpack_in写出，它会检查父目录中的符号链接
+            let renamed = safe_join(output_dir, &path).as_ref() != Some(&target);
+
+            // 目录直接合并
+            if is_dir {
+                if renamed {
+                    unpack_renamed(&mut entry, &target).map_err(|e| limiter.error(e))?;
+                } else {
+                    entry.unpack_in(output_dir).map_err(|e| limiter.error(e))?;
+                }
+                restorer.restore(&mut entry, &target)?;
+                continue;
+            }
+
+            // 跳过上次中断前已完成的条目，数据由entries迭代时读过
+            if journal
+                .as_deref()
+                .is_some_and(|journal| journal.skip(&path))
+            {
+                continue;
+            }
+
+            // 文件、符号链接、硬链接等都按冲突策略处理，覆盖时resolve已删除已有的文件
+            let (size, mtime) = (Some(entry.size()), header.mtime().ok());
+            let Some(resolved) = conflict.resolve(&target, size, mtime)? else {
+                continue;
+            };
+            if let Some(journal) = journal.as_deref_mut() {
+                journal.begin(&path, &resolved)?;
+            }
+            limiter.track(&resolved);
+            if resolved == target && !renamed {
+                entry.unpack_in(output_dir).map_err(|e| limiter.error(e))?;
+            } else if entry_type.is_hard_link() {
+                link_renamed(&entry, output_dir, &resolved)?;
+            } else {
+                unpack_renamed(&mut entry, &resolved).map_err(|e| limiter.error(e))?;
+            }
+            // 硬链接与其源共用元数据，不再单独恢复
+            if !entry_type.is_hard_link() {
+                restorer.restore(&mut entry, &resolved)?;
+            }
+            if let Some(journal) = journal.as_deref_mut() {
+                journal.finish(&path, &resolved)?;
+            }
+        }
+
+        restorer.finish()
+    }
+
+    // 带日志解压，中断后重新执行时从第一个未完成的条目继续
+    pub fn decompress_resumable(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
-        _options: Option<DecompressionOptions>,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        let output_dir = Path::new(output_path);
+        fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+
+        for input_path in input_paths {
+            let mut journal = Journal::open(output_dir, input_path)?;
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+            let file = File::open(input_path).map_err(|e| e.to_string())?;
+            self.extract_stream(
+                file,
+                output_dir,
+                &[],
+                &mut conflict,
+                &limiter,
+                Some(&mut journal),
+            )?;
+            journal.close()?;
+        }
+
+        Ok(())
+    }
+}
+
+// 写入文件或目录 (递归，按名称排序)，符号链接按其指向的内容写入
+//
+// 头部记录所有者、权限和修改时间，按设置读取的扩展属性和ACL写在条目之前的PAX扩展头中
+fn append_tree<W: Write>(
+    builder: &mut Builder<W>,
+    path: &Path,
+    name: &Path,
+    settings: &TarMetadata,
+    format: TarFormat,
+    reproducible: Option<&Reproducible>,
+) -> Result<(), String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut header = match format {
+        TarFormat::Gnu => Header::new_gnu(),
+        TarFormat::Ustar | TarFormat::Pax => Header::new_ustar(),
+    };
+    let mut records = posix::pax_records(path, settings)?;
+    match reproducible {
+        // 所有者清零且不记录用户名，权限和修改时间按可复现规则归一
+        Some(reproducible) => {
+            header.set_metadata_in_mode(&metadata, HeaderMode::Deterministic);
+            header.set_mode(reproducible.mode(&metadata));
+            header.set_mtime(reproducible.mtime(&metadata));
+        }
+        None => {
+            header.set_metadata_in_mode(&metadata, HeaderMode::Complete);
+            posix::set_owner(
+                &mut header,
+                &posix::archive_owner(&metadata, settings)?,
+                &mut records,
+            );
+            // 头部只能保存整秒
+            if format == TarFormat::Pax {
+                records.extend(posix::mtime_record(&metadata));
+            }
+        }
+    }
+    let mut append = |header: &mut Header, data: &mut dyn Read| {
+        append_entry(builder, header, name, &records, format, data)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    };
+
+    if metadata.is_dir() {
+        header.set_size(0);
+        append(&mut header, &mut io::empty())?;
+
+        let mut children = fs::read_dir(path)
+            .map_err(|e| e.to_string())?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        children.sort();
+        for child in children {
+            append_tree(
+                builder,
+                &path.join(&child),
+                &name.join(&child),
+                settings,
+                format,
+                reproducible,
+            )?;
+        }
+        return Ok(());
+    }
+
+    if metadata.is_file() {
+        header.set_size(metadata.len());
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        return append(&mut header, &mut file);
+    }
+
+    // 设备文件记录设备号，套接字无法保存，与GNU tar一样跳过
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+        let file_type = metadata.file_type();
+        if file_type.is_socket() {
+            return Ok(());
+        }
+        if file_type.is_char_device() || file_type.is_block_device() {
+            let device = metadata.rdev();
+            let major = ((device >> 32) & 0xffff_f000) | ((device >> 8) & 0x0000_0fff);
+            let minor = ((device >> 12) & 0xffff_ff00) | (device & 0x0000_00ff);
+            header
+                .set_device_major(major as u32)
+                .map_err(|e| e.to_string())?;
+            header
+                .set_device_minor(minor as u32)
+                .map_err(|e| e.to_string())?;
+        }
+        header.set_size(0);
+        append(&mut header, &mut io::empty())?;
+    }
+
+    Ok(())
+}
+
+// 按头部格式写出一个条目
+//
+// GNU格式由tar crate处理长路径 (././@LongLink条目) 和大数字 (base-256编码)；
+// PAX格式把头部放不下的路径、大小和ID写入PAX扩展头；ustar格式遇到放不下的内容时报错。
+fn append_entry<W: Write>(
+    builder: &mut Builder<W>,
+    header: &mut Header,
+    name: &Path,
+    records: &[(String, Vec<u8>)],
+    format: TarFormat,
+    data: &mut dyn Read,
+) -> Result<(), String> {
+    let mut records = records.to_vec();
+    if format == TarFormat::Gnu {
+        append_records(builder, &records)?;
+        return builder
+            .append_data(header, name, data)
+            .map_err(|e| e.to_string());
+    }
+
+    let size = header.size().map_err(|e| e.to_string())?;
+    let uid = header.uid().map_err(|e| e.to_string())?;
+    let gid = header.gid().map_err(|e| e.to_string())?;
+    let path_fits = header.set_path(name).is_ok();
+
+    if format == TarFormat::Ustar {
+        if !path_fits {
+            return Err("路径过长，ustar格式最多保存256字节的路径，请使用PAX格式".to_string());
+        }
+        if size > USTAR_MAX_SIZE {
+            return Err("文件超过8GB，ustar格式无法保存，请使用PAX格式".to_string());
+        }
+        if uid > USTAR_MAX_ID || gid > USTAR_MAX_ID {
+            return Err("用户或组ID过大，ustar格式无法保存，请使用PAX格式".to_string());
+        }
+        if !records.is_empty() {
+            return Err("ustar格式无法保存扩展属性、ACL和过长的用户名，请使用PAX格式".to_string());
+        }
+    } else {
+        if !path_fits {
+            let path = entry_name(name);
+            set_truncated_path(header, &path);
+            records.push(("path".to_string(), path.into_bytes()));
+        }
+        if size > USTAR_MAX_SIZE {
+            records.push(("size".to_string(), size.to_string().into_bytes()));
+        }
+        if uid > USTAR_MAX_ID {
+            records.push(("uid".to_string(), uid.to_string().into_bytes()));
+        }
+        if gid > USTAR_MAX_ID {
+            records.push(("gid".to_string(), gid.to_string().into_bytes()));
+        }
+    }
+
+    append_records(builder, &records)?;
+    header.set_cksum();
+    builder.append(header, data).map_err(|e| e.to_string())
+}
+
+// 在条目前写出PAX扩展头，没有记录时不写
+fn append_records<W: Write>(
+    builder: &mut Builder<W>,
+    records: &[(String, Vec<u8>)],
+) -> Result<(), String> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    builder
+        .append_pax_extensions(
+            records
+                .iter()
+                .map(|(key, value)| (key.as_str(), &value[..])),
+        )
+        .map_err(|e| e.to_string())
+}
+
+// 归档中的条目名，各级之间使用/分隔
+fn entry_name(name: &Path) -> String {
+    name.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// PAX扩展头中记录了完整路径时，头部的name字段只保存路径开头，供不识别PAX的工具参考
+fn set_truncated_path(header: &mut Header, path: &str) {
+    let Some(ustar) = header.as_ustar_mut() else {
+        return;
+    };
+    let mut end = path.len().min(ustar.name.len());
+    while !path.is_char_boundary(end) {
+        end -= 1;
+    }
+    ustar.name = [0; 100];
+    ustar.name[..end].copy_from_slice(&path.as_bytes()[..end]);
+    ustar.prefix = [0; 155];
+}
+
+// 把条目写到与原名不同的路径，unpack不会创建上级目录；
+// unpack也不检查上级目录中的符号链接，target须已由entry_path确认在输出目录中
+fn unpack_renamed<R: Read>(entry: &mut Entry<'_, R>, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    entry.unpack(target).map(|_| ())
+}
+
+// 以新名称写出硬链接，链接源按原名在输出目录中查找，且必须仍在输出目录中
+fn link_renamed<R: Read>(
+    entry: &Entry<'_, R>,
+    output_dir: &Path,
+    target: &Path,
+) -> Result<(), String> {
+    let source = entry
+        .link_name()
+        .map_err(|e| e.to_string())?
+        .and_then(|name| safe_join(output_dir, &name.to_string_lossy()))
+        .filter(|source| stays_inside(output_dir, source))
+        .ok_or_else(|| format!("硬链接的源无效: {}", target.display()))?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::hard_link(&source, target).map_err(|e| format!("{}: {}", target.display(), e))
+}
+
+impl ArchiveReader for TarCompressor {
+    fn list(&self, archive_path: &str) -> Result<Vec<ArchiveEntry>, String> {
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        self.list_stream(file)
+    }
+
+    fn extract(
+        &self,
+        archive_path: &str,
+        output_path: &str,
+        selection: &[String],
+        conflict: &mut ConflictResolver,
     ) -> Result<(), String> {
         // 确保输出目录存在
         let output_dir = Path::new(output_path);
@@ -66,15 +611,290 @@ impl ComdeAble for TarCompressor {
             fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
         }
 
+        let limiter = Limiter::new(archive_path, conflict.settings().limits);
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        self.extract_stream(file, output_dir, selection, conflict, &limiter, None)
+    }
+}
+
+impl ComdeAble for TarCompressor {
+    fn capabilities(&self) -> Capabilities {
+        // 纯TAR不压缩，没有压缩级别；复合格式沿用外层流压缩器的级别和选项
+        let stream = match self.codec {
+            TarCodec::None => None,
+            TarCodec::Gzip => Some(GzipCompressor::new().capabilities()),
+            TarCodec::Bzip2 => Some(Bzip2Compressor::new().capabilities()),
+            TarCodec::Xz => Some(XzCompressor::new().capabilities()),
+            TarCodec::Zstd => Some(ZstdCompressor::new().capabilities()),
+            TarCodec::Lz4 => Some(Lz4Compressor::new().capabilities()),
+            TarCodec::Lzma => Some(LzmaCompressor::new().capabilities()),
+            TarCodec::Lzip => Some(LzipCompressor::new().capabilities()),
+        };
+
+        let (level, methods, mut compress_options) = match stream {
+            Some(stream) => (stream.level, stream.methods, stream.compress_options),
+            None => (None, vec!["store"], Vec::new()),
+        };
+        // 归档内没有GZIP头部注释，可复现设置对所有外层格式都由tar处理
+        compress_options.retain(|option| !matches!(*option, "comment" | "reproducible"));
+        compress_options.extend(["tar_metadata", "reproducible", "tar_format"]);
+
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: true,
+            multi_input: true,
+            supports_password: false,
+            level,
+            methods,
+            compress_options,
+            // 解码器使用固定设置，只读取文件元数据的恢复方式
+            decompress_options: vec!["tar_metadata"],
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        // 复合格式使用外层流压缩器的选项，与compress中读取的选项类型一致
+        match self.codec {
+            TarCodec::None => Some(CompressionOptions::Tar {
+                level: 0,
+                settings: Default::default(),
+            }),
+            TarCodec::Gzip => GzipCompressor::new().preset_options(preset),
+            TarCodec::Bzip2 => Bzip2Compressor::new().preset_options(preset),
+            TarCodec::Xz => XzCompressor::new().preset_options(preset),
+            TarCodec::Zstd => ZstdCompressor::new().preset_options(preset),
+            TarCodec::Lz4 => Lz4Compressor::new().preset_options(preset),
+            TarCodec::Lzma => LzmaCompressor::new().preset_options(preset),
+            TarCodec::Lzip => LzipCompressor::new().preset_options(preset),
+        }
+    }
+
+    async fn compress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<CompressionOptions>,
+    ) -> Result<(), String> {
+        // 复合格式的选项属于外层流压缩器，设置在各种选项中相同
+        let settings = options
+            .as_ref()
+            .map(|options| options.settings().clone())
+            .unwrap_or_default();
+
+        // 创建输出文件
+        let file = AtomicFile::create(output_path)?;
+
+        let file = match self.codec {
+            TarCodec::None => self.build(file, &input_paths, &settings)?,
+            TarCodec::Gzip => {
+                let level = match options {
+                    Some(CompressionOptions::Gzip { level, .. }) => level,
+                    _ => 6,
+                };
+
+                // GZIP头部不含文件名，修改时间为0，相同输入得到相同的输出
+                let encoder = GzEncoder::new(file, flate2::Compression::new(level.min(9)));
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish().map_err(|e| e.to_string())?
+            }
+            TarCodec::Bzip2 => {
+                let level = match options {
+                    Some(CompressionOptions::Bzip2 { level, .. }) => level,
+                    _ => 6,
+                };
+
+                let encoder = BzEncoder::new(file, bzip2::Compression::new(level.clamp(1, 9)));
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish().map_err(|e| e.to_string())?
+            }
+            TarCodec::Xz => {
+                let (level, extreme, filters, dict_size, check) = match options {
+                    Some(CompressionOptions::Xz {
+                        level,
+                        extreme,
+                        filters,
+                        dict_size,
+                        check,
+                        ..
+                    }) => (level, extreme, filters, dict_size, check),
+                    _ => (6, false, Vec::new(), None, XzCheck::Crc64),
+                };
+
+                let encoder = XzCompressor::new()
+                    .encoder(file, level, extreme, &filters, dict_size, check)?;
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish().map_err(|e| e.to_string())?
+            }
+            TarCodec::Zstd => {
+                let (level, dictionary, long_distance_matching, window_log, checksum) =
+                    match options {
+                        Some(CompressionOptions::Zstd {
+                            level,
+                            dictionary,
+                            long_distance_matching,
+                            window_log,
+                            checksum,
+                            ..
+                        }) => (
+                            level,
+                            dictionary,
+                            long_distance_matching,
+                            window_log,
+                            checksum,
+                        ),
+                        _ => (3, None, false, None, false),
+                    };
+
+                let encoder = ZstdCompressor::new().encoder(
+                    file,
+                    level,
+                    dictionary.as_deref(),
+                    long_distance_matching,
+                    window_log,
+                    checksum,
+                )?;
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish().map_err(|e| e.to_string())?
+            }
+            TarCodec::Lz4 => {
+                let (level, checksum) = match options {
+                    Some(CompressionOptions::Lz4 {
+                        level, checksum, ..
+                    }) => (level, checksum),
+                    _ => (1, false),
+                };
+
+                // 完成归档并写入LZ4帧结束标记
+                let lz4 = Lz4Compressor::new();
+                let encoder =
+                    self.build(lz4.encoder(file, level, checksum)?, &input_paths, &settings)?;
+                lz4.finish(encoder)?
+            }
+            TarCodec::Lzma => {
+                let level = match options {
+                    Some(CompressionOptions::Lzma { level, .. }) => level,
+                    _ => 6,
+                };
+
+                // 完成归档并写入LZMA结束标记
+                let encoder = LzmaCompressor::new().encoder(file, level)?;
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish().map_err(|e| e.to_string())?
+            }
+            TarCodec::Lzip => {
+                let level = match options {
+                    Some(CompressionOptions::Lzip { level, .. }) => level,
+                    _ => 6,
+                };
+
+                // 完成归档并写入lzip成员尾部
+                let encoder = LzipCompressor::new().encoder(file, level, None)?;
+                let encoder = self.build(encoder, &input_paths, &settings)?;
+                encoder.finish()?
+            }
+        };
+
+        file.commit()
+    }
+
+    async fn decompress(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
         // 解压每个输入文件
         for input_path in input_paths {
-            let file = File::open(input_path).map_err(|e| e.to_string())?;
-            let mut archive = Archive::new(file);
-
-            // 解压所有文件到输出目录
-            archive.unpack(output_dir).map_err(|e| e.to_string())?;
+            self.extract(input_path, output_path, &[], &mut conflict)?;
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::TempDir;
+
+    // 含一个普通文件、指向它的符号链接和硬链接的归档
+    fn links_archive(path: &Path) {
+        let mut builder = Builder::new(File::create(path).unwrap());
+        let mut header = Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "data.txt", &b"data"[..])
+            .unwrap();
+        for (entry_type, name) in [(EntryType::Symlink, "soft"), (EntryType::Link, "hard")] {
+            let mut header = Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(0);
+            header.set_mode(0o644);
+            builder.append_link(&mut header, name, "data.txt").unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn extract(archive: &Path, output: &Path, policy: ConflictPolicy) -> Result<(), String> {
+        TarCompressor::new().extract(
+            archive.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &[],
+            &mut ConflictResolver::new(policy),
+        )
+    }
+
+    // 符号链接和硬链接与普通文件一样按冲突策略处理
+    #[test]
+    fn links_follow_conflict_policy() {
+        let dir = TempDir::new("tar-links");
+        let archive = dir.join("links.tar");
+        links_archive(&archive);
+
+        let output = dir.join("skip");
+        fs::create_dir_all(&output).unwrap();
+        for name in ["soft", "hard"] {
+            fs::write(output.join(name), "existing").unwrap();
+        }
+        extract(&archive, &output, ConflictPolicy::Skip).unwrap();
+        for name in ["soft", "hard"] {
+            let metadata = fs::symlink_metadata(output.join(name)).unwrap();
+            assert!(metadata.is_file());
+            assert_eq!(fs::read_to_string(output.join(name)).unwrap(), "existing");
+        }
+
+        let output = dir.join("rename");
+        fs::create_dir_all(&output).unwrap();
+        for name in ["soft", "hard"] {
+            fs::write(output.join(name), "existing").unwrap();
+        }
+        extract(&archive, &output, ConflictPolicy::Rename).unwrap();
+        for name in ["soft", "hard"] {
+            assert_eq!(fs::read_to_string(output.join(name)).unwrap(), "existing");
+            assert_eq!(
+                fs::read_to_string(output.join(format!("{} (1)", name))).unwrap(),
+                "data"
+            );
+        }
+        #[cfg(unix)]
+        assert!(fs::symlink_metadata(output.join("soft (1)"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+
+        let output = dir.join("overwrite");
+        fs::create_dir_all(&output).unwrap();
+        for name in ["soft", "hard"] {
+            fs::write(output.join(name), "existing").unwrap();
+        }
+        extract(&archive, &output, ConflictPolicy::Overwrite).unwrap();
+        for name in ["soft", "hard"] {
+            assert_eq!(fs::read_to_string(output.join(name)).unwrap(), "data");
+        }
+    }
+}
@@ -1,10 +1,220 @@
+use std::ffi::c_void;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 use std::path::Path;
+use std::{mem, ptr};
 use xz2::read::XzDecoder;
-use xz2::write::XzEncoder;
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// liblzma中的LZMA_PRESET_EXTREME标志
+const PRESET_EXTREME: u32 = 1 << 31;
+
+// XZ流头部的魔数
+const XZ_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
+
+// liblzma中增量过滤器的ID，lzma-sys没有导出
+const FILTER_DELTA: u64 = 0x03;
+
+// 编码时每次调用liblzma的输出缓冲区大小
+const OUTPUT_BUFFER_SIZE: usize = 32 * 1024;
+
+// 在LZMA2之前应用的过滤器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XzFilter {
+    X86,
+    Arm,
+    ArmThumb,
+    PowerPc,
+    Ia64,
+    Sparc,
+    // 按字节距离做差分，适合未压缩的音频、图像等定长采样数据
+    Delta { dist: u32 },
+}
+
+impl XzFilter {
+    // 从名称解析过滤器，增量过滤器可写为 delta 或 delta:距离 (1-256)
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let name = name.trim().to_lowercase();
+        let (kind, dist) = match name.split_once(':') {
+            Some((kind, dist)) => (kind, Some(dist)),
+            None => (name.as_str(), None),
+        };
+
+        match (kind, dist) {
+            ("delta", dist) => {
+                let dist = match dist {
+                    Some(dist) => dist
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|dist| (1..=256).contains(dist))
+                        .ok_or_else(|| format!("增量过滤器的距离必须在1到256之间: {}", dist))?,
+                    None => 1,
+                };
+                Ok(Self::Delta { dist })
+            }
+            // 内置的liblzma为5.2版本，ARM64过滤器从5.4才开始提供
+            ("arm64", None) => Err("不支持ARM64过滤器: 需要liblzma 5.4或更高版本".to_string()),
+            ("x86", None) => Ok(Self::X86),
+            ("arm", None) => Ok(Self::Arm),
+            ("armthumb", None) => Ok(Self::ArmThumb),
+            ("powerpc", None) => Ok(Self::PowerPc),
+            ("ia64", None) => Ok(Self::Ia64),
+            ("sparc", None) => Ok(Self::Sparc),
+            (_, Some(_)) => Err(format!("只有增量过滤器可以指定参数: {}", name)),
+            _ => Err(format!("未知的XZ过滤器: {}", name)),
+        }
+    }
+
+    // liblzma中的过滤器ID
+    fn id(&self) -> u64 {
+        match self {
+            XzFilter::X86 => lzma_sys::LZMA_FILTER_X86,
+            XzFilter::Arm => lzma_sys::LZMA_FILTER_ARM,
+            XzFilter::ArmThumb => lzma_sys::LZMA_FILTER_ARMTHUMB,
+            XzFilter::PowerPc => lzma_sys::LZMA_FILTER_POWERPC,
+            XzFilter::Ia64 => lzma_sys::LZMA_FILTER_IA64,
+            XzFilter::Sparc => lzma_sys::LZMA_FILTER_SPARC,
+            XzFilter::Delta { .. } => FILTER_DELTA,
+        }
+    }
+}
+
+// liblzma的lzma_options_delta结构
+#[repr(C)]
+struct DeltaOptions {
+    // 0 即LZMA_DELTA_TYPE_BYTE，目前唯一的类型
+    kind: u32,
+    dist: u32,
+    reserved_int: [u32; 8],
+    reserved_ptr: [*mut c_void; 2],
+}
+
+// XZ完整性校验类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XzCheck {
+    None,
+    Crc32,
+    Crc64,
+    Sha256,
+}
+
+impl XzCheck {
+    // 从名称解析校验类型
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "crc32" => Some(Self::Crc32),
+            "crc64" => Some(Self::Crc64),
+            "sha256" | "sha-256" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    // 获取校验类型显示名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            XzCheck::None => "None",
+            XzCheck::Crc32 => "CRC32",
+            XzCheck::Crc64 => "CRC64",
+            XzCheck::Sha256 => "SHA-256",
+        }
+    }
+
+    // 从流头部的校验ID转换
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0x00 => Some(Self::None),
+            0x01 => Some(Self::Crc32),
+            0x04 => Some(Self::Crc64),
+            0x0A => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+
+    fn to_check(self) -> lzma_sys::lzma_check {
+        match self {
+            XzCheck::None => lzma_sys::LZMA_CHECK_NONE,
+            XzCheck::Crc32 => lzma_sys::LZMA_CHECK_CRC32,
+            XzCheck::Crc64 => lzma_sys::LZMA_CHECK_CRC64,
+            XzCheck::Sha256 => lzma_sys::LZMA_CHECK_SHA256,
+        }
+    }
+}
+
+// 由lzma_end释放的liblzma编码流
+struct RawStream(lzma_sys::lzma_stream);
+
+// liblzma的流状态不依赖所在线程，与xz2的Stream相同
+unsafe impl Send for RawStream {}
+
+impl Drop for RawStream {
+    fn drop(&mut self) {
+        unsafe { lzma_sys::lzma_end(&mut self.0) }
+    }
+}
+
+// 按原始过滤器链编码的XZ写入器；xz2的Filters无法添加增量过滤器，因此直接调用liblzma
+pub struct XzWriter<W: Write> {
+    stream: RawStream,
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> XzWriter<W> {
+    // 编码一次输入，并把产生的数据写入底层写入器，返回消耗的字节数及流是否结束
+    fn code(&mut self, input: &[u8], action: lzma_sys::lzma_action) -> io::Result<(usize, bool)> {
+        let stream = &mut self.stream.0;
+        stream.next_in = input.as_ptr();
+        stream.avail_in = input.len();
+        stream.next_out = self.buffer.as_mut_ptr();
+        stream.avail_out = self.buffer.len();
+        let ret = unsafe { lzma_sys::lzma_code(stream, action) };
+        let consumed = input.len() - stream.avail_in;
+        let produced = self.buffer.len() - stream.avail_out;
+        stream.next_in = ptr::null();
+        stream.next_out = ptr::null_mut();
+
+        self.writer.write_all(&self.buffer[..produced])?;
+        match ret {
+            lzma_sys::LZMA_OK => Ok((consumed, false)),
+            lzma_sys::LZMA_STREAM_END => Ok((consumed, true)),
+            code => Err(io::Error::other(format!("XZ编码失败，错误码: {}", code))),
+        }
+    }
+
+    // 结束XZ流并返回底层写入器
+    pub fn finish(mut self) -> io::Result<W> {
+        while !self.code(&[], lzma_sys::LZMA_FINISH)?.1 {}
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for XzWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        // 输出缓冲区写满时可能尚未消耗输入，继续编码直到有输入被接受
+        loop {
+            let (consumed, _) = self.code(data, lzma_sys::LZMA_RUN)?;
+            if consumed > 0 {
+                return Ok(consumed);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
 
 #[derive(Clone)]
 pub struct XzCompressor;
@@ -13,9 +223,127 @@ impl XzCompressor {
     pub fn new() -> Self {
         XzCompressor
     }
+
+    // 读取XZ流头部中记录的校验类型
+    pub fn read_check(&self, input_path: &str) -> Result<XzCheck, String> {
+        let mut header = [0u8; 12];
+        File::open(input_path)
+            .and_then(|mut file| file.read_exact(&mut header))
+            .map_err(|e| e.to_string())?;
+
+        if header[..6] != XZ_MAGIC || header[6] != 0 {
+            return Err(format!("{} 不是有效的XZ文件", input_path));
+        }
+
+        XzCheck::from_id(header[7] & 0x0F)
+            .ok_or_else(|| format!("未知的XZ校验类型: {:#x}", header[7] & 0x0F))
+    }
+
+    // 根据选项创建写入指定写入器的编码器
+    pub fn encoder<W: Write>(
+        &self,
+        writer: W,
+        level: u32,
+        extreme: bool,
+        filters: &[XzFilter],
+        dict_size: Option<u32>,
+        check: XzCheck,
+    ) -> Result<XzWriter<W>, String> {
+        let mut preset = level.min(9);
+        if extreme {
+            preset |= PRESET_EXTREME;
+        }
+
+        let mut lzma_options: lzma_sys::lzma_options_lzma = unsafe { mem::zeroed() };
+        if unsafe { lzma_sys::lzma_lzma_preset(&mut lzma_options, preset) } != 0 {
+            return Err(format!("无效的XZ压缩级别: {}", level));
+        }
+        if let Some(dict_size) = dict_size {
+            lzma_options.dict_size = dict_size;
+        }
+
+        // 过滤器链: 先按顺序应用BCJ或增量过滤器，最后是LZMA2，以未知ID结尾
+        // liblzma在初始化时复制各过滤器的选项，选项只需在调用期间有效
+        let mut delta_options: Vec<Box<DeltaOptions>> = Vec::new();
+        let mut chain = Vec::with_capacity(filters.len() + 2);
+        for filter in filters {
+            let options = match filter {
+                XzFilter::Delta { dist } => {
+                    let mut options = Box::new(DeltaOptions {
+                        kind: 0,
+                        dist: *dist,
+                        reserved_int: [0; 8],
+                        reserved_ptr: [ptr::null_mut(); 2],
+                    });
+                    let options_ptr = &mut *options as *mut DeltaOptions as *mut c_void;
+                    delta_options.push(options);
+                    options_ptr
+                }
+                _ => ptr::null_mut(),
+            };
+            chain.push(lzma_sys::lzma_filter {
+                id: filter.id(),
+                options,
+            });
+        }
+        chain.push(lzma_sys::lzma_filter {
+            id: lzma_sys::LZMA_FILTER_LZMA2,
+            options: &mut lzma_options as *mut _ as *mut c_void,
+        });
+        chain.push(lzma_sys::lzma_filter {
+            id: lzma_sys::LZMA_VLI_UNKNOWN,
+            options: ptr::null_mut(),
+        });
+
+        let mut stream = RawStream(unsafe { mem::zeroed() });
+        let ret = unsafe {
+            lzma_sys::lzma_stream_encoder(&mut stream.0, chain.as_ptr(), check.to_check())
+        };
+        match ret {
+            lzma_sys::LZMA_OK => Ok(XzWriter {
+                stream,
+                writer,
+                buffer: vec![0; OUTPUT_BUFFER_SIZE],
+            }),
+            lzma_sys::LZMA_OPTIONS_ERROR => {
+                Err("XZ过滤器链无效: 最多只能使用3个过滤器，且参数需受liblzma支持".to_string())
+            }
+            lzma_sys::LZMA_MEM_ERROR => Err("创建XZ编码器时内存不足".to_string()),
+            code => Err(format!("无法创建XZ编码器，错误码: {}", code)),
+        }
+    }
 }
 
 impl ComdeAble for XzCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["lzma2"],
+            compress_options: vec!["level", "extreme", "filters", "dict_size", "check"],
+            decompress_options: Vec::new(),
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::Xz {
+            level: preset.pick([0, 0, 2, 6, 8, 9]),
+            extreme: preset == CompressionPreset::Ultra,
+            filters: Vec::new(),
+            dict_size: None,
+            check: XzCheck::Crc64,
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -23,9 +351,16 @@ impl ComdeAble for XzCompressor {
         options: Option<CompressionOptions>,
     ) -> Result<(), String> {
         // 处理压缩选项
-        let compression_level = match options {
-            Some(CompressionOptions::Tar { level, .. }) => level,
-            _ => 6, // 默认压缩级别为6
+        let (compression_level, extreme, filters, dict_size, check) = match options {
+            Some(CompressionOptions::Xz {
+                level,
+                extreme,
+                filters,
+                dict_size,
+                check,
+                ..
+            }) => (level, extreme, filters, dict_size, check),
+            _ => (6, false, Vec::new(), None, XzCheck::Crc64), // 默认压缩级别为6，CRC64校验
         };
 
         // XZ只能压缩单个文件，不能打包多个文件
@@ -48,32 +383,34 @@ impl ComdeAble for XzCompressor {
             .map_err(|e| e.to_string())?;
 
         // 创建输出文件
-        let output_file = File::create(output_path).map_err(|e| e.to_string())?;
-
-        // 设置压缩级别 (0-9，9为最高压缩率)
-        let compression_level = if compression_level > 9 {
-            9
-        } else {
-            compression_level
-        };
+        let output_file = AtomicFile::create(output_path)?;
 
-        // 压缩数据
-        let mut encoder = XzEncoder::new(output_file, compression_level);
+        // 设置压缩级别 (0-9，9为最高压缩率) 及过滤器链，然后压缩数据
+        let mut encoder = self.encoder(
+            output_file,
+            compression_level,
+            extreme,
+            &filters,
+            dict_size,
+            check,
+        )?;
         encoder.write_all(&buffer).map_err(|e| e.to_string())?;
-        encoder.finish().map_err(|e| e.to_string())?;
-
-        Ok(())
+        encoder.finish().map_err(|e| e.to_string())?.commit()
     }
 
     async fn decompress(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
-        _options: Option<DecompressionOptions>,
+        options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
         // XZ只能处理单个文件
         for input_path in input_paths {
             let input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，并删除已创建的输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
 
             // 获取输出文件名
             let output_file_path = if Path::new(output_path).is_dir() {
@@ -94,12 +431,23 @@ impl ComdeAble for XzCompressor {
                 Path::new(output_path).to_path_buf()
             };
 
-            // 解压数据
-            let mut decoder = XzDecoder::new(input_file);
+            // 解压数据，拼接的多个XZ流会被依次解压
+            let mut decoder = XzDecoder::new_multi_decoder(input_file);
+            // 目标文件已存在时按冲突策略处理，流格式以压缩文件的修改时间作为条目时间
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                None,
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
+            limiter.track(&output_file_path);
             let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
             let mut buffer = Vec::new();
 
-            decoder
+            limiter
+                .reader(&mut decoder)
                 .read_to_end(&mut buffer)
                 .map_err(|e| e.to_string())?;
             output_file.write_all(&buffer).map_err(|e| e.to_string())?;
@@ -108,3 +456,70 @@ impl ComdeAble for XzCompressor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::check_concatenated;
+    use xz2::write::XzEncoder;
+
+    // 首尾相接的多个XZ流应全部解压
+    #[tokio::test]
+    async fn decompresses_concatenated_streams() {
+        let encode = |part: &[u8]| {
+            let mut encoder = XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(part).unwrap();
+            encoder.finish().unwrap()
+        };
+        let options = DecompressionOptions::Xz {
+            conflict: ConflictPolicy::Rename,
+            settings: Default::default(),
+        };
+        check_concatenated(&XzCompressor::new(), "xz", encode, options).await;
+    }
+
+    // 增量过滤器带可选距离，ARM64明确提示不支持
+    #[test]
+    fn parses_filter_names() {
+        assert_eq!(XzFilter::parse(" X86 "), Ok(XzFilter::X86));
+        assert_eq!(XzFilter::parse("delta"), Ok(XzFilter::Delta { dist: 1 }));
+        assert_eq!(XzFilter::parse("delta:4"), Ok(XzFilter::Delta { dist: 4 }));
+        assert!(XzFilter::parse("delta:0").is_err());
+        assert!(XzFilter::parse("delta:257").is_err());
+        assert!(XzFilter::parse("x86:4").is_err());
+        assert!(XzFilter::parse("arm64").unwrap_err().contains("ARM64"));
+        assert!(XzFilter::parse("lz77").unwrap_err().contains("未知"));
+    }
+
+    // 带增量和BCJ过滤器的流可由普通解码器还原
+    #[test]
+    fn filter_chain_round_trip() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 3 % 251) as u8).collect();
+        for filters in [
+            vec![XzFilter::Delta { dist: 4 }],
+            vec![XzFilter::X86, XzFilter::Delta { dist: 256 }],
+        ] {
+            let mut encoder = XzCompressor::new()
+                .encoder(Vec::new(), 6, false, &filters, None, XzCheck::Crc32)
+                .unwrap();
+            encoder.write_all(&data).unwrap();
+            let compressed = encoder.finish().unwrap();
+
+            let mut decoded = Vec::new();
+            XzDecoder::new(&compressed[..])
+                .read_to_end(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, data);
+        }
+    }
+
+    // 超过liblzma上限的过滤器链在创建编码器时报错
+    #[test]
+    fn rejects_long_filter_chain() {
+        let filters = [XzFilter::Delta { dist: 1 }; 4];
+        assert!(XzCompressor::new()
+            .encoder(Vec::new(), 6, false, &filters, None, XzCheck::Crc64)
+            .is_err());
+    }
+}
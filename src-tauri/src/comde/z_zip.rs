@@ -1,11 +1,180 @@
+use chrono::{Datelike, Local, NaiveDate, Timelike};
+use encoding_rs::{Encoding, GBK, SHIFT_JIS};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use zip::read::ZipFile;
 use zip::write::{FileOptions, SimpleFileOptions};
 use zip::CompressionMethod;
 use zip::{ZipArchive, ZipWriter};
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::ConflictResolver;
+use super::journal::Journal;
+use super::limits::Limiter;
+use super::reproducible::{self, Reproducible};
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
+
+// General purpose flag bit 11: the entry name and comment are UTF-8
+const UTF8_FLAG: u16 = 1 << 11;
+
+// Offsets of the general purpose flags in the local and central file headers
+const LOCAL_FLAGS_OFFSET: u64 = 6;
+const CENTRAL_FLAGS_OFFSET: u64 = 8;
+
+// Code page of entry names stored without the UTF-8 flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZipNameEncoding {
+    // Guess from the names in the archive
+    #[default]
+    Auto,
+    Utf8,
+    // Chinese Windows (code page 936)
+    Gbk,
+    // Japanese Windows (code page 932)
+    ShiftJis,
+    // The ZIP specification's default, used by DOS and English Windows
+    Cp437,
+}
+
+impl ZipNameEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '_'], "").as_str() {
+            "auto" => Some(ZipNameEncoding::Auto),
+            "utf8" => Some(ZipNameEncoding::Utf8),
+            "gbk" | "gb2312" | "gb18030" | "cp936" => Some(ZipNameEncoding::Gbk),
+            "shiftjis" | "sjis" | "cp932" => Some(ZipNameEncoding::ShiftJis),
+            "cp437" | "ibm437" => Some(ZipNameEncoding::Cp437),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ZipNameEncoding::Auto => "auto",
+            ZipNameEncoding::Utf8 => "utf8",
+            ZipNameEncoding::Gbk => "gbk",
+            ZipNameEncoding::ShiftJis => "shift_jis",
+            ZipNameEncoding::Cp437 => "cp437",
+        }
+    }
+
+    fn encoding(self) -> Option<&'static Encoding> {
+        match self {
+            ZipNameEncoding::Gbk => Some(GBK),
+            ZipNameEncoding::ShiftJis => Some(SHIFT_JIS),
+            _ => None,
+        }
+    }
+
+    // Guess the code page shared by the archive's legacy (non UTF-8 flagged) names.
+    // Many tools write UTF-8 without setting the flag, so valid UTF-8 wins first;
+    // otherwise the East Asian code page whose decoding looks most like real text,
+    // falling back to CP437 when none decodes cleanly
+    pub fn detect(names: &[Vec<u8>]) -> Self {
+        let names: Vec<&[u8]> = names
+            .iter()
+            .map(Vec::as_slice)
+            .filter(|name| !name.is_ascii())
+            .collect();
+        if names.iter().all(|name| std::str::from_utf8(name).is_ok()) {
+            return ZipNameEncoding::Utf8;
+        }
+
+        // On a tie the last candidate wins, so GBK is preferred
+        [ZipNameEncoding::ShiftJis, ZipNameEncoding::Gbk]
+            .into_iter()
+            .filter_map(|candidate| Some((candidate, candidate.score(&names)?)))
+            .max_by_key(|(_, score)| *score)
+            .map(|(candidate, _)| candidate)
+            .unwrap_or(ZipNameEncoding::Cp437)
+    }
+
+    // How plausible the names look in this code page, None if any name is not valid in it.
+    // Characters re-encoded into the common rows of the code page (GB2312 hanzi,
+    // kana and JIS level 1 kanji) score high, rare rows and half-width katakana score low
+    fn score(self, names: &[&[u8]]) -> Option<i64> {
+        let encoding = self.encoding()?;
+        let mut score = 0;
+        for name in names {
+            let decoded = encoding.decode_without_bom_handling_and_without_replacement(name)?;
+            let mut buffer = [0u8; 4];
+            for c in decoded.chars().filter(|c| !c.is_ascii()) {
+                let (bytes, _, _) = encoding.encode(c.encode_utf8(&mut buffer));
+                score += match (self, bytes.len(), bytes[0]) {
+                    (ZipNameEncoding::Gbk, 2, 0xB0..=0xF7) => 2,
+                    (ZipNameEncoding::Gbk, 2, 0xA1..=0xA9) => 1,
+                    (ZipNameEncoding::ShiftJis, 2, 0x82 | 0x83 | 0x88..=0x98) => 2,
+                    (ZipNameEncoding::ShiftJis, 2, 0x81) => 1,
+                    (ZipNameEncoding::ShiftJis, 2, 0x99..=0x9F | 0xE0..=0xEA) => 0,
+                    _ => -1,
+                };
+            }
+        }
+        Some(score)
+    }
+
+    // Name of an entry; names flagged as UTF-8 (or carrying a Unicode path field) are always UTF-8
+    fn decode<R: Read>(self, file: &ZipFile<R>) -> String {
+        if has_utf8_name(file) {
+            return file.name().to_string();
+        }
+
+        match (self, self.encoding()) {
+            (_, Some(encoding)) => encoding
+                .decode_without_bom_handling(file.name_raw())
+                .0
+                .into_owned(),
+            (ZipNameEncoding::Utf8, None) => String::from_utf8_lossy(file.name_raw()).into_owned(),
+            // The zip crate decodes unflagged names as CP437
+            _ => file.name().to_string(),
+        }
+    }
+}
+
+// The zip crate decodes flagged names as UTF-8 and the rest as CP437, so a name equal to its raw
+// bytes read as UTF-8 was flagged (or is plain ASCII, where the encoding does not matter)
+fn has_utf8_name<R: Read>(file: &ZipFile<R>) -> bool {
+    std::str::from_utf8(file.name_raw()).is_ok_and(|raw| raw == file.name())
+}
+
+// Set the UTF-8 flag on every entry of a finished archive, the zip crate only sets it for
+// non-ASCII names and some extractors then fall back to the system code page
+fn set_utf8_flags<F: Read + Write + Seek>(file: &mut F) -> Result<(), String> {
+    let headers = {
+        let mut archive = ZipArchive::new(&mut *file).map_err(|e| e.to_string())?;
+        let mut headers = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+            headers.push((entry.header_start(), entry.central_header_start()));
+        }
+        headers
+    };
+
+    for (local, central) in headers {
+        set_flag(file, local + LOCAL_FLAGS_OFFSET, UTF8_FLAG)?;
+        set_flag(file, central + CENTRAL_FLAGS_OFFSET, UTF8_FLAG)?;
+    }
+    file.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn set_flag<F: Read + Write + Seek>(file: &mut F, offset: u64, flag: u16) -> Result<(), String> {
+    let mut flags = [0u8; 2];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut flags))
+        .map_err(|e| e.to_string())?;
+    let flags = u16::from_le_bytes(flags) | flag;
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(&flags.to_le_bytes()))
+        .map_err(|e| e.to_string())
+}
 
 #[derive(Clone)]
 pub struct ZipCompressor;
@@ -21,11 +190,17 @@ impl ZipCompressor {
         zip: &mut ZipWriter<W>,
         dir_path: &Path,
         options: SimpleFileOptions,
+        reproducible: Option<&Reproducible>,
     ) -> Result<(), String> {
-        for entry in std::fs::read_dir(dir_path).map_err(|e| e.to_string())? {
-            let entry = entry.map_err(|e| e.to_string())?;
-            let path = entry.path();
+        // Sort by name so the entry order does not depend on the file system
+        let mut paths = std::fs::read_dir(dir_path)
+            .map_err(|e| e.to_string())?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        paths.sort();
 
+        for path in paths {
             if path.is_file() {
                 let mut file = File::open(&path).map_err(|e| e.to_string())?;
                 let mut buffer = Vec::new();
@@ -37,11 +212,135 @@ impl ZipCompressor {
                     .to_string_lossy();
 
                 // Start a new file entry in the zip
-                zip.start_file(name, options).map_err(|e| e.to_string())?;
+                let file_options = entry_options(options, &file, reproducible)?;
+                zip.start_file(name, file_options)
+                    .map_err(|e| e.to_string())?;
                 zip.write_all(&buffer).map_err(|e| e.to_string())?;
             } else if path.is_dir() {
                 // Recursively process subdirectories
-                self.add_directory_to_zip(zip, &path, options)?;
+                self.add_directory_to_zip(zip, &path, options, reproducible)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Decompress with a journal so an interrupted extraction can pick up where it stopped
+    pub fn decompress_resumable(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+    ) -> Result<(), String> {
+        self.extract_all(input_paths, output_path, options, true)
+    }
+
+    // Extract every input archive into output_path
+    fn extract_all(
+        &self,
+        input_paths: Vec<&str>,
+        output_path: &str,
+        options: Option<DecompressionOptions>,
+        resume: bool,
+    ) -> Result<(), String> {
+        // Process password option
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+        let (password, encoding) = match options {
+            Some(DecompressionOptions::Zip {
+                password, encoding, ..
+            }) => (password, encoding),
+            _ => (None, ZipNameEncoding::Auto),
+        };
+
+        // Ensure output directory exists
+        let output_dir = Path::new(output_path);
+        if !output_dir.exists() {
+            std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
+        }
+
+        // Process all input archives
+        for input_path in input_paths {
+            let file = File::open(input_path).map_err(|e| e.to_string())?;
+            let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+
+            // If password is provided, try to set it (implementation depends on the zip library)
+            if let Some(pwd) = &password {
+                // Note: Standard zip crate doesn't support decryption
+                // This would need a different crate or implementation
+                return Err("Password handling for ZIP files not yet implemented".to_string());
+            }
+
+            // A resumable extraction keeps a journal of finished entries in the output directory
+            let mut journal = if resume {
+                Some(Journal::open(output_dir, input_path)?)
+            } else {
+                None
+            };
+
+            // Guess the code page of legacy names once per archive
+            let encoding = match encoding {
+                ZipNameEncoding::Auto => ZipNameEncoding::detect(&legacy_names(&mut archive)?),
+                encoding => encoding,
+            };
+
+            // Abort on the first entry or chunk that goes over the extraction limits,
+            // the limiter removes the files it tracked when dropped after a violation
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
+
+            // Extract each file in the archive
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+                let name = encoding.decode(&file);
+                limiter.begin_entry(&name, Some(file.size()))?;
+
+                if name.ends_with('/') {
+                    // Create directory
+                    if let Some(outpath) = conflict.dir_path(output_dir, &name)? {
+                        std::fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
+                    }
+                } else {
+                    // Names invalid on this system or clashing with another entry are renamed,
+                    // entries that would escape the output directory are skipped
+                    let Some(outpath) = conflict.entry_path(output_dir, &name)? else {
+                        continue;
+                    };
+
+                    // Skip entries finished by an interrupted run
+                    if journal.as_ref().is_some_and(|journal| journal.skip(&name)) {
+                        continue;
+                    }
+
+                    // Ensure parent directory exists
+                    if let Some(p) = outpath.parent() {
+                        if !p.exists() {
+                            std::fs::create_dir_all(p).map_err(|e| e.to_string())?;
+                        }
+                    }
+
+                    // Handle an existing file according to the conflict policy
+                    let mtime = file.last_modified().and_then(zip_datetime_secs);
+                    let Some(outpath) = conflict.resolve(&outpath, Some(file.size()), mtime)?
+                    else {
+                        continue;
+                    };
+
+                    // Extract file
+                    if let Some(journal) = journal.as_mut() {
+                        journal.begin(&name, &outpath)?;
+                    }
+                    // The declared size can lie, so count the bytes actually inflated
+                    limiter.track(&outpath);
+                    let mut outfile = File::create(&outpath).map_err(|e| e.to_string())?;
+                    std::io::copy(&mut limiter.reader(&mut file), &mut outfile)
+                        .map_err(|e| e.to_string())?;
+                    if let Some(journal) = journal.as_mut() {
+                        journal.finish(&name, &outpath)?;
+                    }
+                }
+            }
+
+            if let Some(journal) = journal {
+                journal.close()?;
             }
         }
 
@@ -50,26 +349,66 @@ impl ZipCompressor {
 }
 
 impl ComdeAble for ZipCompressor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: true,
+            multi_input: true,
+            // Password protection is not implemented yet
+            supports_password: false,
+            level: Some(LevelRange {
+                min: 0,
+                max: 9,
+                default: 6,
+            }),
+            methods: vec!["deflate", "store"],
+            compress_options: vec!["level", "reproducible"],
+            decompress_options: vec!["encoding"],
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        Some(CompressionOptions::Zip {
+            level: preset.pick([0, 1, 3, 6, 8, 9]),
+            password: None,
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
         options: Option<CompressionOptions>,
     ) -> Result<(), String> {
+        // Reproducible archives take the inputs in name order
+        let reproducible = reproducible::load(
+            options
+                .as_ref()
+                .is_some_and(|options| options.settings().reproducible),
+        )?;
+
         // Extract compression options
         let (compression_level, password) = match options {
-            Some(CompressionOptions::Zip { level, password }) => (level, password),
+            Some(CompressionOptions::Zip {
+                level, password, ..
+            }) => (level, password),
             _ => (6, None), // Default compression level is 6, no password
         };
 
-        // Create output file
-        let file = File::create(output_path).map_err(|e| e.to_string())?;
+        // Create output file, it only appears under its final name once complete
+        let file = AtomicFile::create(output_path)?;
         let mut zip = ZipWriter::new(file);
 
-        // Configure compression options
-        let mut options = FileOptions::default()
-            .compression_method(CompressionMethod::Deflated)
-            .compression_level(Some(compression_level as i64));
+        // Configure compression options, level 0 stores entries uncompressed
+        let mut options = if compression_level == 0 {
+            FileOptions::default().compression_method(CompressionMethod::Stored)
+        } else {
+            FileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .compression_level(Some(compression_level as i64))
+        };
 
         // Set password if provided (implementation depends on the zip library's capabilities)
         if let Some(pwd) = password {
@@ -78,12 +417,17 @@ impl ComdeAble for ZipCompressor {
             return Err("Password protection for ZIP files not yet implemented".to_string());
         }
 
+        let input_paths = match reproducible {
+            Some(_) => reproducible::sort_inputs(&input_paths),
+            None => input_paths,
+        };
+
         // Process all input paths
         for input_path in input_paths {
             let path = Path::new(input_path);
             if path.is_dir() {
                 // Compress directory
-                self.add_directory_to_zip(&mut zip, path, options)?;
+                self.add_directory_to_zip(&mut zip, path, options, reproducible.as_ref())?;
             } else {
                 // Compress single file
                 let file_name = path.file_name().ok_or("Invalid filename")?;
@@ -91,15 +435,17 @@ impl ComdeAble for ZipCompressor {
                 let mut buffer = Vec::new();
                 file.read_to_end(&mut buffer).map_err(|e| e.to_string())?;
 
-                zip.start_file(file_name.to_string_lossy(), options)
+                let file_options = entry_options(options, &file, reproducible.as_ref())?;
+                zip.start_file(file_name.to_string_lossy(), file_options)
                     .map_err(|e| e.to_string())?;
                 zip.write_all(&buffer).map_err(|e| e.to_string())?;
             }
         }
 
-        // Finalize the zip file
-        zip.finish().map_err(|e| e.to_string())?;
-        Ok(())
+        // Finalize the zip file, flag every name as UTF-8 and move it into place
+        let mut file = zip.finish().map_err(|e| e.to_string())?;
+        set_utf8_flags(&mut file)?;
+        file.commit()
     }
 
     async fn decompress(
@@ -108,53 +454,71 @@ impl ComdeAble for ZipCompressor {
         output_path: &str,
         options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
-        // Process password option
-        let password = match options {
-            Some(DecompressionOptions::Zip { password }) => password,
-            _ => None,
-        };
-
-        // Ensure output directory exists
-        let output_dir = Path::new(output_path);
-        if !output_dir.exists() {
-            std::fs::create_dir_all(output_dir).map_err(|e| e.to_string())?;
-        }
+        self.extract_all(input_paths, output_path, options, false)
+    }
+}
 
-        // Process all input archives
-        for input_path in input_paths {
-            let file = File::open(input_path).map_err(|e| e.to_string())?;
-            let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+// Options for one entry, reproducible archives get a clamped timestamp and a normalized mode
+fn entry_options(
+    options: SimpleFileOptions,
+    file: &File,
+    reproducible: Option<&Reproducible>,
+) -> Result<SimpleFileOptions, String> {
+    let Some(reproducible) = reproducible else {
+        return Ok(options);
+    };
 
-            // If password is provided, try to set it (implementation depends on the zip library)
-            if let Some(pwd) = &password {
-                // Note: Standard zip crate doesn't support decryption
-                // This would need a different crate or implementation
-                return Err("Password handling for ZIP files not yet implemented".to_string());
-            }
+    let metadata = file.metadata().map_err(|e| e.to_string())?;
+    Ok(options
+        .last_modified_time(utc_datetime(reproducible.mtime(&metadata))?)
+        .unix_permissions(reproducible.mode(&metadata)))
+}
 
-            // Extract each file in the archive
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
-                let outpath = output_dir.join(file.name());
+// Convert Unix seconds to a ZIP timestamp in UTC so the output does not depend on the time zone
+fn utc_datetime(secs: u64) -> Result<zip::DateTime, String> {
+    let time = i64::try_from(secs)
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| format!("Invalid timestamp: {}", secs))?
+        .naive_utc();
 
-                if file.name().ends_with('/') {
-                    // Create directory
-                    std::fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
-                } else {
-                    // Ensure parent directory exists
-                    if let Some(p) = outpath.parent() {
-                        if !p.exists() {
-                            std::fs::create_dir_all(p).map_err(|e| e.to_string())?;
-                        }
-                    }
+    // ZIP timestamps start at 1980
+    if time.year() < 1980 {
+        return Ok(zip::DateTime::default());
+    }
+    zip::DateTime::from_date_and_time(
+        time.year() as u16,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .map_err(|_| format!("Timestamp out of ZIP range: {}", secs))
+}
 
-                    // Extract file
-                    let mut outfile = File::create(&outpath).map_err(|e| e.to_string())?;
-                    std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-                }
-            }
+// Raw names of the entries not flagged as UTF-8
+fn legacy_names(archive: &mut ZipArchive<File>) -> Result<Vec<Vec<u8>>, String> {
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(|e| e.to_string())?;
+        if !has_utf8_name(&file) {
+            names.push(file.name_raw().to_vec());
         }
-
-        Ok(())
     }
+    Ok(names)
+}
+
+// Convert a ZIP timestamp (local time, 2-second precision) to Unix seconds
+fn zip_datetime_secs(time: zip::DateTime) -> Option<u64> {
+    let local =
+        NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+            .and_hms_opt(
+                time.hour() as u32,
+                time.minute() as u32,
+                time.second() as u32,
+            )?
+            .and_local_timezone(Local)
+            .earliest()?;
+    u64::try_from(local.timestamp()).ok()
 }
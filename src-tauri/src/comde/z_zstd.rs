@@ -1,9 +1,16 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::Path;
-use zstd::stream::{decode_all, encode_all};
+use std::path::{Path, PathBuf};
+use zstd::stream::{Decoder, Encoder};
+use zstd::zstd_safe::{get_dict_id_from_dict, get_dict_id_from_frame};
 
-use super::{ComdeAble, CompressionOptions, DecompressionOptions};
+use super::atomic::AtomicFile;
+use super::conflict::{modified_secs, ConflictResolver};
+use super::limits::Limiter;
+use super::{
+    Capabilities, ComdeAble, CompressionOptions, CompressionPreset, DecompressionOptions,
+    LevelRange,
+};
 
 #[derive(Clone)]
 pub struct ZstdCompressor;
@@ -12,9 +19,137 @@ impl ZstdCompressor {
     pub fn new() -> Self {
         ZstdCompressor
     }
+
+    // 从样本文件训练字典，返回字典ID
+    pub fn train_dictionary(
+        &self,
+        sample_paths: Vec<&str>,
+        output_path: &str,
+        max_size: usize,
+    ) -> Result<u32, String> {
+        // 收集所有样本文件（目录会被递归展开）
+        let mut samples = Vec::new();
+        for sample_path in sample_paths {
+            self.collect_samples(Path::new(sample_path), &mut samples)?;
+        }
+
+        if samples.is_empty() {
+            return Err("没有找到可用于训练字典的样本文件".to_string());
+        }
+
+        // 训练字典
+        let dictionary = zstd::dict::from_files(&samples, max_size)
+            .map_err(|e| format!("训练字典失败: {}", e))?;
+
+        let dict_id = get_dict_id_from_dict(&dictionary)
+            .map(|id| id.get())
+            .ok_or("训练得到的字典缺少字典ID")?;
+
+        fs::write(output_path, &dictionary).map_err(|e| e.to_string())?;
+
+        Ok(dict_id)
+    }
+
+    // 递归收集样本文件
+    fn collect_samples(&self, path: &Path, samples: &mut Vec<PathBuf>) -> Result<(), String> {
+        if path.is_dir() {
+            for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
+                let entry = entry.map_err(|e| e.to_string())?;
+                self.collect_samples(&entry.path(), samples)?;
+            }
+        } else if path.is_file() {
+            samples.push(path.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    // 创建ZSTD编码器，使用字典时将字典ID写入帧头
+    pub fn encoder<W: Write>(
+        &self,
+        writer: W,
+        level: i32,
+        dictionary: Option<&str>,
+        long_distance_matching: bool,
+        window_log: Option<u32>,
+        checksum: bool,
+    ) -> Result<Encoder<'static, W>, String> {
+        // 加载字典
+        let dictionary = match dictionary {
+            Some(dictionary_path) => self.load_dictionary(dictionary_path)?,
+            None => Vec::new(),
+        };
+
+        // ZSTD压缩级别范围由库决定 (负数为快速级别，越大压缩率越高，越慢)
+        let level_range = zstd::compression_level_range();
+        let level = match level {
+            0 => zstd::DEFAULT_COMPRESSION_LEVEL, // 0表示默认压缩级别
+            level => level.clamp(*level_range.start(), *level_range.end()),
+        };
+
+        let mut encoder =
+            Encoder::with_dictionary(writer, level, &dictionary).map_err(|e| e.to_string())?;
+        encoder.include_dictid(true).map_err(|e| e.to_string())?;
+        encoder
+            .include_checksum(checksum)
+            .map_err(|e| e.to_string())?;
+        encoder
+            .long_distance_matching(long_distance_matching)
+            .map_err(|e| e.to_string())?;
+        if let Some(window_log) = window_log {
+            encoder
+                .window_log(window_log)
+                .map_err(|e| format!("无效的窗口大小 {}: {}", window_log, e))?;
+        }
+
+        Ok(encoder)
+    }
+
+    // 读取字典文件
+    fn load_dictionary(&self, dictionary_path: &str) -> Result<Vec<u8>, String> {
+        fs::read(dictionary_path).map_err(|e| format!("无法读取字典 {}: {}", dictionary_path, e))
+    }
 }
 
 impl ComdeAble for ZstdCompressor {
+    fn capabilities(&self) -> Capabilities {
+        // 级别范围取自当前链接的zstd库，包括全部负数快速级别
+        let level_range = zstd::compression_level_range();
+        Capabilities {
+            can_compress: true,
+            can_decompress: true,
+            is_archive: false,
+            multi_input: false,
+            supports_password: false,
+            level: Some(LevelRange {
+                min: *level_range.start(),
+                max: *level_range.end(),
+                default: 3,
+            }),
+            methods: vec!["zstd"],
+            compress_options: vec![
+                "level",
+                "dictionary",
+                "long_distance_matching",
+                "window_log",
+                "checksum",
+            ],
+            decompress_options: vec!["dictionary", "window_log_max"],
+        }
+    }
+
+    fn preset_options(&self, preset: CompressionPreset) -> Option<CompressionOptions> {
+        // ZSTD没有不压缩的模式，存储预设使用最快的负数级别
+        Some(CompressionOptions::Zstd {
+            level: preset.pick([-7, -1, 1, 3, 12, 19]),
+            dictionary: None,
+            long_distance_matching: preset == CompressionPreset::Ultra,
+            window_log: None,
+            checksum: false,
+            settings: Default::default(),
+        })
+    }
+
     async fn compress(
         &self,
         input_paths: Vec<&str>,
@@ -22,10 +157,24 @@ impl ComdeAble for ZstdCompressor {
         options: Option<CompressionOptions>,
     ) -> Result<(), String> {
         // 处理压缩选项
-        let compression_level = match options {
-            Some(CompressionOptions::Tar { level, .. }) => level,
-            _ => 3, // 默认压缩级别为3
-        };
+        let (compression_level, dictionary, long_distance_matching, window_log, checksum) =
+            match options {
+                Some(CompressionOptions::Zstd {
+                    level,
+                    dictionary,
+                    long_distance_matching,
+                    window_log,
+                    checksum,
+                    ..
+                }) => (
+                    level,
+                    dictionary,
+                    long_distance_matching,
+                    window_log,
+                    checksum,
+                ),
+                _ => (3, None, false, None, false), // 默认压缩级别为3，不使用字典
+            };
 
         // ZSTD只能压缩单个文件，不能打包多个文件
         if input_paths.len() > 1 {
@@ -47,35 +196,50 @@ impl ComdeAble for ZstdCompressor {
             .map_err(|e| e.to_string())?;
 
         // 创建输出文件
-        let mut output_file = File::create(output_path).map_err(|e| e.to_string())?;
-
-        // ZSTD压缩级别范围是-7到22 (越大压缩率越高，越慢)
-        let level = compression_level as i32;
-        let compression_level = match level {
-            i32::MIN..=-1 => -1, // 最低压缩级别(最快)
-            0..=3 => 3,          // 默认压缩级别
-            4..=21 => level,     // 常规压缩级别
-            _ => 22,             // 最高压缩级别(最慢)
-        };
+        let output_file = AtomicFile::create(output_path)?;
 
         // 压缩数据
-        let compressed = encode_all(&buffer[..], compression_level).map_err(|e| e.to_string())?;
-        output_file
-            .write_all(&compressed)
-            .map_err(|e| e.to_string())?;
-
-        Ok(())
+        let mut encoder = self.encoder(
+            output_file,
+            compression_level,
+            dictionary.as_deref(),
+            long_distance_matching,
+            window_log,
+            checksum,
+        )?;
+        encoder.write_all(&buffer).map_err(|e| e.to_string())?;
+        encoder.finish().map_err(|e| e.to_string())?.commit()
     }
 
     async fn decompress(
         &self,
         input_paths: Vec<&str>,
         output_path: &str,
-        _options: Option<DecompressionOptions>,
+        options: Option<DecompressionOptions>,
     ) -> Result<(), String> {
+        let mut conflict = ConflictResolver::from_options(options.as_ref());
+
+        // 处理解压选项
+        let (dictionary, window_log_max) = match options {
+            Some(DecompressionOptions::Zstd {
+                dictionary,
+                window_log_max,
+                ..
+            }) => (dictionary, window_log_max),
+            _ => (None, None),
+        };
+
+        // 加载字典
+        let dictionary = match dictionary {
+            Some(dictionary_path) => Some(self.load_dictionary(&dictionary_path)?),
+            None => None,
+        };
+
         // ZSTD只能处理单个文件
         for input_path in input_paths {
             let mut input_file = File::open(input_path).map_err(|e| e.to_string())?;
+            // 超出解压限制时读取立即失败，此时还未创建输出文件
+            let limiter = Limiter::new(input_path, conflict.settings().limits);
 
             // 获取输出文件名
             let output_file_path = if Path::new(output_path).is_dir() {
@@ -102,10 +266,52 @@ impl ComdeAble for ZstdCompressor {
                 .read_to_end(&mut buffer)
                 .map_err(|e| e.to_string())?;
 
-            // 解压数据
-            let decompressed = decode_all(&buffer[..]).map_err(|e| e.to_string())?;
+            // 检查帧头中的字典ID是否与提供的字典匹配
+            let frame_dict_id = get_dict_id_from_frame(&buffer).map(|id| id.get());
+            let dictionary = match (frame_dict_id, &dictionary) {
+                (Some(id), None) => {
+                    return Err(format!("{} 需要ID为 {} 的字典才能解压", input_path, id));
+                }
+                (Some(id), Some(dict)) => {
+                    let dict_id = get_dict_id_from_dict(dict).map(|id| id.get());
+                    if dict_id != Some(id) {
+                        return Err(format!(
+                            "字典不匹配: {} 需要ID为 {} 的字典，提供的字典ID为 {}",
+                            input_path,
+                            id,
+                            dict_id.unwrap_or(0)
+                        ));
+                    }
+                    dict.as_slice()
+                }
+                (None, Some(dict)) => dict.as_slice(),
+                (None, None) => &[],
+            };
+
+            // 解压数据，解码器会依次解压所有拼接的帧
+            let mut decoder =
+                Decoder::with_dictionary(&buffer[..], dictionary).map_err(|e| e.to_string())?;
+            if let Some(window_log_max) = window_log_max {
+                decoder
+                    .window_log_max(window_log_max)
+                    .map_err(|e| format!("无效的最大窗口大小 {}: {}", window_log_max, e))?;
+            }
+            let mut decompressed = Vec::new();
+            limiter
+                .reader(&mut decoder)
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
 
             // 写入解压后的数据
+            // 目标文件已存在时按冲突策略处理
+            let Some(output_file_path) = conflict.resolve(
+                &output_file_path,
+                Some(decompressed.len() as u64),
+                modified_secs(Path::new(input_path)),
+            )?
+            else {
+                continue;
+            };
             let mut output_file = File::create(&output_file_path).map_err(|e| e.to_string())?;
             output_file
                 .write_all(&decompressed)
@@ -115,3 +321,23 @@ impl ComdeAble for ZstdCompressor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comde::conflict::ConflictPolicy;
+    use crate::comde::testing::check_concatenated;
+
+    // 多个ZSTD帧 (如 cat a.zst b.zst) 应全部解压
+    #[tokio::test]
+    async fn decompresses_concatenated_frames() {
+        let encode = |part: &[u8]| zstd::encode_all(part, 3).unwrap();
+        let options = DecompressionOptions::Zstd {
+            dictionary: None,
+            window_log_max: None,
+            conflict: ConflictPolicy::Rename,
+            settings: Default::default(),
+        };
+        check_concatenated(&ZstdCompressor::new(), "zst", encode, options).await;
+    }
+}
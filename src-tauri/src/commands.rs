@@ -1,9 +1,23 @@
 // Imports for file and path handling
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
 
 // Imports from local modules
-use crate::comde::{CompressionFormat, CompressionOptions, Compressor, DecompressionOptions};
+use crate::comde::bench::{self, BenchGoal, BenchReport};
+use crate::comde::conflict::{self, ConflictAnswer, ConflictInfo, ConflictPolicy};
+use crate::comde::limits::ExtractLimits;
+use crate::comde::posix::TarMetadata;
+use crate::comde::preflight::{self, SpaceCheck};
+use crate::comde::sanitize::{self, RenameLog};
+use crate::comde::{
+    CompressSettings, CompressionFormat, CompressionOptions, CompressionPreset, Compressor,
+    DecompressionOptions, ExtractSettings, TarFormat, XzCheck, XzFilter, ZipNameEncoding,
+};
 
 // 新增导入用于Windows注册表操作
 #[cfg(windows)]
@@ -12,30 +26,99 @@ use std::process::Command;
 // Format option struct for frontend-backend communication
 #[derive(Debug, Serialize)]
 pub struct FormatOption {
-    pub id: String,                 // Unique identifier for format
-    pub name: String,               // Display name
-    pub extension: String,          // File extension
-    pub can_compress: bool,         // Whether format supports compression
-    pub can_decompress: bool,       // Whether format supports decompression
-    pub supports_password: bool,    // Whether format supports password protection
-    pub supports_level: bool,       // Whether format supports compression levels
-    pub min_level: Option<u32>,     // Minimum compression level if applicable
-    pub max_level: Option<u32>,     // Maximum compression level if applicable
-    pub default_level: Option<u32>, // Default compression level if applicable
+    pub id: String,                      // Unique identifier for format
+    pub name: String,                    // Display name
+    pub extension: String,               // File extension
+    pub can_compress: bool,              // Whether format supports compression
+    pub can_decompress: bool,            // Whether format supports decompression
+    pub is_archive: bool,                // Whether format holds many entries (vs a single stream)
+    pub multi_input: bool,               // Whether several inputs can go into one output
+    pub supports_password: bool,         // Whether format supports password protection
+    pub supports_level: bool,            // Whether format supports compression levels
+    pub min_level: Option<i32>,          // Minimum compression level if applicable
+    pub max_level: Option<i32>,          // Maximum compression level if applicable
+    pub default_level: Option<i32>,      // Default compression level if applicable
+    pub methods: Vec<String>,            // Compression methods used when writing
+    pub compress_options: Vec<String>,   // Compression options the format honors
+    pub decompress_options: Vec<String>, // Decompression options the format honors
 }
 
 // Compression options passed from frontend
 #[derive(Debug, Deserialize)]
 pub struct CompressOptions {
     pub format: String,
-    pub level: Option<u32>,
+    pub preset: Option<String>,
+    pub level: Option<i32>,
     pub password: Option<String>,
+    pub dictionary: Option<String>,
+    pub long_distance_matching: Option<bool>,
+    pub window_log: Option<u32>,
+    pub checksum: Option<bool>,
+    pub extreme: Option<bool>,
+    pub filters: Option<Vec<String>>,
+    pub dict_size: Option<u32>,
+    pub check: Option<String>,
+    pub comment: Option<String>,
+    pub window_size: Option<u32>,
+    pub tar_metadata: Option<TarMetadata>, // Owners, xattrs and ACLs recorded by tar formats
+    pub reproducible: Option<bool>, // Sorted entries, fixed owners and modes, mtimes clamped to SOURCE_DATE_EPOCH
+    pub tar_format: Option<TarFormat>, // Header format of new tar archives, PAX when omitted
 }
 
 // Decompression options passed from frontend
 #[derive(Debug, Deserialize)]
 pub struct DecompressOptions {
     pub password: Option<String>,
+    pub dictionary: Option<String>,
+    pub window_log_max: Option<u32>,
+    pub restore_name: Option<bool>,
+    pub conflict: Option<ConflictPolicy>,
+    pub staged: Option<bool>, // Extract into a staging directory and move entries in on success
+    pub limits: Option<ExtractLimits>, // Decompression-bomb limits, the defaults apply when omitted
+    pub encoding: Option<ZipNameEncoding>, // Code page of legacy ZIP names, guessed when omitted
+    pub portable_names: Option<bool>, // Apply Windows and macOS name rules on every platform
+    pub tar_metadata: Option<TarMetadata>, // Owners, modes, mtimes, xattrs and ACLs restored by tar formats
+}
+
+// Payload of the "extract-conflict" event asking the frontend how to handle an existing file
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictPrompt {
+    pub id: u64,
+    #[serde(flatten)]
+    pub info: ConflictInfo,
+}
+
+// Conflict prompts waiting for an answer from the frontend, keyed by prompt id
+static PENDING_CONFLICTS: OnceLock<Mutex<HashMap<u64, Sender<ConflictAnswer>>>> = OnceLock::new();
+static NEXT_CONFLICT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn pending_conflicts() -> &'static Mutex<HashMap<u64, Sender<ConflictAnswer>>> {
+    PENDING_CONFLICTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Route the "ask" conflict policy to the frontend
+///
+/// Each conflict emits an "extract-conflict" event and blocks the extraction
+/// until the frontend answers it through `resolve_conflict`.
+pub fn install_conflict_prompt(app: AppHandle) {
+    conflict::set_ask_handler(Box::new(move |info| {
+        let id = NEXT_CONFLICT_ID.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        pending_conflicts().lock().unwrap().insert(id, sender);
+
+        let prompt = ConflictPrompt {
+            id,
+            info: info.clone(),
+        };
+        if let Err(e) = app.emit("extract-conflict", prompt) {
+            pending_conflicts().lock().unwrap().remove(&id);
+            return Err(format!("Failed to ask how to handle {}: {}", info.path, e));
+        }
+
+        receiver
+            .recv()
+            .map_err(|_| "Extraction cancelled".to_string())
+    }));
 }
 
 // Application version information
@@ -97,34 +180,105 @@ pub async fn compress_files(
 
     // Create compressor instance
     let compressor = Compressor::new(format);
+    let settings = CompressSettings {
+        tar_metadata: options.tar_metadata.clone().unwrap_or_default(),
+        reproducible: options.reproducible.unwrap_or(false),
+        tar_format: options.tar_format.unwrap_or_default(),
+    };
 
-    // Create compression options based on format
-    let compress_options = match format {
-        CompressionFormat::Zip => Some(CompressionOptions::Zip {
-            level: options.level.unwrap_or(6),
-            password: options.password,
-        }),
-        CompressionFormat::Tar => Some(CompressionOptions::Tar {
-            level: options.level.unwrap_or(6),
-        }),
-        CompressionFormat::Gzip => Some(CompressionOptions::Gzip {
-            level: options.level.unwrap_or(6),
-        }),
-        CompressionFormat::Bzip2 => Some(CompressionOptions::Bzip2 {
-            level: options.level.unwrap_or(6),
-        }),
-        CompressionFormat::Xz => Some(CompressionOptions::Xz {
-            level: options.level.unwrap_or(6),
-        }),
-        CompressionFormat::Zstd => Some(CompressionOptions::Zstd {
-            level: options.level.unwrap_or(3),
-        }),
-        CompressionFormat::SevenZip => Some(CompressionOptions::SevenZip {
-            level: options.level.unwrap_or(6),
-            password: options.password,
-        }),
+    // Reject requests the format cannot honor
+    let capabilities = compressor.capabilities();
+    if !capabilities.can_compress {
+        return Err(format!("{} is a read-only format", format.name()));
+    }
+    if options.password.is_some() && !capabilities.supports_password {
+        return Err(format!(
+            "{} does not support password protection",
+            format.name()
+        ));
+    }
+    if input_paths.len() > 1 && !capabilities.multi_input {
+        return Err(format!(
+            "{} can only compress a single input",
+            format.name()
+        ));
+    }
+
+    // Start from the preset's native options, then apply explicit settings
+    let preset = match &options.preset {
+        Some(name) => match CompressionPreset::from_name(name) {
+            Some(preset) => preset,
+            None => return Err(format!("Unsupported preset: {}", name)),
+        },
+        None => CompressionPreset::Normal,
+    };
+    let mut compress_options = match compressor.preset_options(preset) {
+        Some(compress_options) => compress_options,
+        None => return Err(format!("{} is a read-only format", format.name())),
     };
 
+    if let Some(level) = options.level {
+        compress_options.set_level(level);
+    }
+    compress_options.set_settings(settings);
+
+    match &mut compress_options {
+        CompressionOptions::Zip { password, .. }
+        | CompressionOptions::SevenZip { password, .. }
+        | CompressionOptions::Plugin { password, .. } => {
+            *password = options.password;
+        }
+        CompressionOptions::Gzip { comment, .. } => {
+            *comment = options.comment;
+        }
+        CompressionOptions::Xz {
+            extreme,
+            filters,
+            dict_size,
+            check,
+            ..
+        } => {
+            for name in options.filters.unwrap_or_default() {
+                filters.push(XzFilter::parse(&name)?);
+            }
+
+            if let Some(name) = options.check {
+                match XzCheck::from_name(&name) {
+                    Some(value) => *check = value,
+                    None => return Err(format!("Unsupported XZ check type: {}", name)),
+                }
+            }
+
+            *extreme = options.extreme.unwrap_or(*extreme);
+            *dict_size = options.dict_size.or(*dict_size);
+        }
+        CompressionOptions::Zstd {
+            dictionary,
+            long_distance_matching,
+            window_log,
+            checksum,
+            ..
+        } => {
+            *dictionary = options.dictionary;
+            *long_distance_matching = options
+                .long_distance_matching
+                .unwrap_or(*long_distance_matching);
+            *window_log = options.window_log.or(*window_log);
+            *checksum = options.checksum.unwrap_or(*checksum);
+        }
+        CompressionOptions::Lz4 { checksum, .. } => {
+            *checksum = options.checksum.unwrap_or(*checksum);
+        }
+        CompressionOptions::Brotli { window_size, .. } => {
+            *window_size = options.window_size.unwrap_or(*window_size);
+        }
+        CompressionOptions::Tar { .. }
+        | CompressionOptions::Bzip2 { .. }
+        | CompressionOptions::Lzma { .. }
+        | CompressionOptions::Lzip { .. } => {}
+    }
+    let compress_options = Some(compress_options);
+
     // Convert string paths to &str references
     let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
 
@@ -168,12 +322,6 @@ pub async fn decompress_files(
         }
     }
 
-    // Create output directory if it doesn't exist
-    if !std::path::Path::new(&output_path).exists() {
-        std::fs::create_dir_all(&output_path)
-            .map_err(|e| format!("Failed to create output directory: {}", e))?;
-    }
-
     // Parse or auto-detect format
     let format = if !format.is_empty() {
         match CompressionFormat::from_extension(&format) {
@@ -181,64 +329,207 @@ pub async fn decompress_files(
             None => return Err(format!("Unsupported format: {}", format)),
         }
     } else {
-        // Try to auto-detect format from file extension
+        // Try to auto-detect format from file extension or leading bytes
         if let Some(path) = input_paths.first() {
-            let path = PathBuf::from(path);
-            if let Some(ext) = path.extension() {
-                if let Some(ext_str) = ext.to_str() {
-                    if let Some(detected_format) = CompressionFormat::from_extension(ext_str) {
-                        detected_format
-                    } else {
-                        return Err(format!("Cannot detect format from extension: {}", ext_str));
-                    }
-                } else {
-                    return Err("Invalid file extension".to_string());
-                }
-            } else {
-                return Err("File has no extension".to_string());
+            match CompressionFormat::detect(&PathBuf::from(path)) {
+                Some(detected_format) => detected_format,
+                None => return Err(format!("Cannot detect format of: {}", path)),
             }
         } else {
             return Err("No input file provided".to_string());
         }
     };
 
+    // Refuse before writing anything when the archive certainly will not fit
+    let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
+    let check = preflight::check_decompress(format, &input_paths_refs, Path::new(&output_path))?;
+    if check.must_refuse() {
+        return Err(format!(
+            "Not enough disk space: {} needed, {} available",
+            bench::format_bytes(check.required),
+            bench::format_bytes(check.available)
+        ));
+    }
+
+    // Create output directory if it doesn't exist
+    if !Path::new(&output_path).exists() {
+        std::fs::create_dir_all(&output_path)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
     // Create decompressor instance
     let decompressor = Compressor::new(format);
 
+    // Existing files are overwritten unless the frontend picks another policy
+    let conflict = options
+        .as_ref()
+        .and_then(|opts| opts.conflict)
+        .unwrap_or_default();
+    let staged = options
+        .as_ref()
+        .and_then(|opts| opts.staged)
+        .unwrap_or(false);
+    let renames = RenameLog::default();
+    let settings = ExtractSettings {
+        limits: options
+            .as_ref()
+            .and_then(|opts| opts.limits)
+            .unwrap_or_default(),
+        portable_names: options
+            .as_ref()
+            .and_then(|opts| opts.portable_names)
+            .unwrap_or(false),
+        tar_metadata: options
+            .as_ref()
+            .and_then(|opts| opts.tar_metadata.clone())
+            .unwrap_or_default(),
+        renames: renames.clone(),
+    };
+
     // Create decompression options based on format
     let decompress_options = match format {
         CompressionFormat::Zip => {
             if let Some(opts) = options {
                 Some(DecompressionOptions::Zip {
                     password: opts.password,
+                    encoding: opts.encoding.unwrap_or_default(),
+                    conflict,
+                    settings,
+                })
+            } else {
+                Some(DecompressionOptions::Zip {
+                    password: None,
+                    encoding: ZipNameEncoding::Auto,
+                    conflict,
+                    settings,
+                })
+            }
+        }
+        CompressionFormat::Tar => Some(DecompressionOptions::Tar { conflict, settings }),
+        CompressionFormat::Gzip | CompressionFormat::TarGz => Some(DecompressionOptions::Gzip {
+            restore_name: options
+                .as_ref()
+                .and_then(|opts| opts.restore_name)
+                .unwrap_or(false),
+            conflict,
+            settings,
+        }),
+        CompressionFormat::Bzip2 | CompressionFormat::TarBz2 => {
+            Some(DecompressionOptions::Bzip2 { conflict, settings })
+        }
+        CompressionFormat::Xz | CompressionFormat::TarXz => {
+            Some(DecompressionOptions::Xz { conflict, settings })
+        }
+        CompressionFormat::Zstd | CompressionFormat::TarZst => {
+            if let Some(opts) = options {
+                Some(DecompressionOptions::Zstd {
+                    dictionary: opts.dictionary,
+                    window_log_max: opts.window_log_max,
+                    conflict,
+                    settings,
                 })
             } else {
-                Some(DecompressionOptions::Zip { password: None })
+                Some(DecompressionOptions::Zstd {
+                    dictionary: None,
+                    window_log_max: None,
+                    conflict,
+                    settings,
+                })
             }
         }
-        CompressionFormat::Tar => Some(DecompressionOptions::Tar {}),
-        CompressionFormat::Gzip => Some(DecompressionOptions::Gzip {}),
-        CompressionFormat::Bzip2 => Some(DecompressionOptions::Bzip2 {}),
-        CompressionFormat::Xz => Some(DecompressionOptions::Xz {}),
-        CompressionFormat::Zstd => Some(DecompressionOptions::Zstd {}),
         CompressionFormat::SevenZip => {
             if let Some(opts) = options {
                 Some(DecompressionOptions::SevenZip {
                     password: opts.password,
+                    conflict,
+                    settings,
                 })
             } else {
-                Some(DecompressionOptions::SevenZip { password: None })
+                Some(DecompressionOptions::SevenZip {
+                    password: None,
+                    conflict,
+                    settings,
+                })
             }
         }
+        CompressionFormat::Lz4 | CompressionFormat::TarLz4 => {
+            Some(DecompressionOptions::Lz4 { conflict, settings })
+        }
+        CompressionFormat::Brotli => Some(DecompressionOptions::Brotli { conflict, settings }),
+        CompressionFormat::Lzma | CompressionFormat::TarLzma => {
+            Some(DecompressionOptions::Lzma { conflict, settings })
+        }
+        CompressionFormat::Lzip | CompressionFormat::TarLzip => {
+            Some(DecompressionOptions::Lzip { conflict, settings })
+        }
+        CompressionFormat::Cpio
+        | CompressionFormat::Ar
+        | CompressionFormat::Deb
+        | CompressionFormat::Iso => Some(DecompressionOptions::Archive { conflict, settings }),
+        CompressionFormat::Plugin(_) => Some(DecompressionOptions::Plugin {
+            password: options.and_then(|opts| opts.password),
+            conflict,
+            settings,
+        }),
     };
 
-    // Convert string paths to &str references
-    let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
+    // Execute decompression on the blocking pool: extraction does blocking I/O and may
+    // wait for the frontend to answer a conflict prompt, which must not stall an async worker
+    let output_dir = output_path.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
+        tokio::runtime::Handle::current().block_on(async {
+            if staged {
+                decompressor
+                    .decompress_staged(input_paths_refs, &output_dir, decompress_options)
+                    .await
+            } else {
+                decompressor
+                    .decompress(input_paths_refs, &output_dir, decompress_options)
+                    .await
+            }
+        })
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Extraction task failed: {}", e)));
 
-    // Execute decompression
-    decompressor
-        .decompress(input_paths_refs, &output_path, decompress_options)
-        .await
+    // Renamed entries are listed in a report next to the output directory, also when the
+    // extraction stopped halfway
+    let report = sanitize::write_report(Path::new(&output_path), &renames.take());
+    result.and(report.map(|_| ()))
+}
+
+/// Answer a pending "extract-conflict" prompt
+///
+/// # Arguments
+/// * `id` - The prompt id from the event payload
+/// * `choice` - How to handle the file (overwrite, skip, rename, keep_newer); None cancels the extraction
+/// * `apply_to_all` - Apply the choice to the remaining conflicts of the same extraction
+///
+/// # Returns
+/// * `Result<(), String>` - Ok if the extraction was waiting for this answer
+#[tauri::command]
+pub fn resolve_conflict(
+    id: u64,
+    choice: Option<ConflictPolicy>,
+    apply_to_all: bool,
+) -> Result<(), String> {
+    let sender = pending_conflicts()
+        .lock()
+        .unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("No pending conflict with id {}", id))?;
+
+    // Dropping the sender without an answer cancels the extraction
+    let Some(choice) = choice else {
+        return Ok(());
+    };
+    sender
+        .send(ConflictAnswer {
+            choice,
+            apply_to_all,
+        })
+        .map_err(|_| "Extraction is no longer waiting for an answer".to_string())
 }
 
 /// Get list of supported compression formats
@@ -267,38 +558,99 @@ pub fn get_format_options(format: String) -> Result<FormatOption, String> {
         None => return Err(format!("Unsupported format: {}", format)),
     };
 
-    let (
-        can_compress,
-        can_decompress,
-        supports_password,
-        supports_level,
-        min_level,
-        max_level,
-        default_level,
-    ) = match format {
-        CompressionFormat::Zip => (true, true, true, true, Some(1), Some(9), Some(6)),
-        CompressionFormat::Tar => (true, true, false, true, Some(1), Some(9), Some(6)),
-        CompressionFormat::Gzip => (true, true, false, true, Some(1), Some(9), Some(6)),
-        CompressionFormat::Bzip2 => (true, true, false, true, Some(1), Some(9), Some(6)),
-        CompressionFormat::Xz => (true, true, false, true, Some(1), Some(9), Some(6)),
-        CompressionFormat::Zstd => (true, true, false, true, Some(1), Some(22), Some(3)),
-        CompressionFormat::SevenZip => (true, true, true, true, Some(1), Some(9), Some(6)),
-    };
+    // Capabilities are declared by each compressor
+    let capabilities = Compressor::new(format).capabilities();
 
     Ok(FormatOption {
         id: format.extension().to_string(),
         name: format.name().to_string(),
         extension: format.extension().to_string(),
-        can_compress,
-        can_decompress,
-        supports_password,
-        supports_level,
-        min_level,
-        max_level,
-        default_level,
+        can_compress: capabilities.can_compress,
+        can_decompress: capabilities.can_decompress,
+        is_archive: capabilities.is_archive,
+        multi_input: capabilities.multi_input,
+        supports_password: capabilities.supports_password,
+        supports_level: capabilities.level.is_some(),
+        min_level: capabilities.level.map(|level| level.min),
+        max_level: capabilities.level.map(|level| level.max),
+        default_level: capabilities.level.map(|level| level.default),
+        methods: capabilities.methods.iter().map(|m| m.to_string()).collect(),
+        compress_options: capabilities
+            .compress_options
+            .iter()
+            .map(|o| o.to_string())
+            .collect(),
+        decompress_options: capabilities
+            .decompress_options
+            .iter()
+            .map(|o| o.to_string())
+            .collect(),
     })
 }
 
+/// Sample the inputs and trial-compress them with every format to recommend one
+///
+/// # Arguments
+/// * `input_paths` - Files or directories to analyze
+/// * `goal` - "smallest", "fastest" or "balanced" (default)
+///
+/// # Returns
+/// * `Result<BenchReport, String>` - Per-format measurements and the recommendation
+#[tauri::command]
+pub async fn analyze_inputs(
+    input_paths: Vec<String>,
+    goal: Option<String>,
+) -> Result<BenchReport, String> {
+    if input_paths.is_empty() {
+        return Err("No input files provided".to_string());
+    }
+
+    let goal = match goal {
+        Some(name) => match BenchGoal::from_name(&name) {
+            Some(goal) => goal,
+            None => return Err(format!("Unsupported goal: {}", name)),
+        },
+        None => BenchGoal::Balanced,
+    };
+
+    let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
+    bench::analyze(
+        &input_paths_refs,
+        &CompressionFormat::all_formats(),
+        goal,
+        bench::DEFAULT_SAMPLE_SIZE,
+    )
+    .await
+}
+
+/// Estimate the output size and compare it with the free space on the target filesystem
+///
+/// # Arguments
+/// * `input_paths` - Files to compress, or archives to extract
+/// * `output_path` - The output archive, or the output directory when extracting
+/// * `extract` - Whether the inputs are archives to extract
+///
+/// # Returns
+/// * `Result<SpaceCheck, String>` - Required and available bytes and where the estimate came from
+#[tauri::command]
+pub fn check_disk_space(
+    input_paths: Vec<String>,
+    output_path: String,
+    extract: bool,
+) -> Result<SpaceCheck, String> {
+    let input_paths_refs: Vec<&str> = input_paths.iter().map(|s| s.as_str()).collect();
+    if !extract {
+        return preflight::check_compress(&input_paths_refs, Path::new(&output_path));
+    }
+
+    let first = input_paths_refs
+        .first()
+        .ok_or_else(|| "No input archives provided".to_string())?;
+    let format = CompressionFormat::detect(Path::new(first))
+        .ok_or_else(|| format!("Cannot detect format of: {}", first))?;
+    preflight::check_decompress(format, &input_paths_refs, Path::new(&output_path))
+}
+
 /// Get application version information
 ///
 /// # Returns
@@ -520,6 +872,22 @@ pub fn set_file_association(formats: String, open_mode: String) -> Result<(), St
             CompressionFormat::Xz => "XZ 压缩文件",
             CompressionFormat::Zstd => "Zstandard 压缩文件",
             CompressionFormat::SevenZip => "7-Zip 压缩文件",
+            CompressionFormat::Lz4 => "LZ4 压缩文件",
+            CompressionFormat::TarLz4 => "TAR.LZ4 归档文件",
+            CompressionFormat::Brotli => "Brotli 压缩文件",
+            CompressionFormat::Lzma => "LZMA 压缩文件",
+            CompressionFormat::Lzip => "LZIP 压缩文件",
+            CompressionFormat::TarLzma => "TAR.LZMA 归档文件",
+            CompressionFormat::TarLzip => "TAR.LZ 归档文件",
+            CompressionFormat::TarGz => "TAR.GZ 归档文件",
+            CompressionFormat::TarBz2 => "TAR.BZ2 归档文件",
+            CompressionFormat::TarXz => "TAR.XZ 归档文件",
+            CompressionFormat::TarZst => "TAR.ZST 归档文件",
+            CompressionFormat::Cpio => "CPIO 归档文件",
+            CompressionFormat::Ar => "AR 归档文件",
+            CompressionFormat::Deb => "Debian 软件包",
+            CompressionFormat::Iso => "ISO 光盘镜像",
+            CompressionFormat::Plugin(_) => format.name(),
         };
 
         // 注册文件类型
@@ -563,14 +931,36 @@ pub fn set_file_association(formats: String, open_mode: String) -> Result<(), St
             println!("TODO: 实现文件查看器模式");
             format!("\"{}\" view \"%1\"", exe_path_str)
         } else {
-            // GUI模式
-            format!("\"{}\" d \"%1\"", exe_path_str)
+            // GUI模式，智能解压到归档所在目录
+            format!("\"{}\" d --smart \"%1\"", exe_path_str)
         };
 
         command_key
             .0
             .set_value("", &command_str)
             .map_err(|e| format!("Failed to set command: {}", e))?;
+
+        // 右键菜单: 智能解压到此处
+        let extract_key = app_key
+            .0
+            .create_subkey("shell\\ZipHereExtract")
+            .map_err(|e| format!("Failed to create extract menu key: {}", e))?;
+        extract_key
+            .0
+            .set_value("", &"使用 ZipHere 解压到此处")
+            .map_err(|e| format!("Failed to set menu text: {}", e))?;
+        extract_key
+            .0
+            .set_value("Icon", &format!("{},0", exe_path_str))
+            .map_err(|e| format!("Failed to set menu icon: {}", e))?;
+        let extract_command_key = extract_key
+            .0
+            .create_subkey("command")
+            .map_err(|e| format!("Failed to create command key: {}", e))?;
+        extract_command_key
+            .0
+            .set_value("", &format!("\"{}\" d --smart \"%1\"", exe_path_str))
+            .map_err(|e| format!("Failed to set command: {}", e))?;
     }
 
     // 刷新Windows资源管理器
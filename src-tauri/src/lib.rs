@@ -6,7 +6,8 @@ pub mod comde;
 pub mod commands;
 
 use commands::{
-    compress_files, decompress_files, get_format_options, get_supported_formats, get_version_info,
+    analyze_inputs, check_disk_space, compress_files, decompress_files, get_format_options,
+    get_supported_formats, get_version_info, install_conflict_prompt, resolve_conflict,
 };
 
 // 添加 Windows 集成相关的命令
@@ -21,12 +22,20 @@ pub fn run() {
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            // 解压冲突的"询问"模式通过事件交给前端处理
+            install_conflict_prompt(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             compress_files,
             decompress_files,
             get_supported_formats,
             get_format_options,
             get_version_info,
+            analyze_inputs,
+            resolve_conflict,
+            check_disk_space,
             // 添加 Windows 集成相关的命令
             #[cfg(windows)]
             add_context_menu,
@@ -63,6 +63,16 @@ const en: I18nKeyType = {
     "decompressSuccess": "Files extracted successfully!",
     "decompressError": "Extraction failed: {0}",
     "pleaseSelectArchive": "Please select an archive to extract",
+    "conflictPolicy": "When a file already exists:",
+    "conflictOverwrite": "Overwrite",
+    "conflictSkip": "Skip",
+    "conflictRename": "Rename",
+    "conflictKeepNewer": "Keep newer",
+    "conflictAsk": "Ask",
+    "conflictPromptTitle": "File already exists",
+    "conflictPromptMessage": "{0} already exists. What should be done with it?",
+    "conflictApplyToAll": "Apply to all remaining conflicts",
+    "conflictCancel": "Cancel extraction",
     
     // Options - General
     "generalSettings": "General Settings",
@@ -61,6 +61,16 @@ type langKeys =
     "decompressSuccess" |
     "decompressError" |
     "pleaseSelectArchive" |
+    "conflictPolicy" |
+    "conflictOverwrite" |
+    "conflictSkip" |
+    "conflictRename" |
+    "conflictKeepNewer" |
+    "conflictAsk" |
+    "conflictPromptTitle" |
+    "conflictPromptMessage" |
+    "conflictApplyToAll" |
+    "conflictCancel" |
     
     // Options - General
     "generalSettings" |
@@ -63,6 +63,16 @@ const zhCN: I18nKeyType = {
     "decompressSuccess": "文件解压成功!",
     "decompressError": "解压失败: {0}",
     "pleaseSelectArchive": "请选择要解压的文件",
+    "conflictPolicy": "文件已存在时：",
+    "conflictOverwrite": "覆盖",
+    "conflictSkip": "跳过",
+    "conflictRename": "重命名",
+    "conflictKeepNewer": "保留较新",
+    "conflictAsk": "询问",
+    "conflictPromptTitle": "文件已存在",
+    "conflictPromptMessage": "{0} 已存在，要如何处理？",
+    "conflictApplyToAll": "对之后的冲突全部应用",
+    "conflictCancel": "取消解压",
     
     // Options - General
     "generalSettings": "常规设置",
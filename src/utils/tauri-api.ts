@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { open as openDialog, save as saveDialog } from '@tauri-apps/plugin-dialog';
 import { desktopDir } from '@tauri-apps/api/path';
 import { platform } from '@tauri-apps/plugin-os';
@@ -6,17 +7,102 @@ import { platform } from '@tauri-apps/plugin-os';
 /**
  * Compression options interface
  */
+export type CompressionPreset = 'store' | 'fastest' | 'fast' | 'normal' | 'high' | 'ultra';
+
 export interface CompressOptions {
   format: string;
+  preset?: CompressionPreset;
   level?: number;
   password?: string;
+  dictionary?: string;
+  long_distance_matching?: boolean;
+  window_log?: number;
+  checksum?: boolean;
+  extreme?: boolean;
+  filters?: string[];
+  dict_size?: number;
+  check?: 'none' | 'crc32' | 'crc64' | 'sha256';
+  comment?: string;
+  window_size?: number;
+  tar_metadata?: TarMetadata;
+  reproducible?: boolean;
+  tar_format?: TarFormat;
 }
 
+/**
+ * Header format of new tar archives
+ */
+export type TarFormat = 'pax' | 'gnu' | 'ustar';
+
 /**
  * Decompression options interface
  */
 export interface DecompressOptions {
   password?: string;
+  dictionary?: string;
+  window_log_max?: number;
+  restore_name?: boolean;
+  conflict?: ConflictPolicy;
+  staged?: boolean;
+  limits?: ExtractLimits;
+  encoding?: ZipNameEncoding;
+  portable_names?: boolean;
+  tar_metadata?: TarMetadata;
+}
+
+/**
+ * POSIX metadata stored in and restored from tar archives, omitted fields keep their defaults
+ */
+export interface TarMetadata {
+  same_owner?: boolean;
+  numeric_owner?: boolean;
+  owner_map?: IdMapping[];
+  group_map?: IdMapping[];
+  preserve_permissions?: boolean;
+  preserve_mtime?: boolean;
+  xattrs?: boolean;
+  acls?: boolean;
+}
+
+/**
+ * Owner or group mapping, either side may be a name or a numeric id
+ */
+export interface IdMapping {
+  from: string;
+  to: string;
+}
+
+/**
+ * Code page of ZIP entry names stored without the UTF-8 flag
+ */
+export type ZipNameEncoding = 'auto' | 'utf8' | 'gbk' | 'shift_jis' | 'cp437';
+
+/**
+ * Decompression-bomb limits, null means unlimited and omitted fields keep their defaults
+ */
+export interface ExtractLimits {
+  max_total_size?: number | null;
+  max_entry_size?: number | null;
+  max_entries?: number | null;
+  max_depth?: number | null;
+  max_ratio?: number | null;
+}
+
+/**
+ * How to handle files that already exist when extracting
+ */
+export type ConflictPolicy = 'overwrite' | 'skip' | 'rename' | 'keep_newer' | 'ask';
+
+/**
+ * Payload of the "extract-conflict" event, times are Unix seconds
+ */
+export interface ConflictPrompt {
+  id: number;
+  path: string;
+  existing_size: number;
+  existing_mtime: number | null;
+  entry_size: number | null;
+  entry_mtime: number | null;
 }
 
 /**
@@ -28,11 +114,16 @@ export interface FormatOption {
   extension: string;
   can_compress: boolean;
   can_decompress: boolean;
+  is_archive: boolean;
+  multi_input: boolean;
   supports_password: boolean;
   supports_level: boolean;
   min_level?: number;
   max_level?: number;
   default_level?: number;
+  methods: string[];
+  compress_options: string[];
+  decompress_options: string[];
 }
 
 /**
@@ -45,6 +136,34 @@ export interface VersionInfo {
   description: string;
 }
 
+export type BenchGoal = 'smallest' | 'fastest' | 'balanced';
+
+/**
+ * Result of one trial compression
+ */
+export interface BenchResult {
+  format: string;
+  preset: CompressionPreset;
+  level: number | null;
+  compressed_size: number;
+  ratio: number;
+  compress_speed: number;
+  decompress_speed: number;
+  peak_memory: number | null;
+  error: string | null;
+}
+
+/**
+ * Format recommendation report
+ */
+export interface BenchReport {
+  goal: BenchGoal;
+  sample_files: number;
+  sample_size: number;
+  results: BenchResult[];
+  recommendation: BenchResult | null;
+}
+
 /**
  * Compress files
  * @param inputPaths List of input file/directory paths
@@ -84,6 +203,30 @@ export async function decompressFiles(
   });
 }
 
+/**
+ * 监听解压时的文件冲突询问 (冲突处理方式为 ask 时)
+ * @param handler 回调，需调用 resolveConflict 回答
+ */
+export async function onExtractConflict(
+  handler: (prompt: ConflictPrompt) => void
+): Promise<UnlistenFn> {
+  return await listen<ConflictPrompt>('extract-conflict', (event) => handler(event.payload));
+}
+
+/**
+ * 回答文件冲突询问
+ * @param id 询问编号
+ * @param choice 处理方式，null 表示取消解压
+ * @param applyToAll 对之后的冲突全部应用
+ */
+export async function resolveConflict(
+  id: number,
+  choice: Exclude<ConflictPolicy, 'ask'> | null,
+  applyToAll: boolean
+): Promise<void> {
+  return await invoke<void>('resolve_conflict', { id, choice, applyToAll });
+}
+
 /**
  * 获取支持的压缩/解压缩格式
  */
@@ -106,6 +249,42 @@ export async function getVersionInfo(): Promise<VersionInfo> {
   return await invoke<VersionInfo>('get_version_info');
 }
 
+/**
+ * 对输入采样试压缩并推荐格式和级别
+ * @param inputPaths 输入文件/目录路径
+ * @param goal 推荐目标，默认 balanced
+ */
+export async function analyzeInputs(
+  inputPaths: string[],
+  goal?: BenchGoal
+): Promise<BenchReport> {
+  return await invoke<BenchReport>('analyze_inputs', { inputPaths, goal });
+}
+
+/**
+ * Disk space preflight result
+ */
+export interface SpaceCheck {
+  required: number;
+  available: number;
+  // metadata / archive_size are reliable, inputs is an upper bound
+  source: 'metadata' | 'archive_size' | 'inputs';
+}
+
+/**
+ * Estimate the output size and compare it with the free space on the target filesystem
+ * @param inputPaths Files to compress, or archives to extract
+ * @param outputPath Output archive, or output directory when extracting
+ * @param extract Whether the inputs are archives to extract
+ */
+export async function checkDiskSpace(
+  inputPaths: string[],
+  outputPath: string,
+  extract: boolean
+): Promise<SpaceCheck> {
+  return await invoke<SpaceCheck>('check_disk_space', { inputPaths, outputPath, extract });
+}
+
 /**
  * 检查是否为 Windows 系统
  */
@@ -213,11 +392,31 @@ export function getFileFilters(format: string): { name: string, extensions: stri
       return [{ name: 'ZSTD文件', extensions: ['zst', 'zstd'] }];
     case '7z':
       return [{ name: '7-Zip文件', extensions: ['7z'] }];
+    case 'lz4':
+      return [{ name: 'LZ4文件', extensions: ['lz4'] }];
+    case 'tar.lz4':
+      return [{ name: 'TAR.LZ4文件', extensions: ['lz4'] }];
+    case 'br':
+      return [{ name: 'Brotli文件', extensions: ['br'] }];
+    case 'lzma':
+    case 'tar.lzma':
+      return [{ name: 'LZMA文件', extensions: ['lzma'] }];
+    case 'lz':
+    case 'tar.lz':
+      return [{ name: 'LZIP文件', extensions: ['lz'] }];
+    case 'tar.gz':
+      return [{ name: 'TAR.GZ文件', extensions: ['tar.gz', 'tgz'] }];
+    case 'tar.bz2':
+      return [{ name: 'TAR.BZ2文件', extensions: ['tar.bz2', 'tbz2'] }];
+    case 'tar.xz':
+      return [{ name: 'TAR.XZ文件', extensions: ['tar.xz', 'txz'] }];
+    case 'tar.zst':
+      return [{ name: 'TAR.ZST文件', extensions: ['tar.zst', 'tzst'] }];
     default:
       return [
         { 
           name: '压缩文件', 
-          extensions: ['zip', 'tar', 'gz', 'bz2', 'xz', 'zst', '7z'] 
+          extensions: ['zip', 'tar', 'gz', 'bz2', 'xz', 'zst', '7z', 'lz4', 'br', 'lzma', 'lz', 'tgz', 'cpio', 'deb', 'iso'] 
         }
       ];
   }